@@ -0,0 +1,109 @@
+use std::{collections::BTreeMap, path::Path};
+
+use kira::dsp::Frame;
+
+use crate::audio_decoder::FileDecoder;
+
+/// Loudness measurements for the audio that plays during a single
+/// video frame.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct AudioFeatures {
+	/// The root mean square amplitude of the audio.
+	pub rms: f32,
+	/// The largest absolute sample value of the audio.
+	pub peak: f32,
+}
+
+/// The [`AudioFeatures`] of each stem for the current frame, keyed by
+/// stem name.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StemFeatures(pub BTreeMap<String, AudioFeatures>);
+
+impl StemFeatures {
+	pub fn get(&self, name: &str) -> Option<AudioFeatures> {
+		self.0.get(name).copied()
+	}
+}
+
+/// Per-frame [`AudioFeatures`] for a whole audio file.
+pub(crate) struct Analysis {
+	features: Vec<AudioFeatures>,
+}
+
+impl Analysis {
+	pub fn from_file(path: &Path, frame_rate: u64) -> anyhow::Result<Self> {
+		Self::from_files([path], frame_rate)
+	}
+
+	/// Analyzes several audio files as if they were played back to back.
+	pub fn from_files<'a>(
+		paths: impl IntoIterator<Item = &'a Path>,
+		frame_rate: u64,
+	) -> anyhow::Result<Self> {
+		let mut features = vec![];
+		let mut accumulator = FeatureAccumulator::default();
+		let mut audio_frame_index = 0;
+		for path in paths {
+			let mut decoder = FileDecoder::open(path)?;
+			let audio_frames_per_video_frame = decoder.sample_rate() as f64 / frame_rate as f64;
+			while let Some(frames) = decoder.decode()? {
+				for frame in frames {
+					accumulator.add(frame);
+					audio_frame_index += 1;
+					let next_video_frame_start =
+						((features.len() + 1) as f64 * audio_frames_per_video_frame) as usize;
+					if audio_frame_index >= next_video_frame_start {
+						features.push(accumulator.finish());
+					}
+				}
+			}
+		}
+		if !accumulator.is_empty() {
+			features.push(accumulator.finish());
+		}
+		Ok(Self { features })
+	}
+
+	pub fn at_frame(&self, frame: u64) -> AudioFeatures {
+		self.features
+			.get(frame as usize)
+			.copied()
+			.unwrap_or_default()
+	}
+}
+
+/// Collects audio frames and summarizes them as [`AudioFeatures`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) struct FeatureAccumulator {
+	sum_of_squares: f32,
+	peak: f32,
+	num_frames: usize,
+}
+
+impl FeatureAccumulator {
+	pub fn add(&mut self, frame: Frame) {
+		let sample = (frame.left + frame.right) / 2.0;
+		self.sum_of_squares += sample * sample;
+		self.peak = self.peak.max(sample.abs());
+		self.num_frames += 1;
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.num_frames == 0
+	}
+
+	/// Returns the features of the frames added so far and resets the
+	/// accumulator.
+	pub fn finish(&mut self) -> AudioFeatures {
+		let features = if self.is_empty() {
+			AudioFeatures::default()
+		} else {
+			AudioFeatures {
+				rms: (self.sum_of_squares / self.num_frames as f32).sqrt(),
+				peak: self.peak,
+			}
+		};
+		*self = Self::default();
+		features
+	}
+}
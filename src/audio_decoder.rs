@@ -0,0 +1,189 @@
+use std::{fs::File, path::Path};
+
+use kira::{dsp::Frame, sound::FromFileError};
+use symphonia::core::{
+	audio::{AudioBuffer, AudioBufferRef, Signal},
+	codecs::Decoder,
+	conv::{FromSample, IntoSample},
+	formats::{FormatReader, SeekMode, SeekTo},
+	io::MediaSourceStream,
+	meta::{MetadataRevision, StandardTagKey},
+	probe::Hint,
+	sample::Sample,
+};
+
+/// Decodes an audio file chunk by chunk.
+///
+/// Unlike kira's built-in decoder, this exposes the file's tags and
+/// reports the end of the file instead of erroring.
+pub(crate) struct FileDecoder {
+	format_reader: Box<dyn FormatReader>,
+	decoder: Box<dyn Decoder>,
+	track_id: u32,
+	sample_rate: u32,
+	num_frames: usize,
+	tags: FileTags,
+}
+
+impl FileDecoder {
+	pub fn open(path: &Path) -> Result<Self, FromFileError> {
+		let mut hint = Hint::new();
+		if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+			hint.with_extension(extension);
+		}
+		let mss = MediaSourceStream::new(Box::new(File::open(path)?), Default::default());
+		let mut probed = symphonia::default::get_probe().format(
+			&hint,
+			mss,
+			&Default::default(),
+			&Default::default(),
+		)?;
+		let tags = probed
+			.format
+			.metadata()
+			.current()
+			.map(FileTags::from_metadata_revision)
+			.or_else(|| {
+				probed
+					.metadata
+					.get()
+					.and_then(|metadata| metadata.current().map(FileTags::from_metadata_revision))
+			})
+			.unwrap_or_default();
+		let format_reader = probed.format;
+		let default_track = format_reader
+			.default_track()
+			.ok_or(FromFileError::NoDefaultTrack)?;
+		let sample_rate = default_track
+			.codec_params
+			.sample_rate
+			.ok_or(FromFileError::UnknownSampleRate)?;
+		let num_frames = default_track
+			.codec_params
+			.n_frames
+			.ok_or(FromFileError::UnknownDuration)?
+			.try_into()
+			.expect("could not convert u64 into usize");
+		let decoder = symphonia::default::get_codecs()
+			.make(&default_track.codec_params, &Default::default())?;
+		let track_id = default_track.id;
+		Ok(Self {
+			format_reader,
+			decoder,
+			track_id,
+			sample_rate,
+			num_frames,
+			tags,
+		})
+	}
+
+	pub fn sample_rate(&self) -> u32 {
+		self.sample_rate
+	}
+
+	pub fn num_frames(&self) -> usize {
+		self.num_frames
+	}
+
+	pub fn tags(&self) -> &FileTags {
+		&self.tags
+	}
+
+	/// Decodes the next chunk of audio, or returns `None` if the end
+	/// of the file has been reached.
+	pub fn decode(&mut self) -> Result<Option<Vec<Frame>>, FromFileError> {
+		loop {
+			let packet = match self.format_reader.next_packet() {
+				Ok(packet) => packet,
+				Err(symphonia::core::errors::Error::IoError(error))
+					if error.kind() == std::io::ErrorKind::UnexpectedEof =>
+				{
+					return Ok(None)
+				}
+				Err(error) => return Err(error.into()),
+			};
+			if packet.track_id() != self.track_id {
+				continue;
+			}
+			let buffer = self.decoder.decode(&packet)?;
+			return load_frames_from_buffer_ref(&buffer).map(Some);
+		}
+	}
+
+	/// Seeks to the requested frame and returns the frame that was
+	/// actually seeked to.
+	pub fn seek(&mut self, index: usize) -> Result<usize, FromFileError> {
+		let seeked_to = self.format_reader.seek(
+			SeekMode::Accurate,
+			SeekTo::TimeStamp {
+				ts: index.try_into().expect("could not convert usize into u64"),
+				track_id: self.track_id,
+			},
+		)?;
+		self.decoder.reset();
+		Ok(seeked_to
+			.actual_ts
+			.try_into()
+			.expect("could not convert u64 into usize"))
+	}
+}
+
+/// Descriptive tags read from an audio file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub(crate) struct FileTags {
+	pub title: Option<String>,
+	pub artist: Option<String>,
+	pub album: Option<String>,
+}
+
+impl FileTags {
+	fn from_metadata_revision(revision: &MetadataRevision) -> Self {
+		let find_tag = |key: StandardTagKey| {
+			revision
+				.tags()
+				.iter()
+				.find(|tag| tag.std_key == Some(key))
+				.map(|tag| tag.value.to_string())
+		};
+		Self {
+			title: find_tag(StandardTagKey::TrackTitle),
+			artist: find_tag(StandardTagKey::Artist),
+			album: find_tag(StandardTagKey::Album),
+		}
+	}
+}
+
+fn load_frames_from_buffer_ref(buffer: &AudioBufferRef) -> Result<Vec<Frame>, FromFileError> {
+	match buffer {
+		AudioBufferRef::U8(buffer) => load_frames_from_buffer(buffer),
+		AudioBufferRef::U16(buffer) => load_frames_from_buffer(buffer),
+		AudioBufferRef::U24(buffer) => load_frames_from_buffer(buffer),
+		AudioBufferRef::U32(buffer) => load_frames_from_buffer(buffer),
+		AudioBufferRef::S8(buffer) => load_frames_from_buffer(buffer),
+		AudioBufferRef::S16(buffer) => load_frames_from_buffer(buffer),
+		AudioBufferRef::S24(buffer) => load_frames_from_buffer(buffer),
+		AudioBufferRef::S32(buffer) => load_frames_from_buffer(buffer),
+		AudioBufferRef::F32(buffer) => load_frames_from_buffer(buffer),
+		AudioBufferRef::F64(buffer) => load_frames_from_buffer(buffer),
+	}
+}
+
+fn load_frames_from_buffer<S: Sample>(buffer: &AudioBuffer<S>) -> Result<Vec<Frame>, FromFileError>
+where
+	f32: FromSample<S>,
+{
+	match buffer.spec().channels.count() {
+		1 => Ok(buffer
+			.chan(0)
+			.iter()
+			.map(|sample| Frame::from_mono((*sample).into_sample()))
+			.collect()),
+		2 => Ok(buffer
+			.chan(0)
+			.iter()
+			.zip(buffer.chan(1).iter())
+			.map(|(left, right)| Frame::new((*left).into_sample(), (*right).into_sample()))
+			.collect()),
+		_ => Err(FromFileError::UnsupportedChannelConfiguration),
+	}
+}
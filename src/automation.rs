@@ -0,0 +1,391 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	conversions::seconds_to_frames_f64,
+	params::{Gradient, GradientStop, ParamValue, ParamValues, Params},
+};
+
+/// The tempo of a song, used to place keyframes on beats.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Tempo {
+	pub bpm: f64,
+	/// The time of the first beat.
+	pub offset: Duration,
+}
+
+/// A point on the timeline that a keyframe can be attached to.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TimelinePosition {
+	Frame(u64),
+	/// A beat number, which requires the visualizer to have a [`Tempo`].
+	Beat(f64),
+}
+
+impl TimelinePosition {
+	/// Returns a position at the given frame that uses the same unit
+	/// as this position.
+	pub fn with_frame(self, frame: f64, tempo: Option<Tempo>, frame_rate: u64) -> Self {
+		match (self, tempo) {
+			(TimelinePosition::Beat(_), Some(tempo)) => {
+				let seconds = frame / frame_rate as f64 - tempo.offset.as_secs_f64();
+				TimelinePosition::Beat(seconds * tempo.bpm / 60.0)
+			}
+			_ => TimelinePosition::Frame(frame.max(0.0).round() as u64),
+		}
+	}
+
+	/// Returns the (possibly fractional) video frame this position
+	/// refers to. Beat positions resolve to frame 0 if there's no tempo.
+	pub fn to_frame(self, tempo: Option<Tempo>, frame_rate: u64) -> f64 {
+		match self {
+			TimelinePosition::Frame(frame) => frame as f64,
+			TimelinePosition::Beat(beat) => tempo.map_or(0.0, |tempo| {
+				let seconds = tempo.offset.as_secs_f64() + beat * 60.0 / tempo.bpm;
+				seconds_to_frames_f64(seconds, frame_rate)
+			}),
+		}
+	}
+}
+
+/// How a keyframe's value blends into the next keyframe's value.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Interpolation {
+	#[default]
+	Linear,
+	/// Holds the value until the next keyframe.
+	Step,
+	/// Eases between the values using a cubic bezier curve with the
+	/// given control points, like CSS's `cubic-bezier`.
+	Bezier { x1: f32, y1: f32, x2: f32, y2: f32 },
+}
+
+impl Interpolation {
+	pub const EASE_IN_OUT: Self = Self::Bezier {
+		x1: 0.42,
+		y1: 0.0,
+		x2: 0.58,
+		y2: 1.0,
+	};
+
+	/// Maps the linear progress between two keyframes (from 0 to 1) to
+	/// the amount the value should be blended.
+	pub fn ease(self, amount: f32) -> f32 {
+		match self {
+			Interpolation::Linear => amount,
+			Interpolation::Step => 0.0,
+			Interpolation::Bezier { x1, y1, x2, y2 } => {
+				let t = solve_bezier_t(amount, x1, x2);
+				cubic_bezier(t, y1, y2)
+			}
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+	pub position: TimelinePosition,
+	pub value: ParamValue,
+	/// How to get from this keyframe to the next one.
+	pub interpolation: Interpolation,
+}
+
+/// The keyframes that animate a single parameter.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct AutomationTrack {
+	/// The keyframes, sorted by position.
+	pub keyframes: Vec<Keyframe>,
+}
+
+impl AutomationTrack {
+	pub fn insert(&mut self, keyframe: Keyframe, tempo: Option<Tempo>, frame_rate: u64) {
+		let frame = keyframe.position.to_frame(tempo, frame_rate);
+		let index = self
+			.keyframes
+			.partition_point(|other| other.position.to_frame(tempo, frame_rate) <= frame);
+		self.keyframes.insert(index, keyframe);
+	}
+
+	/// Re-sorts the keyframes after their positions have been edited.
+	pub fn sort(&mut self, tempo: Option<Tempo>, frame_rate: u64) {
+		self.keyframes.sort_by(|a, b| {
+			a.position
+				.to_frame(tempo, frame_rate)
+				.total_cmp(&b.position.to_frame(tempo, frame_rate))
+		});
+	}
+
+	/// Returns the value of the track at the given frame, or `None` if
+	/// the track has no keyframes.
+	pub fn value_at(
+		&self,
+		frame: f64,
+		tempo: Option<Tempo>,
+		frame_rate: u64,
+	) -> Option<ParamValue> {
+		let to_frame = |keyframe: &Keyframe| keyframe.position.to_frame(tempo, frame_rate);
+		let next_index = self
+			.keyframes
+			.partition_point(|keyframe| to_frame(keyframe) <= frame);
+		match next_index {
+			0 => self
+				.keyframes
+				.first()
+				.map(|keyframe| keyframe.value.clone()),
+			_ if next_index == self.keyframes.len() => {
+				self.keyframes.last().map(|keyframe| keyframe.value.clone())
+			}
+			_ => {
+				let previous = &self.keyframes[next_index - 1];
+				let next = &self.keyframes[next_index];
+				let previous_frame = to_frame(previous);
+				let progress =
+					((frame - previous_frame) / (to_frame(next) - previous_frame)) as f32;
+				let amount = previous.interpolation.ease(progress);
+				Some(interpolate_values(&previous.value, &next.value, amount))
+			}
+		}
+	}
+}
+
+/// Keyframe tracks for a visualizer's parameters, keyed by parameter name.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Automation(pub BTreeMap<String, AutomationTrack>);
+
+impl Automation {
+	/// Returns the values of the parameters at the given frame, with
+	/// automated parameters overriding the parameters' own values.
+	pub fn evaluate(
+		&self,
+		params: &Params,
+		frame: u64,
+		tempo: Option<Tempo>,
+		frame_rate: u64,
+	) -> ParamValues {
+		ParamValues(
+			params
+				.0
+				.iter()
+				.map(|param| {
+					let value = self
+						.0
+						.get(&param.name)
+						.and_then(|track| track.value_at(frame as f64, tempo, frame_rate))
+						.and_then(|value| param.constrain(value))
+						.unwrap_or_else(|| param.value.clone());
+					(param.name.clone(), value)
+				})
+				.collect(),
+		)
+	}
+
+	/// Re-sorts the keyframes of every track, for example after loading
+	/// tracks that were saved with a different tempo.
+	pub fn sort(&mut self, tempo: Option<Tempo>, frame_rate: u64) {
+		for track in self.0.values_mut() {
+			track.sort(tempo, frame_rate);
+		}
+	}
+}
+
+/// Blends between two parameter values. Values that can't be blended
+/// smoothly (bools, choices, mismatched gradients) switch at the end.
+fn interpolate_values(a: &ParamValue, b: &ParamValue, amount: f32) -> ParamValue {
+	let lerp = |a: f32, b: f32| a + (b - a) * amount;
+	match (a, b) {
+		(ParamValue::Float(a), ParamValue::Float(b)) => ParamValue::Float(lerp(*a, *b)),
+		(ParamValue::Int(a), ParamValue::Int(b)) => {
+			ParamValue::Int(lerp(*a as f32, *b as f32).round() as i64)
+		}
+		(ParamValue::Color(a), ParamValue::Color(b)) => {
+			ParamValue::Color(std::array::from_fn(|i| lerp(a[i], b[i])))
+		}
+		(ParamValue::Vec2(a), ParamValue::Vec2(b)) => {
+			ParamValue::Vec2(std::array::from_fn(|i| lerp(a[i], b[i])))
+		}
+		(ParamValue::Gradient(a), ParamValue::Gradient(b)) if a.stops.len() == b.stops.len() => {
+			ParamValue::Gradient(Gradient {
+				stops: a
+					.stops
+					.iter()
+					.zip(&b.stops)
+					.map(|(a, b)| GradientStop {
+						position: lerp(a.position, b.position),
+						color: std::array::from_fn(|i| lerp(a.color[i], b.color[i])),
+					})
+					.collect(),
+			})
+		}
+		_ if amount >= 1.0 => b.clone(),
+		_ => a.clone(),
+	}
+}
+
+fn cubic_bezier(t: f32, p1: f32, p2: f32) -> f32 {
+	let u = 1.0 - t;
+	3.0 * u * u * t * p1 + 3.0 * u * t * t * p2 + t * t * t
+}
+
+/// Finds the `t` at which the bezier curve's x coordinate equals `x`
+/// using bisection.
+fn solve_bezier_t(x: f32, x1: f32, x2: f32) -> f32 {
+	const ITERATIONS: usize = 20;
+	let mut low = 0.0;
+	let mut high = 1.0;
+	for _ in 0..ITERATIONS {
+		let mid = (low + high) / 2.0;
+		if cubic_bezier(mid, x1, x2) < x {
+			low = mid;
+		} else {
+			high = mid;
+		}
+	}
+	(low + high) / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const FRAME_RATE: u64 = 60;
+
+	fn keyframe(position: TimelinePosition, value: f32, interpolation: Interpolation) -> Keyframe {
+		Keyframe {
+			position,
+			value: ParamValue::Float(value),
+			interpolation,
+		}
+	}
+
+	fn track(keyframes: impl IntoIterator<Item = Keyframe>) -> AutomationTrack {
+		let mut track = AutomationTrack::default();
+		for keyframe in keyframes {
+			track.insert(keyframe, None, FRAME_RATE);
+		}
+		track
+	}
+
+	fn value_at(track: &AutomationTrack, frame: f64) -> Option<ParamValue> {
+		track.value_at(frame, None, FRAME_RATE)
+	}
+
+	#[test]
+	fn empty_track_has_no_value() {
+		assert_eq!(value_at(&AutomationTrack::default(), 0.0), None);
+	}
+
+	#[test]
+	fn holds_the_first_and_last_values() {
+		let track = track([
+			keyframe(TimelinePosition::Frame(10), 1.0, Interpolation::Linear),
+			keyframe(TimelinePosition::Frame(20), 2.0, Interpolation::Linear),
+		]);
+		assert_eq!(value_at(&track, 0.0), Some(ParamValue::Float(1.0)));
+		assert_eq!(value_at(&track, 10.0), Some(ParamValue::Float(1.0)));
+		assert_eq!(value_at(&track, 20.0), Some(ParamValue::Float(2.0)));
+		assert_eq!(value_at(&track, 100.0), Some(ParamValue::Float(2.0)));
+	}
+
+	#[test]
+	fn interpolates_between_keyframes() {
+		let track = track([
+			keyframe(TimelinePosition::Frame(0), 0.0, Interpolation::Linear),
+			keyframe(TimelinePosition::Frame(10), 1.0, Interpolation::Step),
+			keyframe(TimelinePosition::Frame(20), 0.0, Interpolation::Linear),
+		]);
+		assert_eq!(value_at(&track, 5.0), Some(ParamValue::Float(0.5)));
+		assert_eq!(value_at(&track, 15.0), Some(ParamValue::Float(1.0)));
+	}
+
+	#[test]
+	fn insert_keeps_keyframes_sorted() {
+		let track = track([
+			keyframe(TimelinePosition::Frame(20), 2.0, Interpolation::Linear),
+			keyframe(TimelinePosition::Frame(0), 0.0, Interpolation::Linear),
+			keyframe(TimelinePosition::Frame(10), 1.0, Interpolation::Linear),
+		]);
+		let positions = track
+			.keyframes
+			.iter()
+			.map(|keyframe| keyframe.position)
+			.collect::<Vec<_>>();
+		assert_eq!(
+			positions,
+			vec![
+				TimelinePosition::Frame(0),
+				TimelinePosition::Frame(10),
+				TimelinePosition::Frame(20),
+			]
+		);
+	}
+
+	#[test]
+	fn beat_positions_use_the_tempo() {
+		let tempo = Some(Tempo {
+			bpm: 120.0,
+			offset: Duration::from_secs(1),
+		});
+		assert_eq!(
+			TimelinePosition::Beat(2.0).to_frame(tempo, FRAME_RATE),
+			120.0
+		);
+		assert_eq!(
+			TimelinePosition::Beat(0.0).with_frame(90.0, tempo, FRAME_RATE),
+			TimelinePosition::Beat(1.0)
+		);
+		let mut track = AutomationTrack::default();
+		track.insert(
+			keyframe(TimelinePosition::Frame(90), 1.0, Interpolation::Linear),
+			tempo,
+			FRAME_RATE,
+		);
+		track.insert(
+			keyframe(TimelinePosition::Beat(0.0), 0.0, Interpolation::Linear),
+			tempo,
+			FRAME_RATE,
+		);
+		assert_eq!(track.keyframes[0].position, TimelinePosition::Beat(0.0));
+		assert_eq!(
+			track.value_at(75.0, tempo, FRAME_RATE),
+			Some(ParamValue::Float(0.5))
+		);
+	}
+
+	#[test]
+	fn ease_in_out_starts_and_ends_at_the_keyframes() {
+		let ease = Interpolation::EASE_IN_OUT;
+		assert!(ease.ease(0.0).abs() < 0.001);
+		assert!((ease.ease(0.5) - 0.5).abs() < 0.001);
+		assert!((ease.ease(1.0) - 1.0).abs() < 0.001);
+		assert!(ease.ease(0.25) < 0.25);
+	}
+
+	#[test]
+	fn evaluate_overrides_and_clamps_automated_params() {
+		let params = Params::new()
+			.float("size", 0.5, 0.0..=1.0)
+			.bool("visible", true);
+		let mut automation = Automation::default();
+		automation.0.insert(
+			"size".to_string(),
+			track([keyframe(
+				TimelinePosition::Frame(0),
+				2.0,
+				Interpolation::Linear,
+			)]),
+		);
+		automation.0.insert(
+			"missing".to_string(),
+			track([keyframe(
+				TimelinePosition::Frame(0),
+				1.0,
+				Interpolation::Linear,
+			)]),
+		);
+		let values = automation.evaluate(&params, 0, None, FRAME_RATE);
+		assert_eq!(values.float("size"), 1.0);
+		assert!(values.bool("visible"));
+		assert_eq!(values.get("missing"), None);
+	}
+}
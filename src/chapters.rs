@@ -1,12 +1,63 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
 use derive_more::{Index, IndexMut, IntoIterator};
+use micro::color::LinSrgba;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// A named part of the audio.
+///
+/// More information may be added to chapters in the future, so they
+/// can only be created with [`Chapter::new`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub struct Chapter {
 	pub name: String,
 	pub start_frame: u64,
+	pub artist: Option<String>,
+	/// The title of the track, if it's different from the chapter's name.
+	pub title: Option<String>,
+	pub album: Option<String>,
+	pub artwork_path: Option<PathBuf>,
+	/// A linear RGBA color that visualizers can use to theme the
+	/// chapter.
+	pub accent_color: Option<[f32; 4]>,
+	/// Shown under the title (and artist) on title cards.
+	pub subtitle: Option<String>,
+	/// Any other information about the chapter.
+	pub metadata: BTreeMap<String, String>,
+}
+
+impl Chapter {
+	pub fn new(name: impl Into<String>, start_frame: u64) -> Self {
+		Self {
+			name: name.into(),
+			start_frame,
+			artist: None,
+			title: None,
+			album: None,
+			artwork_path: None,
+			accent_color: None,
+			subtitle: None,
+			metadata: BTreeMap::new(),
+		}
+	}
+
+	/// Returns the text used to list the chapter, like "Artist - Title",
+	/// falling back to the chapter's name.
+	pub fn label(&self) -> String {
+		match (&self.artist, &self.title) {
+			(Some(artist), Some(title)) => format!("{} - {}", artist, title),
+			(None, Some(title)) => title.clone(),
+			_ => self.name.clone(),
+		}
+	}
+
+	pub fn accent_lin_srgba(&self) -> Option<LinSrgba> {
+		self.accent_color
+			.map(|[red, green, blue, alpha]| LinSrgba::new(red, green, blue, alpha))
+	}
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Index, IndexMut, IntoIterator)]
+#[derive(Debug, Clone, PartialEq, Index, IndexMut, IntoIterator)]
 pub struct Chapters(pub Vec<Chapter>);
 
 impl Chapters {
@@ -47,4 +98,113 @@ impl Chapters {
 		self.get(chapter_index + 1)
 			.map(|chapter| chapter.start_frame - 1)
 	}
+
+	/// Returns the last frame of a chapter, using the total number of
+	/// frames to find the end of the last chapter.
+	pub fn resolved_end_frame(&self, chapter_index: usize, num_frames: u64) -> u64 {
+		self.end_frame(chapter_index)
+			.unwrap_or_else(|| num_frames.saturating_sub(1))
+	}
+
+	/// Returns the position of `frame` relative to the given chapter.
+	/// Frames after the end of the chapter are treated as its last frame.
+	pub fn timing(&self, chapter_index: usize, frame: u64, num_frames: u64) -> ChapterTiming {
+		let start_frame = self[chapter_index].start_frame;
+		let end_frame = self
+			.resolved_end_frame(chapter_index, num_frames)
+			.max(start_frame);
+		let length = end_frame - start_frame + 1;
+		let frame_in_chapter = frame.saturating_sub(start_frame).min(length - 1);
+		ChapterTiming {
+			frame_in_chapter,
+			length,
+			progress: frame_in_chapter as f32 / length as f32,
+			frames_until_next_chapter: length - frame_in_chapter,
+		}
+	}
+
+	pub fn timing_at_frame(&self, frame: u64, num_frames: u64) -> Option<ChapterTiming> {
+		self.index_at_frame(frame)
+			.map(|chapter_index| self.timing(chapter_index, frame, num_frames))
+	}
+}
+
+/// Where the current frame is within the current chapter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChapterTiming {
+	/// The number of frames since the start of the chapter.
+	pub frame_in_chapter: u64,
+	/// The length of the chapter in frames.
+	pub length: u64,
+	/// How far through the chapter the current frame is, from 0 to 1.
+	pub progress: f32,
+	/// The number of frames until the next chapter starts (or the audio
+	/// ends, for the last chapter).
+	pub frames_until_next_chapter: u64,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn chapters() -> Chapters {
+		Chapters(vec![
+			Chapter::new("Intro", 0),
+			Chapter::new("Verse", 100),
+			Chapter::new("Outro", 300),
+		])
+	}
+
+	#[test]
+	fn label_prefers_artist_and_title() {
+		let mut chapter = Chapter::new("Track 1", 0);
+		assert_eq!(chapter.label(), "Track 1");
+		chapter.title = Some("Song".to_string());
+		assert_eq!(chapter.label(), "Song");
+		chapter.artist = Some("Artist".to_string());
+		assert_eq!(chapter.label(), "Artist - Song");
+	}
+
+	#[test]
+	fn finds_the_chapter_at_a_frame() {
+		let chapters = chapters();
+		assert_eq!(chapters.index_at_frame(0), Some(0));
+		assert_eq!(chapters.index_at_frame(99), Some(0));
+		assert_eq!(chapters.index_at_frame(100), Some(1));
+		assert_eq!(chapters.index_at_frame(1000), Some(2));
+		assert_eq!(
+			Chapters(vec![Chapter::new("Late", 10)]).index_at_frame(5),
+			None
+		);
+	}
+
+	#[test]
+	fn timing_is_relative_to_the_chapter() {
+		let chapters = chapters();
+		assert_eq!(
+			chapters.timing(1, 150, 400),
+			ChapterTiming {
+				frame_in_chapter: 50,
+				length: 200,
+				progress: 0.25,
+				frames_until_next_chapter: 150,
+			}
+		);
+	}
+
+	#[test]
+	fn last_chapter_ends_at_the_last_frame() {
+		let chapters = chapters();
+		let timing = chapters.timing_at_frame(399, 400).unwrap();
+		assert_eq!(timing.length, 100);
+		assert_eq!(timing.frame_in_chapter, 99);
+		assert_eq!(timing.frames_until_next_chapter, 1);
+	}
+
+	#[test]
+	fn frames_past_the_end_of_a_chapter_are_clamped() {
+		let timing = chapters().timing(0, 250, 400);
+		assert_eq!(timing.frame_in_chapter, 99);
+		assert_eq!(timing.progress, 0.99);
+	}
 }
@@ -0,0 +1,641 @@
+use std::{
+	collections::BTreeMap,
+	path::{Path, PathBuf},
+	time::Duration,
+};
+
+use micro::{
+	color::{ColorConstants, LinSrgba},
+	graphics::{shader::Shader, Canvas, CanvasSettings},
+	math::UVec2,
+	ui::{Checkbox, ComboBox, Slider, Ui, Window},
+	Context,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	shaders::load_shader, ChapterTransition, Chapters, LiveInputSource, LyricsDisplaySettings,
+	Markers, MidiMessage, OutputFormat, ParamValues, Params, PostProcessChain,
+	ProgressOverlaySettings, Sections, Stem, Tempo, TitleCardSettings, Visualizer, VisualizerEvent,
+	VisualizerInfo,
+};
+
+/// A [`Visualizer`] made of other visualizers stacked on top of each
+/// other.
+///
+/// Each layer draws into its own canvas, and the canvases are blended
+/// together from the bottom up. The bottom layer decides the audio,
+/// frame rate, resolution, chapters and other settings of the whole
+/// composition.
+///
+/// Every layer gets "<name> opacity" and "<name> visible" parameters so
+/// they can be automated, and the layers' own parameters are prefixed
+/// with "<name>: ". Layers can also be hidden or faded per chapter from
+/// the "Layers" window or with [`Layer::chapter_override`].
+pub struct Compositor {
+	layers: Vec<Layer>,
+	composite_canvases: Option<[Canvas; 2]>,
+	composite_shader: Option<Shader>,
+	show_layers_window: bool,
+}
+
+impl Compositor {
+	pub fn new(base_layer: Layer) -> Self {
+		Self {
+			layers: vec![base_layer],
+			composite_canvases: None,
+			composite_shader: None,
+			show_layers_window: false,
+		}
+	}
+
+	/// Adds a layer on top of the existing layers.
+	pub fn with_layer(mut self, layer: Layer) -> Self {
+		self.layers.push(layer);
+		self
+	}
+
+	fn base(&self) -> &dyn Visualizer {
+		self.layers[0].visualizer.as_ref()
+	}
+
+	fn render_layers_window(&mut self, egui_ctx: &micro::ui::Context, vis_info: &VisualizerInfo) {
+		Window::new("Layers")
+			.open(&mut self.show_layers_window)
+			.show(egui_ctx, |ui| {
+				for layer in &mut self.layers {
+					ui.heading(&layer.name);
+					let mut blend_mode_index = layer.blend_mode as usize;
+					ComboBox::new(("blend_mode", &layer.name), "Blend mode").show_index(
+						ui,
+						&mut blend_mode_index,
+						BlendMode::NUM_BLEND_MODES,
+						|index| BlendMode::from(index).label(),
+					);
+					layer.blend_mode = BlendMode::from(blend_mode_index);
+					if let Some(chapter_index) = vis_info.current_chapter_index {
+						render_chapter_override_editor(ui, layer, chapter_index);
+					}
+					ui.separator();
+				}
+			});
+	}
+}
+
+impl Visualizer for Compositor {
+	fn audio_path(&self) -> PathBuf {
+		self.base().audio_path()
+	}
+
+	fn playlist(&self) -> Option<Vec<PathBuf>> {
+		self.base().playlist()
+	}
+
+	fn stems(&self) -> Vec<Stem> {
+		self.layers
+			.iter()
+			.flat_map(|layer| layer.visualizer.stems())
+			.collect()
+	}
+
+	fn params(&self) -> Params {
+		let mut params = Params::new();
+		for layer in &self.layers {
+			params = params
+				.float(format!("{} opacity", layer.name), 1.0, 0.0..=1.0)
+				.bool(format!("{} visible", layer.name), true);
+			params
+				.0
+				.extend(layer.visualizer.params().0.into_iter().map(|mut param| {
+					param.name = format!("{}: {}", layer.name, param.name);
+					param
+				}));
+		}
+		params
+	}
+
+	fn tempo(&self) -> Option<Tempo> {
+		self.base().tempo()
+	}
+
+	fn live_input(&self) -> Option<LiveInputSource> {
+		self.base().live_input()
+	}
+
+	fn post_process(&self) -> PostProcessChain {
+		self.base().post_process()
+	}
+
+	fn chapter_transition(&self) -> ChapterTransition {
+		self.base().chapter_transition()
+	}
+
+	fn custom_transition_shader(&self) -> Option<String> {
+		self.base().custom_transition_shader()
+	}
+
+	fn title_cards(&self) -> TitleCardSettings {
+		self.base().title_cards()
+	}
+
+	fn lyrics_display(&self) -> LyricsDisplaySettings {
+		self.base().lyrics_display()
+	}
+
+	fn progress_overlay(&self) -> ProgressOverlaySettings {
+		self.base().progress_overlay()
+	}
+
+	fn project_path(&self) -> PathBuf {
+		self.base().project_path()
+	}
+
+	fn save_state(&self) -> anyhow::Result<Option<serde_json::Value>> {
+		let mut layer_states = BTreeMap::new();
+		for layer in &self.layers {
+			layer_states.insert(
+				layer.name.clone(),
+				LayerState {
+					blend_mode: layer.blend_mode,
+					chapter_overrides: layer.chapter_overrides.clone(),
+					visualizer_state: layer.visualizer.save_state()?,
+				},
+			);
+		}
+		Ok(Some(serde_json::to_value(layer_states)?))
+	}
+
+	fn load_state(&mut self, state: serde_json::Value) -> anyhow::Result<()> {
+		let mut layer_states: BTreeMap<String, LayerState> = serde_json::from_value(state)?;
+		for layer in &mut self.layers {
+			let Some(layer_state) = layer_states.remove(&layer.name) else {
+				continue;
+			};
+			layer.blend_mode = layer_state.blend_mode;
+			layer.chapter_overrides = layer_state.chapter_overrides;
+			if let Some(visualizer_state) = layer_state.visualizer_state {
+				layer.visualizer.load_state(visualizer_state)?;
+			}
+		}
+		Ok(())
+	}
+
+	fn frame_rate(&self) -> u64 {
+		self.base().frame_rate()
+	}
+
+	fn video_resolution(&self) -> UVec2 {
+		self.base().video_resolution()
+	}
+
+	fn output_formats(&self) -> Vec<OutputFormat> {
+		self.base().output_formats()
+	}
+
+	fn chapters(&self) -> Option<&Chapters> {
+		self.base().chapters()
+	}
+
+	fn sections(&self) -> Sections {
+		self.base().sections()
+	}
+
+	fn markers(&self) -> Markers {
+		self.base().markers()
+	}
+
+	fn lyrics_path(&self) -> Option<PathBuf> {
+		self.base().lyrics_path()
+	}
+
+	fn ui(
+		&mut self,
+		ctx: &mut Context,
+		egui_ctx: &micro::ui::Context,
+		vis_info: VisualizerInfo,
+	) -> Result<(), anyhow::Error> {
+		self.render_layers_window(egui_ctx, &vis_info);
+		for layer in &mut self.layers {
+			let layer_vis_info = layer_vis_info(&layer.name, &vis_info);
+			layer.visualizer.ui(ctx, egui_ctx, layer_vis_info)?;
+		}
+		Ok(())
+	}
+
+	fn menu(
+		&mut self,
+		ctx: &mut Context,
+		ui: &mut Ui,
+		vis_info: VisualizerInfo,
+	) -> Result<(), anyhow::Error> {
+		if ui.button("Layers").clicked() {
+			self.show_layers_window = true;
+		}
+		for layer in &mut self.layers {
+			let layer_vis_info = layer_vis_info(&layer.name, &vis_info);
+			layer.visualizer.menu(ctx, ui, layer_vis_info)?;
+		}
+		Ok(())
+	}
+
+	fn audio_changed(
+		&mut self,
+		ctx: &mut Context,
+		vis_info: VisualizerInfo,
+		audio_path: &Path,
+	) -> anyhow::Result<()> {
+		for layer in &mut self.layers {
+			let layer_vis_info = layer_vis_info(&layer.name, &vis_info);
+			layer
+				.visualizer
+				.audio_changed(ctx, layer_vis_info, audio_path)?;
+		}
+		Ok(())
+	}
+
+	fn event(
+		&mut self,
+		ctx: &mut Context,
+		vis_info: VisualizerInfo,
+		event: VisualizerEvent,
+	) -> Result<(), anyhow::Error> {
+		for layer in &mut self.layers {
+			let layer_vis_info = layer_vis_info(&layer.name, &vis_info);
+			layer.visualizer.event(ctx, layer_vis_info, event.clone())?;
+		}
+		Ok(())
+	}
+
+	fn midi(
+		&mut self,
+		ctx: &mut Context,
+		vis_info: VisualizerInfo,
+		message: MidiMessage,
+	) -> anyhow::Result<()> {
+		for layer in &mut self.layers {
+			let layer_vis_info = layer_vis_info(&layer.name, &vis_info);
+			layer
+				.visualizer
+				.midi(ctx, layer_vis_info, message.clone())?;
+		}
+		Ok(())
+	}
+
+	fn update(
+		&mut self,
+		ctx: &mut Context,
+		vis_info: VisualizerInfo,
+		delta_time: Duration,
+	) -> anyhow::Result<()> {
+		for layer in &mut self.layers {
+			let layer_vis_info = layer_vis_info(&layer.name, &vis_info);
+			layer.visualizer.update(ctx, layer_vis_info, delta_time)?;
+		}
+		Ok(())
+	}
+
+	fn draw(
+		&mut self,
+		ctx: &mut Context,
+		vis_info: VisualizerInfo,
+		main_canvas: &Canvas,
+	) -> anyhow::Result<()> {
+		let size = main_canvas.size();
+		if self.composite_shader.is_none() {
+			self.composite_shader = Some(load_shader(ctx, include_str!("shaders/composite.glsl"))?);
+		}
+		if self
+			.composite_canvases
+			.as_ref()
+			.map_or(true, |canvases| canvases[0].size() != size)
+		{
+			self.composite_canvases = Some([
+				Canvas::new(ctx, size, CanvasSettings::default()),
+				Canvas::new(ctx, size, CanvasSettings::default()),
+			]);
+		}
+		let composite_shader = self.composite_shader.as_ref().unwrap();
+		let composite_canvases = self.composite_canvases.as_ref().unwrap();
+		composite_canvases[0].render_to(ctx).clear(LinSrgba::BLACK);
+		let mut num_layers_drawn = 0;
+		for layer in &mut self.layers {
+			let opacity = layer.opacity(&vis_info);
+			if opacity <= 0.0 {
+				continue;
+			}
+			if layer
+				.canvas
+				.as_ref()
+				.map_or(true, |canvas| canvas.size() != size)
+			{
+				layer.canvas = Some(Canvas::new(ctx, size, CanvasSettings::default()));
+			}
+			let layer_vis_info = layer_vis_info(&layer.name, &vis_info);
+			let layer_canvas = layer.canvas.as_ref().unwrap();
+			layer.visualizer.draw(ctx, layer_vis_info, layer_canvas)?;
+			composite_shader.send_canvas(ctx, "layer", layer_canvas)?;
+			composite_shader.send_f32(ctx, "blend_mode", layer.blend_mode as usize as f32)?;
+			composite_shader.send_f32(ctx, "opacity", opacity)?;
+			let below = &composite_canvases[num_layers_drawn % 2];
+			let ctx = &mut composite_canvases[(num_layers_drawn + 1) % 2].render_to(ctx);
+			ctx.clear(LinSrgba::BLACK);
+			let ctx = &mut ctx.push_shader(composite_shader);
+			below.draw(ctx);
+			num_layers_drawn += 1;
+		}
+		let ctx = &mut main_canvas.render_to(ctx);
+		ctx.clear(LinSrgba::BLACK);
+		composite_canvases[num_layers_drawn % 2].draw(ctx);
+		Ok(())
+	}
+}
+
+/// A visualizer in a [`Compositor`].
+pub struct Layer {
+	name: String,
+	visualizer: Box<dyn Visualizer>,
+	blend_mode: BlendMode,
+	chapter_overrides: BTreeMap<usize, LayerOverride>,
+	canvas: Option<Canvas>,
+}
+
+impl Layer {
+	/// Creates a layer. The name should be unique within the compositor,
+	/// since it's used to tell the layers' parameters and saved state
+	/// apart.
+	pub fn new(name: impl Into<String>, visualizer: impl Visualizer) -> Self {
+		Self {
+			name: name.into(),
+			visualizer: Box::new(visualizer),
+			blend_mode: BlendMode::default(),
+			chapter_overrides: BTreeMap::new(),
+			canvas: None,
+		}
+	}
+
+	pub fn blend_mode(mut self, blend_mode: BlendMode) -> Self {
+		self.blend_mode = blend_mode;
+		self
+	}
+
+	/// Sets the visibility and opacity of the layer during a chapter.
+	pub fn chapter_override(mut self, chapter_index: usize, layer_override: LayerOverride) -> Self {
+		self.chapter_overrides.insert(chapter_index, layer_override);
+		self
+	}
+
+	/// Returns how opaque the layer should be drawn, or 0 if it's hidden.
+	///
+	/// Missing visibility or opacity parameters count as fully visible.
+	fn opacity(&self, vis_info: &VisualizerInfo) -> f32 {
+		let params = &vis_info.params;
+		if !params
+			.try_bool(&format!("{} visible", self.name))
+			.unwrap_or(true)
+		{
+			return 0.0;
+		}
+		let opacity = params
+			.try_float(&format!("{} opacity", self.name))
+			.unwrap_or(1.0);
+		let chapter_override = vis_info
+			.current_chapter_index
+			.and_then(|chapter_index| self.chapter_overrides.get(&chapter_index));
+		match chapter_override {
+			Some(LayerOverride { visible: false, .. }) => 0.0,
+			Some(LayerOverride {
+				opacity: chapter_opacity,
+				..
+			}) => opacity * chapter_opacity,
+			None => opacity,
+		}
+	}
+}
+
+/// How a [`Layer`] is combined with the layers below it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum BlendMode {
+	#[default]
+	Alpha,
+	Add,
+	Multiply,
+	Screen,
+}
+
+impl BlendMode {
+	pub const NUM_BLEND_MODES: usize = 4;
+
+	pub fn label(self) -> &'static str {
+		match self {
+			BlendMode::Alpha => "Alpha",
+			BlendMode::Add => "Add",
+			BlendMode::Multiply => "Multiply",
+			BlendMode::Screen => "Screen",
+		}
+	}
+}
+
+impl From<usize> for BlendMode {
+	fn from(value: usize) -> Self {
+		match value {
+			0 => Self::Alpha,
+			1 => Self::Add,
+			2 => Self::Multiply,
+			3 => Self::Screen,
+			_ => panic!("invalid BlendMode"),
+		}
+	}
+}
+
+/// The visibility and opacity of a [`Layer`] during a specific chapter.
+/// The opacity is multiplied with the layer's opacity parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LayerOverride {
+	pub visible: bool,
+	pub opacity: f32,
+}
+
+impl Default for LayerOverride {
+	fn default() -> Self {
+		Self {
+			visible: true,
+			opacity: 1.0,
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
+struct LayerState {
+	blend_mode: BlendMode,
+	chapter_overrides: BTreeMap<usize, LayerOverride>,
+	visualizer_state: Option<serde_json::Value>,
+}
+
+/// Returns the [`VisualizerInfo`] for a layer, with only the layer's own
+/// parameters under their original names.
+fn layer_vis_info(layer_name: &str, vis_info: &VisualizerInfo) -> VisualizerInfo {
+	let prefix = format!("{}: ", layer_name);
+	VisualizerInfo {
+		params: ParamValues(
+			vis_info
+				.params
+				.0
+				.iter()
+				.filter_map(|(name, value)| {
+					name.strip_prefix(&prefix)
+						.map(|name| (name.to_string(), value.clone()))
+				})
+				.collect(),
+		),
+		..vis_info.clone()
+	}
+}
+
+fn render_chapter_override_editor(ui: &mut Ui, layer: &mut Layer, chapter_index: usize) {
+	let mut overridden = layer.chapter_overrides.contains_key(&chapter_index);
+	if ui
+		.add(Checkbox::new(&mut overridden, "Override in this chapter"))
+		.changed()
+	{
+		if overridden {
+			layer
+				.chapter_overrides
+				.insert(chapter_index, LayerOverride::default());
+		} else {
+			layer.chapter_overrides.remove(&chapter_index);
+		}
+	}
+	if let Some(layer_override) = layer.chapter_overrides.get_mut(&chapter_index) {
+		ui.add(Checkbox::new(&mut layer_override.visible, "Visible"));
+		ui.add(Slider::new(&mut layer_override.opacity, 0.0..=1.0).text("Opacity"));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use crate::{AudioFeatures, ParamValue, StemFeatures};
+
+	use super::*;
+
+	struct EmptyVisualizer;
+
+	impl Visualizer for EmptyVisualizer {
+		fn draw(
+			&mut self,
+			_ctx: &mut Context,
+			_vis_info: VisualizerInfo,
+			_main_canvas: &Canvas,
+		) -> anyhow::Result<()> {
+			Ok(())
+		}
+	}
+
+	fn vis_info(
+		params: impl IntoIterator<Item = (&'static str, ParamValue)>,
+		current_chapter_index: Option<usize>,
+	) -> VisualizerInfo {
+		let resolution = UVec2::new(1920, 1080);
+		VisualizerInfo {
+			resolution,
+			output_format: OutputFormat::new("Default", resolution),
+			current_frame: 0,
+			current_time: Duration::ZERO,
+			num_frames: 0,
+			current_chapter_index,
+			current_chapter: None,
+			chapter_timing: None,
+			current_section: None,
+			section_timing: None,
+			transition: None,
+			audio: AudioFeatures::default(),
+			stems: StemFeatures::default(),
+			params: ParamValues(
+				params
+					.into_iter()
+					.map(|(name, value)| (name.to_string(), value))
+					.collect(),
+			),
+			markers: Arc::new(Markers::default()),
+			lyrics: None,
+		}
+	}
+
+	#[test]
+	fn layers_without_params_are_fully_visible() {
+		let layer = Layer::new("Stars", EmptyVisualizer);
+		assert_eq!(layer.opacity(&vis_info([], None)), 1.0);
+	}
+
+	#[test]
+	fn layer_opacity_follows_params() {
+		let layer = Layer::new("Stars", EmptyVisualizer);
+		let info = vis_info(
+			[
+				("Stars opacity", ParamValue::Float(0.5)),
+				("Stars visible", ParamValue::Bool(true)),
+			],
+			None,
+		);
+		assert_eq!(layer.opacity(&info), 0.5);
+		let info = vis_info(
+			[
+				("Stars opacity", ParamValue::Float(0.5)),
+				("Stars visible", ParamValue::Bool(false)),
+			],
+			None,
+		);
+		assert_eq!(layer.opacity(&info), 0.0);
+	}
+
+	#[test]
+	fn chapter_overrides_apply_only_in_their_chapter() {
+		let layer = Layer::new("Stars", EmptyVisualizer)
+			.chapter_override(
+				1,
+				LayerOverride {
+					visible: true,
+					opacity: 0.5,
+				},
+			)
+			.chapter_override(
+				2,
+				LayerOverride {
+					visible: false,
+					opacity: 1.0,
+				},
+			);
+		let params = [("Stars opacity", ParamValue::Float(0.5))];
+		assert_eq!(layer.opacity(&vis_info(params.clone(), Some(0))), 0.5);
+		assert_eq!(layer.opacity(&vis_info(params.clone(), Some(1))), 0.25);
+		assert_eq!(layer.opacity(&vis_info(params.clone(), Some(2))), 0.0);
+		assert_eq!(layer.opacity(&vis_info(params, None)), 0.5);
+	}
+
+	#[test]
+	fn layer_vis_info_only_has_the_layers_own_params() {
+		let info = vis_info(
+			[
+				("Stars opacity", ParamValue::Float(0.5)),
+				("Stars: count", ParamValue::Int(100)),
+				("Stars: twinkle", ParamValue::Bool(true)),
+				("Starship: speed", ParamValue::Float(2.0)),
+			],
+			Some(1),
+		);
+		let layer_info = layer_vis_info("Stars", &info);
+		assert_eq!(
+			layer_info.params,
+			ParamValues(
+				[
+					("count".to_string(), ParamValue::Int(100)),
+					("twinkle".to_string(), ParamValue::Bool(true)),
+				]
+				.into_iter()
+				.collect()
+			)
+		);
+		assert_eq!(layer_info.current_chapter_index, Some(1));
+	}
+}
@@ -9,3 +9,22 @@ pub fn seconds_to_frames_i64(seconds: f64, frame_rate: u64) -> i64 {
 pub fn frame_to_seconds(frame: u64, frame_rate: u64) -> f64 {
 	frame as f64 / frame_rate as f64
 }
+
+pub fn seconds_to_frames_f64(seconds: f64, frame_rate: u64) -> f64 {
+	seconds * frame_rate as f64
+}
+
+/// Formats a time like "1:23", or "1:02:03" if `include_hours` is true.
+pub fn format_timestamp(seconds: f64, include_hours: bool) -> String {
+	let seconds = seconds as u64;
+	if include_hours {
+		format!(
+			"{}:{:02}:{:02}",
+			seconds / 3600,
+			seconds / 60 % 60,
+			seconds % 60
+		)
+	} else {
+		format!("{}:{:02}", seconds / 60, seconds % 60)
+	}
+}
@@ -1,11 +1,45 @@
+mod analysis;
+mod audio_decoder;
+mod automation;
 mod chapters;
+mod compositor;
 mod conversions;
+mod lyrics;
+mod markers;
+mod midi;
+mod output_formats;
+mod params;
+mod post_process;
+mod progress_overlay;
+mod sections;
+mod shaders;
+mod title_cards;
+mod transitions;
 mod vis_runner;
 
-pub use chapters::*;
 pub use micro::*;
+pub use rosc::{OscMessage, OscType};
+
+pub use analysis::{AudioFeatures, StemFeatures};
+pub use automation::*;
+pub use chapters::*;
+pub use compositor::*;
+pub use lyrics::*;
+pub use markers::*;
+pub use midi::*;
+pub use output_formats::*;
+pub use params::*;
+pub use post_process::*;
+pub use progress_overlay::*;
+pub use sections::*;
+pub use title_cards::*;
+pub use transitions::*;
 
-use std::{path::PathBuf, time::Duration};
+use std::{
+	path::{Path, PathBuf},
+	sync::Arc,
+	time::Duration,
+};
 
 use micro::{graphics::Canvas, math::UVec2, ui::Ui};
 use vis_runner::VisRunner;
@@ -31,7 +65,83 @@ pub fn run<T: Visualizer>(
 
 #[allow(unused_variables)]
 pub trait Visualizer: 'static {
-	fn audio_path(&self) -> PathBuf;
+	/// The audio to visualize. Visualizers with a
+	/// [`playlist`](Visualizer::playlist) or a
+	/// [`live_input`](Visualizer::live_input) don't need to implement
+	/// this; it defaults to the first track of the playlist.
+	fn audio_path(&self) -> PathBuf {
+		self.playlist()
+			.and_then(|paths| paths.into_iter().next())
+			.expect("the visualizer must implement audio_path or playlist")
+	}
+
+	fn playlist(&self) -> Option<Vec<PathBuf>> {
+		None
+	}
+
+	fn stems(&self) -> Vec<Stem> {
+		vec![]
+	}
+
+	fn params(&self) -> Params {
+		Params::default()
+	}
+
+	fn tempo(&self) -> Option<Tempo> {
+		None
+	}
+
+	fn live_input(&self) -> Option<LiveInputSource> {
+		None
+	}
+
+	fn post_process(&self) -> PostProcessChain {
+		PostProcessChain::default()
+	}
+
+	fn chapter_transition(&self) -> ChapterTransition {
+		ChapterTransition::default()
+	}
+
+	fn custom_transition_shader(&self) -> Option<String> {
+		None
+	}
+
+	fn title_cards(&self) -> TitleCardSettings {
+		TitleCardSettings::default()
+	}
+
+	/// An `.lrc` or `.srt` file to load when the project doesn't have
+	/// lyrics saved yet.
+	fn lyrics_path(&self) -> Option<PathBuf> {
+		None
+	}
+
+	fn lyrics_display(&self) -> LyricsDisplaySettings {
+		LyricsDisplaySettings::default()
+	}
+
+	fn progress_overlay(&self) -> ProgressOverlaySettings {
+		ProgressOverlaySettings::default()
+	}
+
+	/// Where the runner's settings and the visualizer's state are saved.
+	/// Defaults to `project.json` next to the visualizer's executable.
+	fn project_path(&self) -> PathBuf {
+		let file_name = "project.json";
+		match std::env::current_exe() {
+			Ok(exe_path) => exe_path.with_file_name(file_name),
+			Err(_) => file_name.into(),
+		}
+	}
+
+	fn save_state(&self) -> anyhow::Result<Option<serde_json::Value>> {
+		Ok(None)
+	}
+
+	fn load_state(&mut self, state: serde_json::Value) -> anyhow::Result<()> {
+		Ok(())
+	}
 
 	fn frame_rate(&self) -> u64 {
 		60
@@ -41,10 +151,27 @@ pub trait Visualizer: 'static {
 		UVec2::new(3840, 2160)
 	}
 
+	/// The sizes the visualizer can be previewed and rendered at. The
+	/// first format is used by default.
+	fn output_formats(&self) -> Vec<OutputFormat> {
+		vec![OutputFormat::new("Default", self.video_resolution())]
+	}
+
 	fn chapters(&self) -> Option<&Chapters> {
 		None
 	}
 
+	/// The sections of each chapter when the project doesn't have any
+	/// saved yet.
+	fn sections(&self) -> Sections {
+		Sections::default()
+	}
+
+	/// The markers to use when the project doesn't have any saved yet.
+	fn markers(&self) -> Markers {
+		Markers::default()
+	}
+
 	fn ui(
 		&mut self,
 		ctx: &mut Context,
@@ -63,15 +190,33 @@ pub trait Visualizer: 'static {
 		Ok(())
 	}
 
+	fn audio_changed(
+		&mut self,
+		ctx: &mut Context,
+		vis_info: VisualizerInfo,
+		audio_path: &Path,
+	) -> anyhow::Result<()> {
+		Ok(())
+	}
+
 	fn event(
 		&mut self,
 		ctx: &mut Context,
 		vis_info: VisualizerInfo,
-		event: Event,
+		event: VisualizerEvent,
 	) -> Result<(), anyhow::Error> {
 		Ok(())
 	}
 
+	fn midi(
+		&mut self,
+		ctx: &mut Context,
+		vis_info: VisualizerInfo,
+		message: MidiMessage,
+	) -> anyhow::Result<()> {
+		Ok(())
+	}
+
 	fn update(
 		&mut self,
 		ctx: &mut Context,
@@ -81,6 +226,12 @@ pub trait Visualizer: 'static {
 		Ok(())
 	}
 
+	/// Draws the current frame to `main_canvas`.
+	///
+	/// During a chapter transition, this is called twice per frame, once
+	/// for each chapter, with [`TransitionInfo::role`] saying which one is
+	/// being drawn. Per-frame state (like particle simulations) should be
+	/// advanced in [`Visualizer::update`] rather than here.
 	fn draw(
 		&mut self,
 		ctx: &mut Context,
@@ -89,10 +240,76 @@ pub trait Visualizer: 'static {
 	) -> anyhow::Result<()>;
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct VisualizerInfo {
 	pub resolution: UVec2,
+	/// The format being previewed or rendered. `resolution` may be
+	/// smaller than the format's resolution in the preview.
+	pub output_format: OutputFormat,
 	pub current_frame: u64,
 	pub current_time: Duration,
+	/// The length of the audio in frames, or 0 in live mode.
+	pub num_frames: u64,
 	pub current_chapter_index: Option<usize>,
+	/// The chapter at the current frame, including its metadata.
+	pub current_chapter: Option<Chapter>,
+	pub chapter_timing: Option<ChapterTiming>,
+	pub current_section: Option<Section>,
+	pub section_timing: Option<SectionTiming>,
+	pub transition: Option<TransitionInfo>,
+	/// Features of the main audio, or of the input device in live mode.
+	pub audio: AudioFeatures,
+	pub stems: StemFeatures,
+	pub params: ParamValues,
+	/// Shared with the runner, so cloning the info doesn't copy every
+	/// marker.
+	pub markers: Arc<Markers>,
+	/// The line of lyrics at the current frame, if lyrics are loaded.
+	pub lyrics: Option<ActiveLyric>,
+}
+
+impl VisualizerInfo {
+	/// Returns the markers at the current frame or within the
+	/// `num_frames` frames before it.
+	pub fn recent_markers(&self, num_frames: u64) -> &[Marker] {
+		self.markers
+			.within_last_frames(self.current_frame, num_frames)
+	}
+
+	/// Returns the first marker of the given kind after the current frame.
+	pub fn next_marker_of_kind(&self, kind: &str) -> Option<&Marker> {
+		self.markers.next_of_kind(self.current_frame, kind)
+	}
+}
+
+/// Input that's passed to [`Visualizer::event`].
+#[derive(Debug, Clone)]
+pub enum VisualizerEvent {
+	/// An input event from the window, such as a key press.
+	Input(Event),
+	/// An OSC message whose address isn't handled by the runner itself.
+	Osc(OscMessage),
+}
+
+/// An audio file that's analyzed alongside the main audio but
+/// not played back.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Stem {
+	pub name: String,
+	pub path: PathBuf,
+}
+
+/// Where audio comes from when the visualizer is driven by a live input
+/// instead of an audio file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LiveInputSource {
+	/// The system's default input device.
+	DefaultDevice,
+	/// An input device by name. On Linux, PipeWire and PulseAudio
+	/// monitor sources show up as input devices, so this can also be
+	/// used to capture another program's output.
+	Device(String),
+	/// An audio file that's streamed in real time as if it were coming
+	/// from an input device. Useful for testing live mode.
+	File(PathBuf),
 }
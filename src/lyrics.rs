@@ -0,0 +1,486 @@
+use std::{
+	path::{Path, PathBuf},
+	time::Duration,
+};
+
+use anyhow::{anyhow, bail, Context};
+use serde::{Deserialize, Serialize};
+
+/// How long the last line of an LRC file is shown, since LRC files don't
+/// say when a line ends.
+const LAST_LRC_LINE_DURATION: Duration = Duration::from_secs(5);
+
+/// Timed text loaded from an `.lrc` or `.srt` file.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Lyrics {
+	/// Sorted by start time.
+	pub lines: Vec<LyricLine>,
+}
+
+impl Lyrics {
+	/// Loads lyrics from an `.lrc` or `.srt` file, depending on the file
+	/// extension.
+	pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+		let source = std::fs::read_to_string(path)?;
+		let extension = path
+			.extension()
+			.and_then(|extension| extension.to_str())
+			.map(str::to_lowercase);
+		let lyrics = match extension.as_deref() {
+			Some("lrc") => Self::from_lrc(&source),
+			Some("srt") => Self::from_srt(&source),
+			_ => bail!("{} is not an .lrc or .srt file", path.display()),
+		};
+		lyrics.with_context(|| format!("could not load lyrics from {}", path.display()))
+	}
+
+	/// Parses an LRC file, including word timings from enhanced LRC
+	/// (`<mm:ss.xx>` tags within a line).
+	pub fn from_lrc(source: &str) -> anyhow::Result<Self> {
+		let mut offset = 0.0;
+		let mut entries = vec![];
+		for line in source.trim_start_matches('\u{feff}').lines() {
+			let mut rest = line.trim();
+			let mut times = vec![];
+			while let Some(tag_and_rest) = rest.strip_prefix('[') {
+				let Some((tag, after_tag)) = tag_and_rest.split_once(']') else {
+					break;
+				};
+				if let Some(time) = parse_lrc_time(tag) {
+					times.push(time?);
+				} else if let Some(milliseconds) = tag.strip_prefix("offset:") {
+					let milliseconds = milliseconds
+						.trim()
+						.parse::<f64>()
+						.ok()
+						.filter(|milliseconds| milliseconds.is_finite())
+						.ok_or_else(|| anyhow!("invalid offset: {}", milliseconds))?;
+					offset = milliseconds / 1000.0;
+				}
+				rest = after_tag;
+			}
+			entries.extend(times.into_iter().map(|time| (time, rest)));
+		}
+		// a positive offset makes the lyrics appear sooner
+		let mut entries = entries
+			.into_iter()
+			.map(|(time, text)| ((time - offset).max(0.0), text))
+			.collect::<Vec<_>>();
+		entries.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+		let mut lines = vec![];
+		for (i, (start, text)) in entries.iter().enumerate() {
+			let end = entries[i + 1..]
+				.iter()
+				.map(|(time, _)| *time)
+				.find(|time| time > start)
+				.unwrap_or(start + LAST_LRC_LINE_DURATION.as_secs_f64());
+			let (text, words) = parse_enhanced_lrc_words(text, end, offset)?;
+			if text.is_empty() {
+				continue;
+			}
+			lines.push(LyricLine {
+				start: seconds_to_duration(*start)?,
+				end: seconds_to_duration(end)?,
+				text,
+				words,
+			});
+		}
+		Ok(Self { lines })
+	}
+
+	/// Parses an SRT subtitle file. Multi-line subtitles are joined with
+	/// `\n`.
+	pub fn from_srt(source: &str) -> anyhow::Result<Self> {
+		let source = source.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+		let mut lines = vec![];
+		for block in source.split("\n\n") {
+			let mut block_lines = block.lines().skip_while(|line| line.trim().is_empty());
+			let Some(first_line) = block_lines.next() else {
+				continue;
+			};
+			let timing = if first_line.contains("-->") {
+				first_line
+			} else {
+				block_lines
+					.next()
+					.ok_or_else(|| anyhow!("subtitle {} has no timing", first_line.trim()))?
+			};
+			let (start, end) = timing
+				.split_once("-->")
+				.ok_or_else(|| anyhow!("invalid subtitle timing: {}", timing))?;
+			// the end time may be followed by position information
+			let end = end.split_whitespace().next().unwrap_or_default();
+			lines.push(LyricLine {
+				start: parse_srt_time(start.trim())?,
+				end: parse_srt_time(end)?,
+				text: block_lines.map(str::trim).collect::<Vec<_>>().join("\n"),
+				words: vec![],
+			});
+		}
+		lines.sort_by_key(|line| line.start);
+		Ok(Self { lines })
+	}
+
+	/// Returns the line (and word, if the line has word timings) being
+	/// sung at the given time.
+	pub fn active_at(&self, time: Duration) -> Option<ActiveLyric> {
+		let line_index = self.lines.iter().rposition(|line| line.start <= time)?;
+		let line = &self.lines[line_index];
+		if time >= line.end {
+			return None;
+		}
+		let word = line
+			.words
+			.iter()
+			.rposition(|word| word.start <= time)
+			.map(|word_index| {
+				let word = &line.words[word_index];
+				ActiveWord {
+					index: word_index,
+					progress: progress(time, word.start, word.end),
+				}
+			});
+		Some(ActiveLyric {
+			line_index,
+			line: line.clone(),
+			line_progress: progress(time, line.start, line.end),
+			word,
+		})
+	}
+
+	/// Returns the lyrics as an SRT file, with times relative to
+	/// `start_time`. Lines that end before `start_time` are left out.
+	pub fn to_srt(&self, start_time: Duration) -> String {
+		let mut srt = String::new();
+		let lines = self.lines.iter().filter(|line| line.end > start_time);
+		for (i, line) in lines.enumerate() {
+			srt += &format!(
+				"{}\n{} --> {}\n{}\n\n",
+				i + 1,
+				format_srt_time(line.start.saturating_sub(start_time)),
+				format_srt_time(line.end - start_time),
+				line.text
+			);
+		}
+		srt
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LyricLine {
+	pub start: Duration,
+	pub end: Duration,
+	pub text: String,
+	/// The timing of each word in the line. Only enhanced LRC files have
+	/// word timings.
+	pub words: Vec<LyricWord>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LyricWord {
+	pub start: Duration,
+	pub end: Duration,
+	pub text: String,
+}
+
+/// The lyrics at the current frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActiveLyric {
+	pub line_index: usize,
+	pub line: LyricLine,
+	/// How far through the line the current frame is, from 0 to 1.
+	pub line_progress: f32,
+	/// The latest word in the line that's started, if the line has word
+	/// timings.
+	pub word: Option<ActiveWord>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ActiveWord {
+	/// The index of the word in [`LyricLine::words`].
+	pub index: usize,
+	/// How far through the word the current frame is, from 0 to 1. Stays
+	/// at 1 until the next word starts.
+	pub progress: f32,
+}
+
+/// Settings for the built-in lyrics overlay, which shows the current line
+/// and highlights the words that have been sung.
+///
+/// Like title cards, the overlay is drawn after post-processing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LyricsDisplaySettings {
+	pub enabled: bool,
+	/// A TrueType or OpenType font file. Lyrics aren't shown until a font
+	/// is chosen.
+	pub font_path: Option<PathBuf>,
+	/// The height of the text as a fraction of the video height.
+	pub font_size: f32,
+	/// Where the center of the text is, from (0, 0) at the top left of
+	/// the video to (1, 1) at the bottom right.
+	pub position: [f32; 2],
+	pub text_color: [f32; 4],
+	/// The color of words that have already been sung.
+	pub highlight_color: [f32; 4],
+	/// The color of the box behind the text.
+	pub background_color: [f32; 4],
+}
+
+impl Default for LyricsDisplaySettings {
+	fn default() -> Self {
+		Self {
+			enabled: false,
+			font_path: None,
+			font_size: 0.05,
+			position: [0.5, 0.85],
+			text_color: [1.0, 1.0, 1.0, 1.0],
+			highlight_color: [1.0, 0.8, 0.2, 1.0],
+			background_color: [0.0, 0.0, 0.0, 0.0],
+		}
+	}
+}
+
+/// Parses an LRC timestamp like `01:23.45`, returning the time in
+/// seconds, or `None` if the tag isn't a timestamp.
+fn parse_lrc_time(tag: &str) -> Option<anyhow::Result<f64>> {
+	let (minutes, seconds) = tag.split_once(':')?;
+	let minutes: u64 = minutes.trim().parse().ok()?;
+	let seconds = seconds
+		.trim()
+		.parse::<f64>()
+		.ok()
+		.filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+		.ok_or_else(|| anyhow!("invalid LRC time: {}", tag));
+	Some(seconds.map(|seconds| minutes as f64 * 60.0 + seconds))
+}
+
+/// Splits a line of enhanced LRC into its words. Returns the line's text
+/// with the timestamps removed and the word timings (which are empty if
+/// the line has no `<mm:ss.xx>` tags).
+fn parse_enhanced_lrc_words(
+	text: &str,
+	line_end: f64,
+	offset: f64,
+) -> anyhow::Result<(String, Vec<LyricWord>)> {
+	let mut segments = vec![];
+	let mut untimed_text = String::new();
+	let mut rest = text;
+	while let Some(tag_start) = rest.find('<') {
+		let Some(tag_length) = rest[tag_start..].find('>') else {
+			break;
+		};
+		let Some(time) = parse_lrc_time(&rest[tag_start + 1..tag_start + tag_length]) else {
+			break;
+		};
+		let time = time?;
+		untimed_text += &rest[..tag_start];
+		if let Some((_, segment_text)) = segments.last_mut() {
+			*segment_text = &rest[..tag_start];
+		}
+		segments.push(((time - offset).max(0.0), ""));
+		rest = &rest[tag_start + tag_length + 1..];
+	}
+	untimed_text += rest;
+	if let Some((_, segment_text)) = segments.last_mut() {
+		*segment_text = rest;
+	}
+	let mut words = vec![];
+	for (i, (start, text)) in segments.iter().enumerate() {
+		let text = text.trim();
+		if text.is_empty() {
+			// a timestamp with no text after it only marks the end of the
+			// previous word
+			continue;
+		}
+		let end = segments.get(i + 1).map_or(line_end, |(time, _)| *time);
+		words.push(LyricWord {
+			start: seconds_to_duration(*start)?,
+			end: seconds_to_duration(end.max(*start))?,
+			text: text.to_string(),
+		});
+	}
+	if words.is_empty() {
+		return Ok((untimed_text.trim().to_string(), words));
+	}
+	let text = words
+		.iter()
+		.map(|word| word.text.as_str())
+		.collect::<Vec<_>>()
+		.join(" ");
+	Ok((text, words))
+}
+
+/// Parses an SRT timestamp like `01:02:03,456`.
+fn parse_srt_time(time: &str) -> anyhow::Result<Duration> {
+	let invalid_time = || anyhow!("invalid subtitle time: {}", time);
+	let mut parts = time.split(':');
+	let (Some(hours), Some(minutes), Some(seconds), None) =
+		(parts.next(), parts.next(), parts.next(), parts.next())
+	else {
+		return Err(invalid_time());
+	};
+	let hours: u64 = hours.parse().map_err(|_| invalid_time())?;
+	let minutes: u64 = minutes.parse().map_err(|_| invalid_time())?;
+	let seconds = seconds
+		.replace(',', ".")
+		.parse::<f64>()
+		.ok()
+		.and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+		.ok_or_else(invalid_time)?;
+	hours
+		.checked_mul(3600)
+		.zip(minutes.checked_mul(60))
+		.and_then(|(hours, minutes)| hours.checked_add(minutes))
+		.and_then(|whole_seconds| Duration::from_secs(whole_seconds).checked_add(seconds))
+		.ok_or_else(invalid_time)
+}
+
+/// Converts a time in seconds to a [`Duration`], failing if the time is
+/// negative, not a number or too large.
+fn seconds_to_duration(seconds: f64) -> anyhow::Result<Duration> {
+	Duration::try_from_secs_f64(seconds).map_err(|_| anyhow!("invalid time: {} seconds", seconds))
+}
+
+fn format_srt_time(time: Duration) -> String {
+	let milliseconds = time.as_millis();
+	format!(
+		"{:02}:{:02}:{:02},{:03}",
+		milliseconds / 3_600_000,
+		milliseconds / 60_000 % 60,
+		milliseconds / 1000 % 60,
+		milliseconds % 1000
+	)
+}
+
+fn progress(time: Duration, start: Duration, end: Duration) -> f32 {
+	if end <= start {
+		return 1.0;
+	}
+	((time - start).as_secs_f32() / (end - start).as_secs_f32()).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn lrc_lines_end_when_the_next_line_starts() {
+		let lyrics = Lyrics::from_lrc(
+			"[ar:Someone]\n[00:01.50]First line\n[00:03.00][00:07.00]Repeated line\n",
+		)
+		.unwrap();
+		let times = lyrics
+			.lines
+			.iter()
+			.map(|line| (line.start, line.end, line.text.as_str()))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			times,
+			[
+				(
+					Duration::from_millis(1500),
+					Duration::from_secs(3),
+					"First line"
+				),
+				(
+					Duration::from_secs(3),
+					Duration::from_secs(7),
+					"Repeated line"
+				),
+				(
+					Duration::from_secs(7),
+					Duration::from_secs(7) + LAST_LRC_LINE_DURATION,
+					"Repeated line"
+				),
+			]
+		);
+	}
+
+	#[test]
+	fn lrc_offset_makes_lyrics_appear_sooner() {
+		let lyrics = Lyrics::from_lrc("[offset:500]\n[00:00.25]A\n[00:02.00]B\n").unwrap();
+		assert_eq!(lyrics.lines[0].start, Duration::ZERO);
+		assert_eq!(lyrics.lines[1].start, Duration::from_millis(1500));
+	}
+
+	#[test]
+	fn enhanced_lrc_has_word_timings() {
+		let lyrics =
+			Lyrics::from_lrc("[00:01.00]<00:01.00>Hello <00:01.50>world<00:02.50>\n").unwrap();
+		let line = &lyrics.lines[0];
+		assert_eq!(line.text, "Hello world");
+		assert_eq!(
+			line.words,
+			[
+				LyricWord {
+					start: Duration::from_secs(1),
+					end: Duration::from_millis(1500),
+					text: "Hello".to_string(),
+				},
+				LyricWord {
+					start: Duration::from_millis(1500),
+					end: Duration::from_millis(2500),
+					text: "world".to_string(),
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn invalid_lrc_times_are_errors() {
+		assert!(Lyrics::from_lrc("[00:NaN]Line\n").is_err());
+		assert!(Lyrics::from_lrc("[00:inf]Line\n").is_err());
+		assert!(Lyrics::from_lrc("[00:-1.0]Line\n").is_err());
+		assert!(Lyrics::from_lrc("[00:01.00]<00:nan>Word\n").is_err());
+		assert!(Lyrics::from_lrc("[offset:inf]\n[00:01.00]Line\n").is_err());
+	}
+
+	#[test]
+	fn srt_subtitles_are_parsed() {
+		let lyrics = Lyrics::from_srt(
+			"\u{feff}1\r\n00:00:01,000 --> 00:00:02,500 X1:0\r\nFirst\r\nline\r\n\r\n2\r\n00:01:00,000 --> 01:00:00,000\r\nSecond\r\n",
+		)
+		.unwrap();
+		assert_eq!(
+			lyrics.lines,
+			[
+				LyricLine {
+					start: Duration::from_secs(1),
+					end: Duration::from_millis(2500),
+					text: "First\nline".to_string(),
+					words: vec![],
+				},
+				LyricLine {
+					start: Duration::from_secs(60),
+					end: Duration::from_secs(3600),
+					text: "Second".to_string(),
+					words: vec![],
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn invalid_srt_times_are_errors() {
+		for time in [
+			"00:00:NaN",
+			"00:00:inf",
+			"00:00:-1,000",
+			"00:01",
+			"x:00:01,000",
+		] {
+			assert!(parse_srt_time(time).is_err(), "{} should be invalid", time);
+		}
+		assert!(parse_srt_time(&format!("{}:00:00,000", u64::MAX)).is_err());
+	}
+
+	#[test]
+	fn srt_round_trips() {
+		let lyrics = Lyrics::from_srt("1\n00:00:01,000 --> 00:00:02,000\nLine\n").unwrap();
+		assert_eq!(
+			lyrics.to_srt(Duration::from_millis(500)),
+			"1\n00:00:00,500 --> 00:00:01,500\nLine\n\n"
+		);
+	}
+}
@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+
+/// A named point on the timeline, like a snare roll or a vocal chop,
+/// that visualizers can react to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Marker {
+	pub name: String,
+	/// A free-form category, so visualizers can react differently to
+	/// different kinds of markers.
+	pub kind: String,
+	pub frame: u64,
+}
+
+impl Marker {
+	pub fn new(name: impl Into<String>, kind: impl Into<String>, frame: u64) -> Self {
+		Self {
+			name: name.into(),
+			kind: kind.into(),
+			frame,
+		}
+	}
+}
+
+/// Markers sorted by frame.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Markers(pub Vec<Marker>);
+
+impl Markers {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Adds a marker. Markers can be added in any order.
+	pub fn with(mut self, marker: Marker) -> Self {
+		self.insert(marker);
+		self
+	}
+
+	pub fn insert(&mut self, marker: Marker) {
+		let index = self.0.partition_point(|other| other.frame <= marker.frame);
+		self.0.insert(index, marker);
+	}
+
+	/// Re-sorts the markers after their frames have been edited.
+	pub fn sort(&mut self) {
+		self.0.sort_by_key(|marker| marker.frame);
+	}
+
+	pub fn len(&self) -> usize {
+		self.0.len()
+	}
+
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Returns the markers between `start_frame` and `end_frame`
+	/// (inclusive).
+	pub fn between(&self, start_frame: u64, end_frame: u64) -> &[Marker] {
+		let start_index = self.0.partition_point(|marker| marker.frame < start_frame);
+		let end_index = self.0.partition_point(|marker| marker.frame <= end_frame);
+		&self.0[start_index..end_index.max(start_index)]
+	}
+
+	/// Returns the markers at `frame` or within the `num_frames` frames
+	/// before it.
+	pub fn within_last_frames(&self, frame: u64, num_frames: u64) -> &[Marker] {
+		self.between(frame.saturating_sub(num_frames), frame)
+	}
+
+	/// Returns the first marker of the given kind after `frame`.
+	pub fn next_of_kind(&self, frame: u64, kind: &str) -> Option<&Marker> {
+		self.0
+			.iter()
+			.find(|marker| marker.frame > frame && marker.kind == kind)
+	}
+
+	/// Returns the latest marker of the given kind at or before `frame`.
+	pub fn previous_of_kind(&self, frame: u64, kind: &str) -> Option<&Marker> {
+		self.0
+			.iter()
+			.rev()
+			.find(|marker| marker.frame <= frame && marker.kind == kind)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn markers() -> Markers {
+		Markers::new()
+			.with(Marker::new("c", "drop", 30))
+			.with(Marker::new("a", "beat", 10))
+			.with(Marker::new("b", "beat", 20))
+			.with(Marker::new("b2", "beat", 20))
+	}
+
+	fn names(markers: &[Marker]) -> Vec<&str> {
+		markers.iter().map(|marker| marker.name.as_str()).collect()
+	}
+
+	#[test]
+	fn between_includes_both_ends() {
+		let markers = markers();
+		assert_eq!(names(markers.between(10, 20)), ["a", "b", "b2"]);
+		assert_eq!(names(markers.between(11, 30)), ["b", "b2", "c"]);
+		assert_eq!(names(markers.between(21, 29)), Vec::<&str>::new());
+		assert_eq!(names(markers.between(0, 100)), ["a", "b", "b2", "c"]);
+	}
+
+	#[test]
+	fn between_is_empty_for_reversed_ranges() {
+		assert!(markers().between(30, 10).is_empty());
+	}
+
+	#[test]
+	fn within_last_frames_stops_at_zero() {
+		let markers = markers();
+		assert_eq!(names(markers.within_last_frames(20, 5)), ["b", "b2"]);
+		assert_eq!(names(markers.within_last_frames(15, 100)), ["a"]);
+	}
+
+	#[test]
+	fn next_and_previous_of_kind() {
+		let markers = markers();
+		assert_eq!(markers.next_of_kind(10, "beat").unwrap().name, "b");
+		assert_eq!(markers.next_of_kind(20, "beat"), None);
+		assert_eq!(markers.previous_of_kind(25, "beat").unwrap().name, "b2");
+		assert_eq!(markers.previous_of_kind(25, "drop"), None);
+	}
+}
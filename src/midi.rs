@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+
+/// A message received from a MIDI input.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MidiMessage {
+	NoteOn {
+		channel: u8,
+		note: u8,
+		velocity: u8,
+	},
+	NoteOff {
+		channel: u8,
+		note: u8,
+		velocity: u8,
+	},
+	ControlChange {
+		channel: u8,
+		controller: u8,
+		value: u8,
+	},
+	/// Any other message, as the raw bytes that were received.
+	Other(Vec<u8>),
+}
+
+impl MidiMessage {
+	pub fn from_bytes(bytes: &[u8]) -> Self {
+		match *bytes {
+			[status, note, velocity] if status & 0xF0 == 0x90 && velocity > 0 => Self::NoteOn {
+				channel: status & 0x0F,
+				note,
+				velocity,
+			},
+			// a note on with a velocity of 0 is a note off by convention
+			[status, note, velocity] if status & 0xF0 == 0x80 || status & 0xF0 == 0x90 => {
+				Self::NoteOff {
+					channel: status & 0x0F,
+					note,
+					velocity,
+				}
+			}
+			[status, controller, value] if status & 0xF0 == 0xB0 => Self::ControlChange {
+				channel: status & 0x0F,
+				controller,
+				value,
+			},
+			_ => Self::Other(bytes.to_vec()),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn note_on_with_zero_velocity_is_parsed_as_note_off() {
+		assert_eq!(
+			MidiMessage::from_bytes(&[0x93, 60, 0]),
+			MidiMessage::NoteOff {
+				channel: 3,
+				note: 60,
+				velocity: 0
+			}
+		);
+		assert_eq!(
+			MidiMessage::from_bytes(&[0x93, 60, 1]),
+			MidiMessage::NoteOn {
+				channel: 3,
+				note: 60,
+				velocity: 1
+			}
+		);
+	}
+
+	#[test]
+	fn unknown_messages_keep_their_bytes() {
+		assert_eq!(
+			MidiMessage::from_bytes(&[0xF8]),
+			MidiMessage::Other(vec![0xF8])
+		);
+	}
+}
@@ -0,0 +1,73 @@
+use micro::math::UVec2;
+
+/// A named video size that the visualizer can be previewed and rendered
+/// at, like a landscape video or a vertical short.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OutputFormat {
+	pub name: String,
+	pub resolution: UVec2,
+}
+
+impl OutputFormat {
+	pub fn new(name: impl Into<String>, resolution: UVec2) -> Self {
+		Self {
+			name: name.into(),
+			resolution,
+		}
+	}
+
+	/// Returns 16:9, 9:16 and 1:1 formats whose shorter side is
+	/// `short_side` pixels long.
+	pub fn standard_formats(short_side: u32) -> Vec<Self> {
+		let long_side = short_side * 16 / 9;
+		vec![
+			Self::new("Landscape (16:9)", UVec2::new(long_side, short_side)),
+			Self::new("Vertical (9:16)", UVec2::new(short_side, long_side)),
+			Self::new("Square (1:1)", UVec2::splat(short_side)),
+		]
+	}
+
+	/// Returns the format's name in a form that can be used in a file
+	/// name, like "landscape-16-9".
+	pub fn file_name_suffix(&self) -> String {
+		let mut suffix = String::new();
+		for character in self.name.chars() {
+			if character.is_alphanumeric() {
+				suffix.extend(character.to_lowercase());
+			} else if !suffix.is_empty() && !suffix.ends_with('-') {
+				suffix.push('-');
+			}
+		}
+		suffix.trim_end_matches('-').to_string()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn file_name_suffix_keeps_only_words_and_numbers() {
+		let suffix = |name: &str| OutputFormat::new(name, UVec2::ONE).file_name_suffix();
+		assert_eq!(suffix("Landscape (16:9)"), "landscape-16-9");
+		assert_eq!(suffix("  Vertical -- Short!  "), "vertical-short");
+		assert_eq!(suffix("Default"), "default");
+		assert_eq!(suffix("???"), "");
+	}
+
+	#[test]
+	fn standard_formats_share_the_short_side() {
+		let resolutions = OutputFormat::standard_formats(1080)
+			.into_iter()
+			.map(|format| format.resolution)
+			.collect::<Vec<_>>();
+		assert_eq!(
+			resolutions,
+			vec![
+				UVec2::new(1920, 1080),
+				UVec2::new(1080, 1920),
+				UVec2::new(1080, 1080),
+			]
+		);
+	}
+}
@@ -0,0 +1,401 @@
+use std::{collections::BTreeMap, ops::RangeInclusive};
+
+use micro::{color::LinSrgba, math::Vec2};
+use serde::{Deserialize, Serialize};
+
+/// A list of parameters a visualizer exposes to the user.
+///
+/// The runner shows an inspector for the parameters, saves their values
+/// in the project file, and passes the current values to the visualizer
+/// via [`VisualizerInfo::params`](crate::VisualizerInfo::params).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Params(pub Vec<Param>);
+
+impl Params {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn float(self, name: impl Into<String>, default: f32, range: RangeInclusive<f32>) -> Self {
+		self.with(name, ParamKind::Float { range }, ParamValue::Float(default))
+	}
+
+	pub fn int(self, name: impl Into<String>, default: i64, range: RangeInclusive<i64>) -> Self {
+		self.with(name, ParamKind::Int { range }, ParamValue::Int(default))
+	}
+
+	pub fn color(self, name: impl Into<String>, default: LinSrgba) -> Self {
+		self.with(
+			name,
+			ParamKind::Color,
+			ParamValue::Color([default.red, default.green, default.blue, default.alpha]),
+		)
+	}
+
+	pub fn bool(self, name: impl Into<String>, default: bool) -> Self {
+		self.with(name, ParamKind::Bool, ParamValue::Bool(default))
+	}
+
+	/// Adds a parameter that can be set to one of the given options. The
+	/// value is the index of the selected option.
+	pub fn choice(
+		self,
+		name: impl Into<String>,
+		default: usize,
+		options: impl IntoIterator<Item = impl Into<String>>,
+	) -> Self {
+		let options = options.into_iter().map(Into::into).collect();
+		self.with(
+			name,
+			ParamKind::Choice { options },
+			ParamValue::Choice(default),
+		)
+	}
+
+	pub fn vec2(self, name: impl Into<String>, default: Vec2, range: RangeInclusive<f32>) -> Self {
+		self.with(
+			name,
+			ParamKind::Vec2 { range },
+			ParamValue::Vec2(default.to_array()),
+		)
+	}
+
+	pub fn gradient(self, name: impl Into<String>, default: Gradient) -> Self {
+		self.with(name, ParamKind::Gradient, ParamValue::Gradient(default))
+	}
+
+	pub fn get(&self, name: &str) -> Option<&Param> {
+		self.0.iter().find(|param| param.name == name)
+	}
+
+	pub fn get_mut(&mut self, name: &str) -> Option<&mut Param> {
+		self.0.iter_mut().find(|param| param.name == name)
+	}
+
+	pub fn len(&self) -> usize {
+		self.0.len()
+	}
+
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	pub fn values(&self) -> ParamValues {
+		ParamValues(
+			self.0
+				.iter()
+				.map(|param| (param.name.clone(), param.value.clone()))
+				.collect(),
+		)
+	}
+
+	/// Sets the values of parameters from a previous session. Values that
+	/// don't match a parameter's name and type are ignored.
+	pub fn apply_values(&mut self, values: &ParamValues) {
+		for (name, value) in &values.0 {
+			if let Some(param) = self.get_mut(name) {
+				param.set(value.clone());
+			}
+		}
+	}
+
+	fn with(mut self, name: impl Into<String>, kind: ParamKind, default: ParamValue) -> Self {
+		self.0.push(Param {
+			name: name.into(),
+			kind,
+			value: default.clone(),
+			default,
+		});
+		self
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+	pub name: String,
+	pub kind: ParamKind,
+	pub default: ParamValue,
+	pub value: ParamValue,
+}
+
+impl Param {
+	/// Sets the value of the parameter if the value is the right type,
+	/// clamping it to the parameter's range.
+	pub fn set(&mut self, value: ParamValue) {
+		if let Some(value) = self.constrain(value) {
+			self.value = value;
+		}
+	}
+
+	/// Returns the value clamped to the parameter's range, or `None` if
+	/// the value is the wrong type for the parameter.
+	pub fn constrain(&self, value: ParamValue) -> Option<ParamValue> {
+		if std::mem::discriminant(&value) != std::mem::discriminant(&self.default) {
+			return None;
+		}
+		let value = match (&self.kind, value) {
+			(ParamKind::Float { range }, ParamValue::Float(value)) => {
+				ParamValue::Float(value.clamp(*range.start(), *range.end()))
+			}
+			(ParamKind::Int { range }, ParamValue::Int(value)) => {
+				ParamValue::Int(value.clamp(*range.start(), *range.end()))
+			}
+			(ParamKind::Choice { options }, ParamValue::Choice(index)) => {
+				ParamValue::Choice(index.min(options.len().saturating_sub(1)))
+			}
+			(ParamKind::Vec2 { range }, ParamValue::Vec2([x, y])) => ParamValue::Vec2([
+				x.clamp(*range.start(), *range.end()),
+				y.clamp(*range.start(), *range.end()),
+			]),
+			(_, value) => value,
+		};
+		Some(value)
+	}
+
+	/// Sets the value of the parameter from a number from 0 to 1, like
+	/// the position of a knob. Colors, vectors and gradients can't be
+	/// set this way.
+	pub fn set_normalized(&mut self, amount: f32) {
+		let amount = amount.clamp(0.0, 1.0);
+		let value = match &self.kind {
+			ParamKind::Float { range } => {
+				ParamValue::Float(range.start() + (range.end() - range.start()) * amount)
+			}
+			ParamKind::Int { range } => {
+				let span = (range.end() - range.start()) as f32;
+				ParamValue::Int(range.start() + (span * amount).round() as i64)
+			}
+			ParamKind::Bool => ParamValue::Bool(amount >= 0.5),
+			ParamKind::Choice { options } => {
+				ParamValue::Choice((amount * options.len() as f32) as usize)
+			}
+			ParamKind::Color | ParamKind::Vec2 { .. } | ParamKind::Gradient => return,
+		};
+		self.set(value);
+	}
+
+	pub fn reset(&mut self) {
+		self.value = self.default.clone();
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamKind {
+	Float { range: RangeInclusive<f32> },
+	Int { range: RangeInclusive<i64> },
+	Color,
+	Bool,
+	Choice { options: Vec<String> },
+	Vec2 { range: RangeInclusive<f32> },
+	Gradient,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ParamValue {
+	Float(f32),
+	Int(i64),
+	/// A linear RGBA color.
+	Color([f32; 4]),
+	Bool(bool),
+	Choice(usize),
+	Vec2([f32; 2]),
+	Gradient(Gradient),
+}
+
+/// A snapshot of the values of every parameter, keyed by name.
+///
+/// The typed getters panic if there's no parameter with the given name
+/// and type, since that's usually a mistake in the visualizer's code.
+/// The `try_` getters return `None` instead, for parameters that may not
+/// exist.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct ParamValues(pub BTreeMap<String, ParamValue>);
+
+impl ParamValues {
+	pub fn get(&self, name: &str) -> Option<&ParamValue> {
+		self.0.get(name)
+	}
+
+	pub fn float(&self, name: &str) -> f32 {
+		self.try_float(name)
+			.unwrap_or_else(|| panic!("no float parameter named {}", name))
+	}
+
+	pub fn try_float(&self, name: &str) -> Option<f32> {
+		match self.get(name) {
+			Some(ParamValue::Float(value)) => Some(*value),
+			_ => None,
+		}
+	}
+
+	pub fn int(&self, name: &str) -> i64 {
+		self.try_int(name)
+			.unwrap_or_else(|| panic!("no int parameter named {}", name))
+	}
+
+	pub fn try_int(&self, name: &str) -> Option<i64> {
+		match self.get(name) {
+			Some(ParamValue::Int(value)) => Some(*value),
+			_ => None,
+		}
+	}
+
+	pub fn color(&self, name: &str) -> LinSrgba {
+		self.try_color(name)
+			.unwrap_or_else(|| panic!("no color parameter named {}", name))
+	}
+
+	pub fn try_color(&self, name: &str) -> Option<LinSrgba> {
+		match self.get(name) {
+			Some(ParamValue::Color([red, green, blue, alpha])) => {
+				Some(LinSrgba::new(*red, *green, *blue, *alpha))
+			}
+			_ => None,
+		}
+	}
+
+	pub fn bool(&self, name: &str) -> bool {
+		self.try_bool(name)
+			.unwrap_or_else(|| panic!("no bool parameter named {}", name))
+	}
+
+	pub fn try_bool(&self, name: &str) -> Option<bool> {
+		match self.get(name) {
+			Some(ParamValue::Bool(value)) => Some(*value),
+			_ => None,
+		}
+	}
+
+	pub fn choice(&self, name: &str) -> usize {
+		self.try_choice(name)
+			.unwrap_or_else(|| panic!("no choice parameter named {}", name))
+	}
+
+	pub fn try_choice(&self, name: &str) -> Option<usize> {
+		match self.get(name) {
+			Some(ParamValue::Choice(index)) => Some(*index),
+			_ => None,
+		}
+	}
+
+	pub fn vec2(&self, name: &str) -> Vec2 {
+		self.try_vec2(name)
+			.unwrap_or_else(|| panic!("no vec2 parameter named {}", name))
+	}
+
+	pub fn try_vec2(&self, name: &str) -> Option<Vec2> {
+		match self.get(name) {
+			Some(ParamValue::Vec2(value)) => Some(Vec2::from_array(*value)),
+			_ => None,
+		}
+	}
+
+	pub fn gradient(&self, name: &str) -> &Gradient {
+		self.try_gradient(name)
+			.unwrap_or_else(|| panic!("no gradient parameter named {}", name))
+	}
+
+	pub fn try_gradient(&self, name: &str) -> Option<&Gradient> {
+		match self.get(name) {
+			Some(ParamValue::Gradient(gradient)) => Some(gradient),
+			_ => None,
+		}
+	}
+}
+
+/// A series of colors blended together.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Gradient {
+	/// The colors in the gradient, sorted by position.
+	pub stops: Vec<GradientStop>,
+}
+
+impl Gradient {
+	pub fn new(stops: impl IntoIterator<Item = (f32, LinSrgba)>) -> Self {
+		let mut gradient = Self {
+			stops: stops
+				.into_iter()
+				.map(|(position, color)| GradientStop {
+					position,
+					color: [color.red, color.green, color.blue, color.alpha],
+				})
+				.collect(),
+		};
+		gradient.sort();
+		gradient
+	}
+
+	/// Returns the color of the gradient at `position` (from 0 to 1).
+	/// Non-finite positions are treated as 0.
+	pub fn sample(&self, position: f32) -> LinSrgba {
+		let position = if position.is_finite() { position } else { 0.0 };
+		let color = match self.stops.as_slice() {
+			[] => [0.0; 4],
+			[first, ..] if position <= first.position => first.color,
+			[.., last] if position >= last.position => last.color,
+			stops => {
+				let next_index = stops
+					.iter()
+					.position(|stop| stop.position > position)
+					.unwrap();
+				let previous = &stops[next_index - 1];
+				let next = &stops[next_index];
+				let amount = (position - previous.position) / (next.position - previous.position);
+				std::array::from_fn(|i| {
+					previous.color[i] + (next.color[i] - previous.color[i]) * amount
+				})
+			}
+		};
+		let [red, green, blue, alpha] = color;
+		LinSrgba::new(red, green, blue, alpha)
+	}
+
+	pub fn sort(&mut self) {
+		self.stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GradientStop {
+	pub position: f32,
+	/// A linear RGBA color.
+	pub color: [f32; 4],
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn try_getters_return_none_for_missing_or_mistyped_params() {
+		let values = ParamValues(BTreeMap::from([
+			("Speed".to_string(), ParamValue::Float(0.5)),
+			("Enabled".to_string(), ParamValue::Bool(true)),
+		]));
+		assert_eq!(values.try_float("Speed"), Some(0.5));
+		assert_eq!(values.try_bool("Enabled"), Some(true));
+		assert_eq!(values.try_float("Enabled"), None);
+		assert_eq!(values.try_int("Missing"), None);
+		assert_eq!(values.try_gradient("Speed"), None);
+	}
+
+	#[test]
+	fn gradients_sample_non_finite_positions_at_the_start() {
+		let gradient = Gradient::new([
+			(0.0, LinSrgba::new(0.0, 0.0, 0.0, 1.0)),
+			(0.5, LinSrgba::new(0.5, 0.5, 0.5, 1.0)),
+			(1.0, LinSrgba::new(1.0, 1.0, 1.0, 1.0)),
+		]);
+		for position in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+			assert_eq!(gradient.sample(position).red, 0.0);
+		}
+		assert_eq!(gradient.sample(0.25).red, 0.25);
+	}
+
+	#[test]
+	#[should_panic(expected = "no float parameter named Missing")]
+	fn typed_getters_panic_for_missing_params() {
+		ParamValues::default().float("Missing");
+	}
+}
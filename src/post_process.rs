@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{ParamValues, Params};
+
+/// Shader effects that are applied to the main canvas, in order, after
+/// [`Visualizer::draw`](crate::Visualizer::draw). The result is what's
+/// shown in the preview and written to rendered videos.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct PostProcessChain(pub Vec<PostProcessEffect>);
+
+impl PostProcessChain {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn with(mut self, kind: PostProcessEffectKind) -> Self {
+		self.0.push(PostProcessEffect::new(kind));
+		self
+	}
+}
+
+/// A single effect in a [`PostProcessChain`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PostProcessEffect {
+	pub kind: PostProcessEffectKind,
+	pub enabled: bool,
+	/// The values of the parameters returned by
+	/// [`PostProcessEffectKind::params`].
+	pub values: ParamValues,
+}
+
+impl PostProcessEffect {
+	pub fn new(kind: PostProcessEffectKind) -> Self {
+		Self {
+			kind,
+			enabled: true,
+			values: kind.params().values(),
+		}
+	}
+
+	/// Adds default values for any parameters that are missing, such as
+	/// when an effect gains a parameter after a project was saved.
+	pub(crate) fn fill_missing_values(&mut self) {
+		let mut params = self.kind.params();
+		params.apply_values(&self.values);
+		self.values = params.values();
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PostProcessEffectKind {
+	Bloom,
+	ChromaticAberration,
+	FilmGrain,
+	Vignette,
+	Crt,
+	Feedback,
+}
+
+impl PostProcessEffectKind {
+	pub const NUM_KINDS: usize = 6;
+
+	pub fn label(self) -> &'static str {
+		match self {
+			PostProcessEffectKind::Bloom => "Bloom",
+			PostProcessEffectKind::ChromaticAberration => "Chromatic aberration",
+			PostProcessEffectKind::FilmGrain => "Film grain",
+			PostProcessEffectKind::Vignette => "Vignette",
+			PostProcessEffectKind::Crt => "CRT",
+			PostProcessEffectKind::Feedback => "Feedback",
+		}
+	}
+
+	/// Returns the parameters of the effect with their default values.
+	/// Each parameter is passed to the effect's shader as a uniform named
+	/// after the parameter in snake case. Sizes in pixels are measured at
+	/// 1080p and scaled to the canvas the effect is applied to.
+	pub fn params(self) -> Params {
+		match self {
+			PostProcessEffectKind::Bloom => Params::new()
+				.float("Threshold", 0.8, 0.0..=1.0)
+				.float("Intensity", 1.0, 0.0..=4.0)
+				.float("Radius", 4.0, 1.0..=16.0),
+			PostProcessEffectKind::ChromaticAberration => {
+				Params::new().float("Amount", 4.0, 0.0..=32.0)
+			}
+			PostProcessEffectKind::FilmGrain => Params::new()
+				.float("Intensity", 0.1, 0.0..=1.0)
+				.float("Size", 1.0, 1.0..=8.0),
+			PostProcessEffectKind::Vignette => Params::new()
+				.float("Intensity", 0.5, 0.0..=1.0)
+				.float("Radius", 0.75, 0.0..=1.5)
+				.float("Softness", 0.5, 0.01..=1.0),
+			PostProcessEffectKind::Crt => Params::new()
+				.float("Curvature", 0.1, 0.0..=0.5)
+				.float("Scanline intensity", 0.25, 0.0..=1.0)
+				.float("Scanline count", 540.0, 100.0..=2000.0),
+			PostProcessEffectKind::Feedback => Params::new()
+				.float("Amount", 0.9, 0.0..=1.0)
+				.float("Zoom", 1.01, 0.9..=1.1)
+				.float("Rotation", 0.0, -0.1..=0.1),
+		}
+	}
+}
+
+impl From<usize> for PostProcessEffectKind {
+	fn from(value: usize) -> Self {
+		match value {
+			0 => Self::Bloom,
+			1 => Self::ChromaticAberration,
+			2 => Self::FilmGrain,
+			3 => Self::Vignette,
+			4 => Self::Crt,
+			5 => Self::Feedback,
+			_ => panic!("invalid PostProcessEffectKind"),
+		}
+	}
+}
@@ -0,0 +1,81 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+/// Settings for the overlay that shows a progress bar and the elapsed
+/// and total time.
+///
+/// Like title cards, the overlay is drawn after post-processing, so it
+/// looks the same in the preview and in rendered videos.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProgressOverlaySettings {
+	pub enabled: bool,
+	pub scope: ProgressScope,
+	pub show_bar: bool,
+	/// Shows the elapsed and total time above the bar. This needs a font.
+	pub show_time: bool,
+	/// A TrueType or OpenType font file for the time.
+	pub font_path: Option<PathBuf>,
+	/// The height of the time text as a fraction of the video height.
+	pub font_size: f32,
+	/// Where the top left corner of the overlay is, from (0, 0) at the top
+	/// left of the video to (1, 1) at the bottom right.
+	pub position: [f32; 2],
+	/// The width and height of the bar as fractions of the video size.
+	pub bar_size: [f32; 2],
+	pub bar_color: [f32; 4],
+	/// The color of the part of the bar that hasn't been reached yet.
+	pub bar_background_color: [f32; 4],
+	pub text_color: [f32; 4],
+}
+
+impl Default for ProgressOverlaySettings {
+	fn default() -> Self {
+		Self {
+			enabled: false,
+			scope: ProgressScope::default(),
+			show_bar: true,
+			show_time: true,
+			font_path: None,
+			font_size: 0.025,
+			position: [0.03, 0.93],
+			bar_size: [0.2, 0.006],
+			bar_color: [1.0, 1.0, 1.0, 0.9],
+			bar_background_color: [1.0, 1.0, 1.0, 0.25],
+			text_color: [1.0, 1.0, 1.0, 0.9],
+		}
+	}
+}
+
+/// What the progress overlay measures progress through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum ProgressScope {
+	/// The whole audio file (or playlist).
+	#[default]
+	Whole,
+	/// The current chapter. Falls back to the whole audio if there are no
+	/// chapters.
+	Chapter,
+}
+
+impl ProgressScope {
+	pub const NUM_SCOPES: usize = 2;
+
+	pub fn label(self) -> &'static str {
+		match self {
+			ProgressScope::Whole => "Whole audio",
+			ProgressScope::Chapter => "Current chapter",
+		}
+	}
+}
+
+impl From<usize> for ProgressScope {
+	fn from(value: usize) -> Self {
+		match value {
+			0 => Self::Whole,
+			1 => Self::Chapter,
+			_ => panic!("invalid ProgressScope"),
+		}
+	}
+}
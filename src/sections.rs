@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// A structural part of a chapter, like an intro, build or drop.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Section {
+	pub name: String,
+	/// The first frame of the section, relative to the start of its
+	/// chapter.
+	pub start_frame: u64,
+}
+
+impl Section {
+	pub fn new(name: impl Into<String>, start_frame: u64) -> Self {
+		Self {
+			name: name.into(),
+			start_frame,
+		}
+	}
+}
+
+/// The sections of each chapter, keyed by chapter index.
+///
+/// Audio without chapters is treated as a single chapter with an index
+/// of 0.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Sections(pub BTreeMap<usize, Vec<Section>>);
+
+impl Sections {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Adds a section to a chapter. Sections can be added in any order.
+	pub fn with(mut self, chapter_index: usize, section: Section) -> Self {
+		self.insert(chapter_index, section);
+		self
+	}
+
+	/// Returns the sections of a chapter, sorted by start frame.
+	pub fn for_chapter(&self, chapter_index: usize) -> &[Section] {
+		self.0
+			.get(&chapter_index)
+			.map(Vec::as_slice)
+			.unwrap_or_default()
+	}
+
+	pub fn for_chapter_mut(&mut self, chapter_index: usize) -> &mut Vec<Section> {
+		self.0.entry(chapter_index).or_default()
+	}
+
+	pub fn insert(&mut self, chapter_index: usize, section: Section) {
+		let sections = self.for_chapter_mut(chapter_index);
+		let index = sections.partition_point(|other| other.start_frame <= section.start_frame);
+		sections.insert(index, section);
+	}
+
+	/// Re-sorts the sections of every chapter after their start frames
+	/// have been edited, and removes chapters with no sections.
+	pub fn sort(&mut self) {
+		for sections in self.0.values_mut() {
+			sections.sort_by_key(|section| section.start_frame);
+		}
+		self.0.retain(|_, sections| !sections.is_empty());
+	}
+
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.0.values().all(Vec::is_empty)
+	}
+
+	/// Returns the index of the section playing at the given frame
+	/// (relative to the start of the chapter), or `None` if the frame
+	/// is before the chapter's first section.
+	pub fn index_at_frame(&self, chapter_index: usize, frame_in_chapter: u64) -> Option<usize> {
+		self.for_chapter(chapter_index)
+			.iter()
+			.rposition(|section| section.start_frame <= frame_in_chapter)
+	}
+
+	/// Returns where the given frame is within its section.
+	/// `chapter_length` is used to find the end of the chapter's last
+	/// section.
+	pub fn timing_at_frame(
+		&self,
+		chapter_index: usize,
+		frame_in_chapter: u64,
+		chapter_length: u64,
+	) -> Option<SectionTiming> {
+		let section_index = self.index_at_frame(chapter_index, frame_in_chapter)?;
+		let sections = self.for_chapter(chapter_index);
+		let start_frame = sections[section_index].start_frame;
+		let end_frame = sections
+			.get(section_index + 1)
+			.map_or(chapter_length, |section| section.start_frame)
+			.max(start_frame + 1);
+		let frame_in_section = frame_in_chapter - start_frame;
+		Some(SectionTiming {
+			section_index,
+			frame_in_section,
+			length: end_frame - start_frame,
+			progress: (frame_in_section as f32 / (end_frame - start_frame) as f32).min(1.0),
+		})
+	}
+}
+
+/// Where the current frame is within the current section.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SectionTiming {
+	/// The index of the section within its chapter.
+	pub section_index: usize,
+	pub frame_in_section: u64,
+	/// The length of the section in frames.
+	pub length: u64,
+	/// How far through the section the current frame is, from 0 to 1.
+	pub progress: f32,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn sections() -> Sections {
+		Sections::new()
+			.with(0, Section::new("Drop", 100))
+			.with(0, Section::new("Intro", 0))
+			.with(1, Section::new("Build", 50))
+	}
+
+	#[test]
+	fn timing_runs_until_the_next_section() {
+		assert_eq!(
+			sections().timing_at_frame(0, 25, 300),
+			Some(SectionTiming {
+				section_index: 0,
+				frame_in_section: 25,
+				length: 100,
+				progress: 0.25,
+			})
+		);
+	}
+
+	#[test]
+	fn last_section_runs_until_the_end_of_the_chapter() {
+		assert_eq!(
+			sections().timing_at_frame(0, 250, 300),
+			Some(SectionTiming {
+				section_index: 1,
+				frame_in_section: 150,
+				length: 200,
+				progress: 0.75,
+			})
+		);
+	}
+
+	#[test]
+	fn no_timing_before_the_first_section_or_in_chapters_without_sections() {
+		let sections = sections();
+		assert_eq!(sections.timing_at_frame(1, 10, 300), None);
+		assert_eq!(sections.timing_at_frame(2, 10, 300), None);
+	}
+
+	#[test]
+	fn progress_stops_at_one_past_the_end_of_the_chapter() {
+		let timing = sections().timing_at_frame(1, 500, 300).unwrap();
+		assert_eq!(timing.length, 250);
+		assert_eq!(timing.progress, 1.0);
+	}
+}
@@ -0,0 +1,22 @@
+use micro::{graphics::shader::Shader, Context};
+
+/// Declarations shared by full-screen shaders, which are applied by
+/// drawing a canvas while the shader is active.
+const SHADER_HEADER: &str = "#version 330 core
+
+in vec2 TexCoords;
+out vec4 FragColor;
+
+uniform sampler2D Texture;
+uniform vec2 resolution;
+uniform float time;
+// the size of a pixel of a 1080p output in pixels of this canvas
+uniform float pixel_scale;
+";
+
+pub(crate) fn load_shader(ctx: &mut Context, source: &str) -> anyhow::Result<Shader> {
+	Ok(Shader::from_fragment_str(
+		ctx,
+		&format!("{}{}", SHADER_HEADER, source),
+	)?)
+}
@@ -0,0 +1,22 @@
+// `Texture` is everything below the layer, and `layer` is the layer
+// being blended on top of it.
+uniform sampler2D layer;
+uniform float blend_mode;
+uniform float opacity;
+
+void main() {
+	vec4 base = texture(Texture, TexCoords);
+	vec4 top = texture(layer, TexCoords);
+	vec3 blended;
+	int mode = int(blend_mode);
+	if (mode == 1) {
+		blended = base.rgb + top.rgb;
+	} else if (mode == 2) {
+		blended = base.rgb * top.rgb;
+	} else if (mode == 3) {
+		blended = 1.0 - (1.0 - base.rgb) * (1.0 - top.rgb);
+	} else {
+		blended = top.rgb;
+	}
+	FragColor = vec4(mix(base.rgb, blended, top.a * opacity), 1.0);
+}
@@ -0,0 +1,22 @@
+// averages each block of factor x factor pixels of the texture (whose size
+// is `resolution`) into one pixel
+
+uniform float factor;
+
+const int MAX_FACTOR = 4;
+
+void main() {
+	vec2 texel_size = 1.0 / resolution;
+	int samples = int(factor);
+	vec4 sum = vec4(0.0);
+	for (int x = 0; x < MAX_FACTOR; x++) {
+		for (int y = 0; y < MAX_FACTOR; y++) {
+			if (x >= samples || y >= samples) {
+				continue;
+			}
+			vec2 offset = (vec2(x, y) - (factor - 1.0) / 2.0) * texel_size;
+			sum += texture(Texture, TexCoords + offset);
+		}
+	}
+	FragColor = sum / (factor * factor);
+}
@@ -0,0 +1,19 @@
+uniform float threshold;
+uniform float intensity;
+uniform float radius;
+
+void main() {
+	vec4 color = texture(Texture, TexCoords);
+	vec3 glow = vec3(0.0);
+	float total_weight = 0.0;
+	for (int x = -4; x <= 4; x++) {
+		for (int y = -4; y <= 4; y++) {
+			vec2 offset = vec2(x, y) * radius * pixel_scale / resolution;
+			float weight = exp(-float(x * x + y * y) / 8.0);
+			vec3 sample_color = texture(Texture, TexCoords + offset).rgb;
+			glow += max(sample_color - threshold, 0.0) * weight;
+			total_weight += weight;
+		}
+	}
+	FragColor = vec4(color.rgb + glow / total_weight * intensity, color.a);
+}
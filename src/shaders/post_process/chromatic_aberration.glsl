@@ -0,0 +1,11 @@
+uniform float amount;
+
+void main() {
+	// the offset grows towards the edges and is `amount` pixels (at 1080p)
+	// at the corners
+	vec2 offset = (TexCoords - 0.5) * 2.0 * amount * pixel_scale / resolution;
+	vec4 color = texture(Texture, TexCoords);
+	float red = texture(Texture, TexCoords + offset).r;
+	float blue = texture(Texture, TexCoords - offset).b;
+	FragColor = vec4(red, color.g, blue, color.a);
+}
@@ -0,0 +1,17 @@
+uniform float curvature;
+uniform float scanline_intensity;
+uniform float scanline_count;
+
+void main() {
+	vec2 position = TexCoords * 2.0 - 1.0;
+	position += position * position.yx * position.yx * curvature;
+	vec2 coords = position * 0.5 + 0.5;
+	if (any(lessThan(coords, vec2(0.0))) || any(greaterThan(coords, vec2(1.0)))) {
+		FragColor = vec4(0.0, 0.0, 0.0, 1.0);
+		return;
+	}
+	vec4 color = texture(Texture, coords);
+	float scanline = 0.5 + 0.5 * sin(coords.y * scanline_count * 6.28318531);
+	color.rgb *= 1.0 - scanline_intensity * (1.0 - scanline);
+	FragColor = color;
+}
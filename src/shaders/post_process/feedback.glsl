@@ -0,0 +1,14 @@
+uniform sampler2D previous_frame;
+uniform float amount;
+uniform float zoom;
+uniform float rotation;
+
+void main() {
+	vec4 color = texture(Texture, TexCoords);
+	vec2 position = TexCoords - 0.5;
+	float s = sin(rotation);
+	float c = cos(rotation);
+	position = mat2(c, -s, s, c) * position / zoom;
+	vec4 previous = texture(previous_frame, position + 0.5);
+	FragColor = max(color, previous * amount);
+}
@@ -0,0 +1,13 @@
+uniform float intensity;
+uniform float size;
+
+float random(vec2 position) {
+	return fract(sin(dot(position, vec2(12.9898, 78.233))) * 43758.5453);
+}
+
+void main() {
+	vec4 color = texture(Texture, TexCoords);
+	vec2 cell = floor(TexCoords * resolution / (size * pixel_scale));
+	float noise = random(cell + fract(time) * 100.0) - 0.5;
+	FragColor = vec4(color.rgb + noise * intensity, color.a);
+}
@@ -0,0 +1,10 @@
+uniform float intensity;
+uniform float radius;
+uniform float softness;
+
+void main() {
+	vec4 color = texture(Texture, TexCoords);
+	vec2 position = (TexCoords - 0.5) * vec2(resolution.x / resolution.y, 1.0);
+	float brightness = smoothstep(radius, radius - softness, length(position));
+	FragColor = vec4(mix(color.rgb * (1.0 - intensity), color.rgb, brightness), color.a);
+}
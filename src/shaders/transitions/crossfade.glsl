@@ -0,0 +1,6 @@
+uniform sampler2D to;
+uniform float progress;
+
+void main() {
+	FragColor = mix(texture(Texture, TexCoords), texture(to, TexCoords), progress);
+}
@@ -0,0 +1,11 @@
+uniform sampler2D to;
+uniform float progress;
+
+const float EDGE_WIDTH = 0.05;
+
+void main() {
+	// the edge starts fully off the left side and ends fully off the right
+	float edge = progress * (1.0 + EDGE_WIDTH) - EDGE_WIDTH;
+	float amount = smoothstep(edge + EDGE_WIDTH, edge, TexCoords.x);
+	FragColor = mix(texture(Texture, TexCoords), texture(to, TexCoords), amount);
+}
@@ -0,0 +1,8 @@
+uniform sampler2D to;
+uniform float progress;
+
+void main() {
+	vec2 zoomed_coords = (TexCoords - 0.5) / (1.0 + progress) + 0.5;
+	vec4 from_color = texture(Texture, zoomed_coords);
+	FragColor = mix(from_color, texture(to, TexCoords), progress * progress);
+}
@@ -0,0 +1,139 @@
+use std::{path::PathBuf, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+/// Settings for the overlay that shows a chapter's name, artist and
+/// subtitle when the chapter starts.
+///
+/// The overlay is drawn on top of the visualizer (after post-processing),
+/// so it looks the same in the preview and in rendered videos.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TitleCardSettings {
+	pub enabled: bool,
+	pub style: TitleCardStyle,
+	/// A TrueType or OpenType font file. Title cards aren't shown until a
+	/// font is chosen.
+	pub font_path: Option<PathBuf>,
+	/// The height of the chapter name as a fraction of the video height.
+	/// The artist and subtitle are drawn smaller.
+	pub font_size: f32,
+	/// Where the title card is anchored, from (0, 0) at the top left of
+	/// the video to (1, 1) at the bottom right.
+	pub position: [f32; 2],
+	pub text_color: [f32; 4],
+	/// The color of the bar behind lower thirds.
+	pub background_color: [f32; 4],
+	/// How long after the start of the chapter the title card appears.
+	pub delay: Duration,
+	pub fade_in: Duration,
+	/// How long the title card stays fully visible.
+	pub hold: Duration,
+	pub fade_out: Duration,
+}
+
+impl TitleCardSettings {
+	/// Returns how visible the title card is (from 0 to 1) at the given
+	/// time since the start of a chapter.
+	pub fn opacity(&self, time_since_chapter_start: Duration) -> f32 {
+		let Some(time) = time_since_chapter_start.checked_sub(self.delay) else {
+			return 0.0;
+		};
+		if time < self.fade_in {
+			return time.as_secs_f32() / self.fade_in.as_secs_f32();
+		}
+		let Some(time) = time.checked_sub(self.fade_in + self.hold) else {
+			return 1.0;
+		};
+		if time < self.fade_out {
+			return 1.0 - time.as_secs_f32() / self.fade_out.as_secs_f32();
+		}
+		0.0
+	}
+}
+
+impl Default for TitleCardSettings {
+	fn default() -> Self {
+		Self {
+			enabled: false,
+			style: TitleCardStyle::default(),
+			font_path: None,
+			font_size: 0.05,
+			position: [0.05, 0.8],
+			text_color: [1.0, 1.0, 1.0, 1.0],
+			background_color: [0.0, 0.0, 0.0, 0.6],
+			delay: Duration::from_secs(1),
+			fade_in: Duration::from_millis(500),
+			hold: Duration::from_secs(5),
+			fade_out: Duration::from_millis(500),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum TitleCardStyle {
+	/// Left-aligned text on a bar, with the top left corner of the bar at
+	/// the title card's position.
+	#[default]
+	LowerThird,
+	/// Centered text with no background.
+	Centered,
+}
+
+impl TitleCardStyle {
+	pub const NUM_STYLES: usize = 2;
+
+	pub fn label(self) -> &'static str {
+		match self {
+			TitleCardStyle::LowerThird => "Lower third",
+			TitleCardStyle::Centered => "Centered",
+		}
+	}
+}
+
+impl From<usize> for TitleCardStyle {
+	fn from(value: usize) -> Self {
+		match value {
+			0 => Self::LowerThird,
+			1 => Self::Centered,
+			_ => panic!("invalid TitleCardStyle"),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn opacity_at(settings: &TitleCardSettings, milliseconds: u64) -> f32 {
+		settings.opacity(Duration::from_millis(milliseconds))
+	}
+
+	#[test]
+	fn title_cards_fade_in_hold_and_fade_out() {
+		// 1 s delay, 0.5 s fade in, 5 s hold, 0.5 s fade out
+		let settings = TitleCardSettings::default();
+		assert_eq!(opacity_at(&settings, 0), 0.0);
+		assert_eq!(opacity_at(&settings, 1000), 0.0);
+		assert!((opacity_at(&settings, 1250) - 0.5).abs() < 1e-6);
+		assert_eq!(opacity_at(&settings, 1500), 1.0);
+		assert_eq!(opacity_at(&settings, 6500), 1.0);
+		assert!((opacity_at(&settings, 6750) - 0.5).abs() < 1e-6);
+		assert_eq!(opacity_at(&settings, 7000), 0.0);
+		assert_eq!(opacity_at(&settings, 60_000), 0.0);
+	}
+
+	#[test]
+	fn zero_length_fades_switch_instantly() {
+		let settings = TitleCardSettings {
+			delay: Duration::ZERO,
+			fade_in: Duration::ZERO,
+			hold: Duration::from_secs(1),
+			fade_out: Duration::ZERO,
+			..Default::default()
+		};
+		assert_eq!(opacity_at(&settings, 0), 1.0);
+		assert_eq!(opacity_at(&settings, 999), 1.0);
+		assert_eq!(opacity_at(&settings, 1000), 0.0);
+	}
+}
@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// How the runner moves from one chapter to the next.
+///
+/// During a transition, the visualizer is drawn twice per frame: once
+/// with [`VisualizerInfo::current_chapter_index`](crate::VisualizerInfo::current_chapter_index)
+/// set to the previous chapter and once with the new chapter. The two
+/// images are then blended together.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ChapterTransition {
+	pub kind: TransitionKind,
+	/// How long the transition lasts, starting at the beginning of the
+	/// new chapter.
+	pub duration: Duration,
+}
+
+impl Default for ChapterTransition {
+	fn default() -> Self {
+		Self {
+			kind: TransitionKind::Cut,
+			duration: Duration::from_secs(2),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum TransitionKind {
+	/// Switches chapters immediately.
+	#[default]
+	Cut,
+	Crossfade,
+	/// Reveals the new chapter from left to right.
+	Wipe,
+	/// Zooms into the previous chapter while fading to the new one.
+	Zoom,
+	/// Uses the shader from
+	/// [`Visualizer::custom_transition_shader`](crate::Visualizer::custom_transition_shader).
+	Custom,
+}
+
+impl TransitionKind {
+	pub const NUM_KINDS: usize = 5;
+
+	pub fn label(self) -> &'static str {
+		match self {
+			TransitionKind::Cut => "Cut",
+			TransitionKind::Crossfade => "Crossfade",
+			TransitionKind::Wipe => "Wipe",
+			TransitionKind::Zoom => "Zoom",
+			TransitionKind::Custom => "Custom",
+		}
+	}
+}
+
+impl From<usize> for TransitionKind {
+	fn from(value: usize) -> Self {
+		match value {
+			0 => Self::Cut,
+			1 => Self::Crossfade,
+			2 => Self::Wipe,
+			3 => Self::Zoom,
+			4 => Self::Custom,
+			_ => panic!("invalid TransitionKind"),
+		}
+	}
+}
+
+/// A transition between two chapters that's in progress.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransitionInfo {
+	pub from_chapter_index: usize,
+	pub to_chapter_index: usize,
+	/// How far along the transition is, from 0 to 1.
+	pub progress: f32,
+	/// Which of the two chapters is being drawn.
+	pub role: TransitionRole,
+}
+
+/// Which side of a transition [`Visualizer::draw`](crate::Visualizer::draw)
+/// is drawing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransitionRole {
+	/// The chapter that's ending, drawn first.
+	From,
+	/// The chapter that's starting.
+	To,
+}
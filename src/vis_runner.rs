@@ -1,9 +1,41 @@
+mod automation_window;
+mod cached_font;
+mod calibration;
 mod chapters;
+mod downsampler;
+mod live_input;
+mod live_recording;
+mod lyrics;
+mod machine_settings;
+mod markers;
+mod midi;
+mod open_audio;
+mod osc;
+mod params_window;
+mod playback_clock;
+mod playlist;
+mod post_process_window;
+mod post_processor;
+mod progress_overlay;
+mod project;
+mod recording;
 mod rendering;
+mod sections;
+mod title_cards;
+mod tracklist;
+mod transition_renderer;
 mod ui;
 
-use std::{io::Write, process::Child, time::Duration};
+use std::{
+	collections::{BTreeMap, HashSet},
+	io::Write,
+	path::PathBuf,
+	process::Child,
+	sync::Arc,
+	time::{Duration, Instant},
+};
 
+use anyhow::bail;
 use kira::{
 	manager::{AudioManager, AudioManagerSettings},
 	sound::{
@@ -20,61 +52,271 @@ use micro::{
 	math::{UVec2, Vec2},
 	App, Context, Event,
 };
+use serde::{Deserialize, Serialize};
+
+use cached_font::CachedFont;
+use calibration::Calibration;
+use downsampler::Downsampler;
+use live_input::{live_mode, LiveInput};
+use live_recording::{FinishingLiveRecording, LiveRecording};
+use machine_settings::MachineSettings;
+use midi::{MidiBindings, MidiConnection, MidiControl, MidiLearnTarget};
+use osc::{OscBindError, OscServer};
+use playback_clock::PlaybackClock;
+use playlist::Playlist;
+use post_processor::PostProcessor;
+use project::Timeline;
+use recording::{RecordedInput, Take};
+use rendering::BatchRender;
+use transition_renderer::TransitionRenderer;
 
 use crate::{
+	analysis::{Analysis, AudioFeatures, StemFeatures},
 	conversions::{frame_to_seconds, seconds_to_frames, seconds_to_frames_i64},
-	Visualizer, VisualizerInfo,
+	Automation, ChapterTransition, Chapters, Lyrics, LyricsDisplaySettings, Markers, OutputFormat,
+	Params, PostProcessChain, ProgressOverlaySettings, Sections, TitleCardSettings, TransitionInfo,
+	TransitionRole, Visualizer, VisualizerEvent, VisualizerInfo,
 };
 
+/// Keys that control the runner instead of (or as well as) being passed
+/// to the visualizer.
+const RUNNER_HOTKEYS: [Scancode; 8] = [
+	Scancode::Space,
+	Scancode::Left,
+	Scancode::Right,
+	Scancode::Comma,
+	Scancode::Period,
+	Scancode::LeftBracket,
+	Scancode::RightBracket,
+	Scancode::M,
+];
 const FINISHED_SEEK_DETECTION_THRESHOLD: Duration = Duration::from_millis(100);
+const DEFAULT_MARKER_KIND: &str = "hit";
 
 pub struct VisRunner {
 	visualizer: Box<dyn Visualizer>,
 	audio_manager: AudioManager,
+	playlist: Playlist,
+	analysis: Analysis,
+	stem_analyses: Vec<(String, Analysis)>,
+	params: Params,
+	automation: Automation,
+	post_process: PostProcessChain,
+	post_processor: PostProcessor,
+	chapter_transition: ChapterTransition,
+	transition_renderer: TransitionRenderer,
+	title_card_settings: TitleCardSettings,
+	title_card_title_font: Option<CachedFont>,
+	title_card_detail_font: Option<CachedFont>,
+	sections: Sections,
+	markers: Arc<Markers>,
+	/// The kind given to markers added at the playhead.
+	new_marker_kind: String,
+	lyrics_path: Option<PathBuf>,
+	lyrics: Option<Lyrics>,
+	lyrics_display_settings: LyricsDisplaySettings,
+	lyrics_font: Option<CachedFont>,
+	progress_overlay_settings: ProgressOverlaySettings,
+	progress_overlay_font: Option<CachedFont>,
+	output_formats: Vec<OutputFormat>,
+	/// The format that's previewed, or that's being rendered.
+	output_format_index: usize,
+	take: Option<Take>,
+	/// Input events from recorded takes, sorted by frame.
+	recorded_events: Vec<(u64, RecordedInput)>,
+	/// The timelines of audio files that were open before, keyed by
+	/// their paths.
+	other_timelines: BTreeMap<PathBuf, Timeline>,
+	midi_connection: Option<MidiConnection>,
+	/// Why the last attempt to connect to a MIDI port failed.
+	midi_connection_error: Option<String>,
+	/// The MIDI input ports, read when the MIDI window opens.
+	midi_port_names: Option<Vec<String>>,
+	midi_bindings: MidiBindings,
+	midi_learn_target: Option<MidiLearnTarget>,
+	/// Buttons that are held down, so holding a button only triggers its
+	/// transport action once.
+	pressed_midi_controls: HashSet<MidiControl>,
+	osc_server: Option<OscServer>,
+	osc_bind_error: Option<OscBindError>,
+	/// The OSC port while it's being edited in the OSC window.
+	osc_port_input: Option<u16>,
+	/// Cached so the input devices aren't enumerated every time the
+	/// live input menu is drawn.
+	live_input_device_names: Option<Vec<String>>,
+	live_recording: Option<LiveRecording>,
+	/// Why the last audio file couldn't be opened.
+	audio_error: Option<String>,
+	/// Problems loading or saving the project, shown in the menu bar
+	/// until they're dismissed.
+	project_errors: Vec<String>,
+	finishing_live_recordings: Vec<FinishingLiveRecording>,
+	/// The path of the last finished live recording, or why it couldn't
+	/// be finished.
+	live_recording_result: Option<Result<PathBuf, String>>,
 	mode: Mode,
 	num_frames: u64,
-	previous_frame: u64,
+	drawn_state: Option<DrawnState>,
 	canvas: Canvas,
+	downsampler: Downsampler,
 	live_resolution: LiveResolution,
 	rendering_settings: RenderingSettings,
 	show_rendering_window: bool,
+	show_params_window: bool,
+	show_automation_window: bool,
+	show_midi_window: bool,
+	show_osc_window: bool,
+	show_post_process_window: bool,
+	show_title_cards_window: bool,
+	show_sections_window: bool,
+	sections_import_error: Option<String>,
+	show_markers_window: bool,
+	markers_import_error: Option<String>,
+	show_lyrics_window: bool,
+	show_progress_overlay_window: bool,
+	selected_automation_param_index: usize,
+	automation_keyframes_on_beats: bool,
+	selected_effect_kind_index: usize,
+	last_output_directory: Option<PathBuf>,
 	volume: Volume,
+	machine_settings: MachineSettings,
+	calibration: Option<Calibration>,
+	/// Whether a text field had keyboard focus in the last UI pass, in
+	/// which case key presses are typing rather than hotkeys.
+	egui_wants_keyboard_input: bool,
 }
 
 impl VisRunner {
 	pub fn new(ctx: &mut Context, visualizer: Box<dyn Visualizer>) -> anyhow::Result<Self> {
 		let audio_manager = AudioManager::new(AudioManagerSettings::default())?;
-		let sound_data = StreamingSoundData::from_file(visualizer.audio_path())?;
-		let num_frames =
-			seconds_to_frames(sound_data.duration().as_secs_f64(), visualizer.frame_rate());
-		let canvas = Canvas::new(
-			ctx,
-			visualizer.video_resolution(),
-			CanvasSettings::default(),
-		);
-		let rendering_settings = if let Some(chapters) = visualizer.chapters() {
-			RenderingSettings {
-				start_chapter_index: 0,
-				end_chapter_index: chapters.len() - 1,
-			}
+		let live_input_source = visualizer.live_input();
+		let playlist = if live_input_source.is_some() {
+			Playlist::empty()
 		} else {
-			RenderingSettings::default()
+			Playlist::load(
+				visualizer
+					.playlist()
+					.unwrap_or_else(|| vec![visualizer.audio_path()]),
+				visualizer.frame_rate(),
+			)?
 		};
-		Ok(VisRunner {
-			visualizer,
-			audio_manager,
-			mode: Mode::Stopped {
-				data: Some(sound_data),
+		let mode = match live_input_source {
+			Some(source) => live_mode(source)?,
+			None => Mode::Stopped {
+				data: Some(playlist.sound_data()?),
 				start_frame: 0,
 			},
+		};
+		let num_frames = playlist.num_frames(visualizer.frame_rate());
+		let analysis = Analysis::from_files(playlist.paths(), visualizer.frame_rate())?;
+		let stem_analyses = analyze_stems(visualizer.as_ref())?;
+		let params = visualizer.params();
+		let post_process = visualizer.post_process();
+		let output_formats = visualizer.output_formats();
+		let Some(default_output_format) = output_formats.first() else {
+			bail!("the visualizer must have at least one output format");
+		};
+		let initial_resolution = default_output_format.resolution;
+		let post_processor = PostProcessor::new(ctx)?;
+		let chapter_transition = visualizer.chapter_transition();
+		let title_card_settings = visualizer.title_cards();
+		let sections = visualizer.sections();
+		let markers = Arc::new(visualizer.markers());
+		let lyrics_path = visualizer.lyrics_path();
+		let lyrics_display_settings = visualizer.lyrics_display();
+		let progress_overlay_settings = visualizer.progress_overlay();
+		let transition_renderer =
+			TransitionRenderer::new(ctx, visualizer.custom_transition_shader().as_deref())?;
+		let canvas = Canvas::new(ctx, initial_resolution, CanvasSettings::default());
+		let downsampler = Downsampler::new(ctx)?;
+		let mut rendering_settings = RenderingSettings::default();
+		rendering_settings.reset_chapter_range(resolve_chapters(visualizer.as_ref(), &playlist));
+		let mut vis_runner = VisRunner {
+			visualizer,
+			audio_manager,
+			playlist,
+			analysis,
+			stem_analyses,
+			params,
+			automation: Automation::default(),
+			post_process,
+			post_processor,
+			chapter_transition,
+			transition_renderer,
+			title_card_settings,
+			title_card_title_font: None,
+			title_card_detail_font: None,
+			sections,
+			markers,
+			new_marker_kind: DEFAULT_MARKER_KIND.to_string(),
+			lyrics_path: None,
+			lyrics: None,
+			lyrics_display_settings,
+			lyrics_font: None,
+			progress_overlay_settings,
+			progress_overlay_font: None,
+			output_formats,
+			output_format_index: 0,
+			take: None,
+			recorded_events: vec![],
+			other_timelines: BTreeMap::new(),
+			midi_connection: None,
+			midi_connection_error: None,
+			midi_port_names: None,
+			midi_bindings: MidiBindings::default(),
+			midi_learn_target: None,
+			pressed_midi_controls: HashSet::new(),
+			osc_server: None,
+			osc_bind_error: None,
+			osc_port_input: None,
+			live_input_device_names: None,
+			live_recording: None,
+			audio_error: None,
+			project_errors: vec![],
+			finishing_live_recordings: vec![],
+			live_recording_result: None,
+			mode,
 			num_frames,
-			previous_frame: u64::MAX,
+			drawn_state: None,
 			canvas,
+			downsampler,
 			live_resolution: LiveResolution::Full,
 			rendering_settings,
 			show_rendering_window: false,
+			show_params_window: false,
+			show_automation_window: false,
+			show_midi_window: false,
+			show_osc_window: false,
+			show_post_process_window: false,
+			show_title_cards_window: false,
+			show_sections_window: false,
+			sections_import_error: None,
+			show_markers_window: false,
+			markers_import_error: None,
+			show_lyrics_window: false,
+			show_progress_overlay_window: false,
+			selected_automation_param_index: 0,
+			automation_keyframes_on_beats: true,
+			selected_effect_kind_index: 0,
+			last_output_directory: None,
 			volume: Volume::Decibels(0.0),
-		})
+			machine_settings: MachineSettings::load(),
+			calibration: None,
+			egui_wants_keyboard_input: false,
+		};
+		if let Some(lyrics_path) = lyrics_path {
+			if let Err(error) = vis_runner.load_lyrics(lyrics_path) {
+				vis_runner
+					.project_errors
+					.push(format!("Could not load lyrics: {}", error));
+			}
+		}
+		vis_runner.load_project()?;
+		if let Some(port_name) = vis_runner.machine_settings.midi_port_name.clone() {
+			// the controller might not be plugged in, which is fine
+			vis_runner.midi_connection = MidiConnection::connect(&port_name).ok();
+		}
+		Ok(vis_runner)
 	}
 
 	fn playing(&self) -> bool {
@@ -82,6 +324,7 @@ impl VisRunner {
 			Mode::Stopped { .. } => false,
 			Mode::PlayingOrPaused { sound, .. } => sound.state() == PlaybackState::Playing,
 			Mode::Rendering { .. } => false,
+			Mode::Live { .. } => false,
 		}
 	}
 
@@ -89,12 +332,19 @@ impl VisRunner {
 		match &self.mode {
 			Mode::Stopped { start_frame, .. } => *start_frame,
 			Mode::PlayingOrPaused {
-				sound,
+				clock,
 				in_progress_seek,
+				..
 			} => in_progress_seek.unwrap_or_else(|| {
-				seconds_to_frames(sound.position(), self.visualizer.frame_rate())
+				let position =
+					(clock.position() - self.machine_settings.av_offset_seconds()).max(0.0);
+				seconds_to_frames(position, self.visualizer.frame_rate())
 			}),
 			Mode::Rendering { current_frame, .. } => *current_frame,
+			Mode::Live { start_time, .. } => seconds_to_frames(
+				start_time.elapsed().as_secs_f64(),
+				self.visualizer.frame_rate(),
+			),
 		}
 	}
 
@@ -102,12 +352,11 @@ impl VisRunner {
 		match &mut self.mode {
 			Mode::Stopped { data, start_frame } => {
 				let mut data = data.take().unwrap();
-				data.settings.start_position = PlaybackPosition::Seconds(frame_to_seconds(
-					*start_frame,
-					self.visualizer.frame_rate(),
-				));
+				let start_position = frame_to_seconds(*start_frame, self.visualizer.frame_rate());
+				data.settings.start_position = PlaybackPosition::Seconds(start_position);
 				self.mode = Mode::PlayingOrPaused {
 					sound: self.audio_manager.play(data)?,
+					clock: PlaybackClock::new(start_position),
 					in_progress_seek: None,
 				};
 			}
@@ -115,6 +364,8 @@ impl VisRunner {
 				sound.resume(Tween::default());
 			}
 			Mode::Rendering { .. } => unreachable!("not supported in rendering mode"),
+			// there's nothing to play back in live mode
+			Mode::Live { .. } => {}
 		}
 		Ok(())
 	}
@@ -143,11 +394,13 @@ impl VisRunner {
 			Mode::PlayingOrPaused {
 				sound,
 				in_progress_seek,
+				..
 			} => {
 				sound.seek_to(frame_to_seconds(frame, self.visualizer.frame_rate()));
 				*in_progress_seek = Some(frame);
 			}
 			Mode::Rendering { .. } => unreachable!("not supported in rendering mode"),
+			Mode::Live { .. } => {}
 		}
 		Ok(())
 	}
@@ -164,26 +417,100 @@ impl VisRunner {
 
 	fn vis_info(&self) -> VisualizerInfo {
 		let current_frame = self.current_frame();
+		let section = self.section_at_frame(current_frame);
+		// the timeline (stems, automation and markers) belongs to the
+		// audio file, so none of it applies to live input
+		let live = self.live();
 		VisualizerInfo {
 			resolution: self.current_resolution(),
+			output_format: self.output_format().clone(),
 			current_frame,
 			current_time: Duration::from_secs_f64(frame_to_seconds(
 				current_frame,
 				self.visualizer.frame_rate(),
 			)),
+			num_frames: if live { 0 } else { self.num_frames },
 			current_chapter_index: self
-				.visualizer
 				.chapters()
 				.and_then(|chapters| chapters.index_at_frame(current_frame)),
+			current_chapter: self
+				.chapters()
+				.and_then(|chapters| chapters.at_frame(current_frame))
+				.cloned(),
+			chapter_timing: self
+				.chapters()
+				.and_then(|chapters| chapters.timing_at_frame(current_frame, self.num_frames)),
+			current_section: section.as_ref().map(|(section, _)| section.clone()),
+			section_timing: section.map(|(_, timing)| timing),
+			transition: self.transition_at_frame(current_frame),
+			audio: match &self.mode {
+				Mode::Live { features, .. } => *features,
+				_ => self.analysis.at_frame(current_frame),
+			},
+			stems: if live {
+				StemFeatures::default()
+			} else {
+				StemFeatures(
+					self.stem_analyses
+						.iter()
+						.map(|(name, analysis)| (name.clone(), analysis.at_frame(current_frame)))
+						.collect(),
+				)
+			},
+			params: if live {
+				self.params.values()
+			} else {
+				self.automation.evaluate(
+					&self.params,
+					current_frame,
+					self.visualizer.tempo(),
+					self.visualizer.frame_rate(),
+				)
+			},
+			markers: if live {
+				Arc::default()
+			} else {
+				self.markers.clone()
+			},
+			lyrics: self.active_lyric_at_frame(current_frame),
 		}
 	}
 
+	fn chapters(&self) -> Option<&Chapters> {
+		if self.live() {
+			return None;
+		}
+		resolve_chapters(self.visualizer.as_ref(), &self.playlist)
+	}
+
 	fn current_resolution(&self) -> UVec2 {
-		if matches!(self.mode, Mode::Rendering { .. }) {
-			self.visualizer.video_resolution()
+		if let Mode::Rendering {
+			output_format,
+			supersampling,
+			..
+		} = &self.mode
+		{
+			return output_format.resolution * supersampling.factor();
+		}
+		self.output_format().resolution / self.live_resolution.as_divisor()
+	}
+
+	/// Returns the formats that will be rendered with the current
+	/// rendering settings.
+	fn formats_to_render(&self) -> impl Iterator<Item = &OutputFormat> {
+		let formats: &[OutputFormat] = if self.rendering_settings.render_all_formats {
+			&self.output_formats
 		} else {
-			self.visualizer.video_resolution() / self.live_resolution.as_divisor()
+			std::slice::from_ref(&self.output_formats[self.output_format_index])
+		};
+		formats.iter()
+	}
+
+	fn output_format(&self) -> &OutputFormat {
+		if let Mode::Rendering { output_format, .. } = &self.mode {
+			return output_format;
 		}
+		&self.output_formats[self.output_format_index]
 	}
 }
 
@@ -194,23 +521,56 @@ impl App<anyhow::Error> for VisRunner {
 		egui_ctx: &micro::ui::Context,
 	) -> Result<(), anyhow::Error> {
 		self.render_main_menu(ctx, egui_ctx)?;
+		self.render_section_timeline(egui_ctx)?;
 		self.render_rendering_window(ctx, egui_ctx)?;
+		self.render_calibration_window(egui_ctx)?;
+		self.render_params_window(egui_ctx)?;
+		self.render_automation_window(egui_ctx)?;
+		self.render_midi_window(egui_ctx);
+		self.render_osc_window(egui_ctx)?;
+		self.render_post_process_window(egui_ctx)?;
+		self.render_title_cards_window(egui_ctx);
+		self.render_sections_window(egui_ctx)?;
+		self.render_markers_window(egui_ctx)?;
+		self.render_lyrics_window(egui_ctx)?;
+		self.render_progress_overlay_window(egui_ctx);
 		self.visualizer.ui(ctx, egui_ctx, self.vis_info())?;
+		self.egui_wants_keyboard_input = egui_ctx.wants_keyboard_input();
 		Ok(())
 	}
 
 	fn event(&mut self, ctx: &mut Context, event: Event) -> Result<(), anyhow::Error> {
+		if let Event::DropFile { filename, .. } = &event {
+			self.open_dropped_file(ctx, filename.as_ref())?;
+		}
+		// keys typed into a text field aren't hotkeys
 		if let Event::KeyPressed { key, .. } = event {
-			match key {
-				Scancode::Space => self.toggle_playback()?,
-				Scancode::Left => self.seek_by_seconds(-10.0)?,
-				Scancode::Right => self.seek_by_seconds(10.0)?,
-				Scancode::Comma => self.go_to_previous_chapter()?,
-				Scancode::Period => self.go_to_next_chapter()?,
-				_ => {}
+			if !self.egui_wants_keyboard_input {
+				match key {
+					Scancode::Space => self.toggle_playback()?,
+					Scancode::Left => self.seek_by_seconds(-10.0)?,
+					Scancode::Right => self.seek_by_seconds(10.0)?,
+					Scancode::Comma => self.go_to_previous_chapter()?,
+					Scancode::Period => self.go_to_next_chapter()?,
+					Scancode::LeftBracket => self.go_to_previous_section()?,
+					Scancode::RightBracket => self.go_to_next_section()?,
+					Scancode::M => self.add_marker_at_playhead(),
+					_ => {}
+				}
 			}
 		}
 
+		// runner hotkeys control playback, so replaying them to the
+		// visualizer when rendering wouldn't make sense
+		let runner_hotkey = matches!(
+			event,
+			Event::KeyPressed { key, .. } | Event::KeyReleased { key, .. }
+				if RUNNER_HOTKEYS.contains(&key)
+		);
+		let event = VisualizerEvent::Input(event);
+		if !runner_hotkey {
+			self.record_input(RecordedInput::Event(event.clone()));
+		}
 		self.visualizer.event(ctx, self.vis_info(), event)?;
 
 		Ok(())
@@ -223,6 +583,7 @@ impl App<anyhow::Error> for VisRunner {
 
 		if let Mode::PlayingOrPaused {
 			sound,
+			clock,
 			in_progress_seek,
 		} = &mut self.mode
 		{
@@ -238,16 +599,32 @@ impl App<anyhow::Error> for VisRunner {
 					<= detection_threshold_frames
 				{
 					*in_progress_seek = None;
+					clock.resync(sound.position());
 				}
 			}
+			clock.update(sound.position(), sound.state() == PlaybackState::Playing);
 			if sound.state() == PlaybackState::Stopped {
+				self.stop_recording();
 				self.mode = Mode::Stopped {
-					data: Some(StreamingSoundData::from_file(self.visualizer.audio_path())?),
+					data: Some(self.playlist.sound_data()?),
 					start_frame: 0,
 				};
 			}
 		}
 
+		self.update_live_input()?;
+		self.update_finishing_live_recordings();
+
+		if let Some(calibration) = &mut self.calibration {
+			calibration.update(&mut self.audio_manager)?;
+		}
+
+		self.handle_midi_messages(ctx)?;
+		self.update_osc_server();
+		self.handle_osc_messages(ctx)?;
+		self.record_param_changes();
+		self.replay_recorded_events(ctx)?;
+
 		self.visualizer.update(ctx, self.vis_info(), delta_time)?;
 
 		Ok(())
@@ -256,9 +633,61 @@ impl App<anyhow::Error> for VisRunner {
 	fn draw(&mut self, ctx: &mut Context) -> Result<(), anyhow::Error> {
 		ctx.clear(LinSrgba::BLACK);
 		let current_frame = self.current_frame();
-		if current_frame != self.previous_frame {
-			self.visualizer.draw(ctx, self.vis_info(), &self.canvas)?;
-			self.previous_frame = current_frame;
+		let vis_info = self.vis_info();
+		let drawn_state = DrawnState {
+			vis_info: vis_info.clone(),
+			post_process: self.post_process.clone(),
+			chapter_transition: self.chapter_transition,
+			title_card_settings: self.title_card_settings.clone(),
+			lyrics_display_settings: self.lyrics_display_settings.clone(),
+			progress_overlay_settings: self.progress_overlay_settings.clone(),
+		};
+		// redraw when settings change, even if the frame hasn't
+		if self.drawn_state.as_ref() != Some(&drawn_state) {
+			if let Some(transition) = vis_info.transition {
+				let from_vis_info = VisualizerInfo {
+					transition: Some(TransitionInfo {
+						role: TransitionRole::From,
+						..transition
+					}),
+					current_chapter_index: Some(transition.from_chapter_index),
+					current_chapter: self
+						.chapters()
+						.and_then(|chapters| chapters.get(transition.from_chapter_index))
+						.cloned(),
+					chapter_timing: self.chapters().map(|chapters| {
+						chapters.timing(
+							transition.from_chapter_index,
+							current_frame,
+							self.num_frames,
+						)
+					}),
+					..vis_info.clone()
+				};
+				let from_canvas = self
+					.transition_renderer
+					.from_canvas(ctx, self.canvas.size());
+				self.visualizer.draw(ctx, from_vis_info, from_canvas)?;
+				self.visualizer.draw(ctx, vis_info, &self.canvas)?;
+				self.transition_renderer.apply(
+					ctx,
+					&self.canvas,
+					self.chapter_transition.kind,
+					transition.progress,
+				)?;
+			} else {
+				self.visualizer.draw(ctx, vis_info, &self.canvas)?;
+			}
+			self.post_processor.apply(
+				ctx,
+				&self.canvas,
+				&self.post_process,
+				frame_to_seconds(current_frame, self.visualizer.frame_rate()) as f32,
+			)?;
+			self.draw_title_card(ctx)?;
+			self.draw_lyrics(ctx)?;
+			self.draw_progress_overlay(ctx)?;
+			self.drawn_state = Some(drawn_state);
 		}
 		let max_horizontal_scale = ctx.window_size().x as f32 / self.canvas.size().x as f32;
 		let max_vertical_scale = ctx.window_size().y as f32 / self.canvas.size().y as f32;
@@ -268,14 +697,24 @@ impl App<anyhow::Error> for VisRunner {
 			.scaled_2d(Vec2::splat(scale))
 			.translated_2d(ctx.window_size().as_vec2() / 2.0)
 			.draw(ctx);
+		self.write_live_recording_frames(ctx)?;
 		if let Mode::Rendering {
 			end_frame,
 			current_frame,
 			canvas_read_buffer,
 			ffmpeg_process,
+			supersampling,
+			..
 		} = &mut self.mode
 		{
-			self.canvas.read(ctx, canvas_read_buffer);
+			let supersampling_factor = supersampling.factor();
+			if supersampling_factor > 1 {
+				self.downsampler
+					.apply(ctx, &self.canvas, supersampling_factor)?
+					.read(ctx, canvas_read_buffer);
+			} else {
+				self.canvas.read(ctx, canvas_read_buffer);
+			}
 			let ffmpeg_stdin = ffmpeg_process.stdin.as_mut().unwrap();
 			let write_result = ffmpeg_stdin.write_all(canvas_read_buffer);
 			if write_result.is_err() {
@@ -283,7 +722,7 @@ impl App<anyhow::Error> for VisRunner {
 			} else {
 				*current_frame += 1;
 				if *current_frame > *end_frame {
-					self.stop_rendering(ctx)?;
+					self.finish_rendering(ctx)?;
 				}
 			}
 		}
@@ -291,6 +730,17 @@ impl App<anyhow::Error> for VisRunner {
 	}
 }
 
+/// Everything the canvas was last drawn from.
+#[derive(PartialEq)]
+struct DrawnState {
+	vis_info: VisualizerInfo,
+	post_process: PostProcessChain,
+	chapter_transition: ChapterTransition,
+	title_card_settings: TitleCardSettings,
+	lyrics_display_settings: LyricsDisplaySettings,
+	progress_overlay_settings: ProgressOverlaySettings,
+}
+
 #[allow(clippy::large_enum_variant)]
 enum Mode {
 	Stopped {
@@ -299,6 +749,7 @@ enum Mode {
 	},
 	PlayingOrPaused {
 		sound: StreamingSoundHandle<FromFileError>,
+		clock: PlaybackClock,
 		in_progress_seek: Option<u64>,
 	},
 	Rendering {
@@ -306,11 +757,28 @@ enum Mode {
 		current_frame: u64,
 		canvas_read_buffer: Vec<u8>,
 		ffmpeg_process: Child,
+		/// Files ffmpeg reads chapters and subtitles from, which are
+		/// deleted once it's done.
+		temp_files: Vec<PathBuf>,
+		/// The format being rendered, which stays the same even if the
+		/// selected format changes.
+		output_format: OutputFormat,
+		supersampling: Supersampling,
+		/// Set when every output format is being rendered one after
+		/// another.
+		batch: Option<BatchRender>,
+	},
+	Live {
+		input: LiveInput,
+		start_time: Instant,
+		/// The features of the audio that arrived during the last update.
+		features: AudioFeatures,
 	},
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
 enum LiveResolution {
+	#[default]
 	Full,
 	Half,
 	Quarter,
@@ -347,8 +815,168 @@ impl From<usize> for LiveResolution {
 	}
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+/// The largest width or height a supersampled canvas can have. Every
+/// desktop GPU supports textures at least this big, and each of the
+/// full-size canvases used while rendering takes up 256 MB at this size.
+const MAX_SUPERSAMPLED_SIZE: u32 = 8192;
+
+/// How many times larger than the output resolution the visualizer is
+/// drawn when rendering. The image is shrunk back down before it's sent
+/// to ffmpeg, which smooths out jagged edges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+enum Supersampling {
+	#[default]
+	Off,
+	X2,
+	X4,
+}
+
+impl Supersampling {
+	const NUM_FACTORS: usize = 3;
+
+	fn factor(self) -> u32 {
+		match self {
+			Supersampling::Off => 1,
+			Supersampling::X2 => 2,
+			Supersampling::X4 => 4,
+		}
+	}
+
+	/// Returns whether a video with the given resolution can be rendered
+	/// with this much supersampling.
+	fn supports(self, resolution: UVec2) -> bool {
+		self == Supersampling::Off
+			|| resolution.max_element() * self.factor() <= MAX_SUPERSAMPLED_SIZE
+	}
+
+	fn label(self) -> &'static str {
+		match self {
+			Supersampling::Off => "Off",
+			Supersampling::X2 => "2x",
+			Supersampling::X4 => "4x",
+		}
+	}
+}
+
+impl From<usize> for Supersampling {
+	fn from(value: usize) -> Self {
+		match value {
+			0 => Self::Off,
+			1 => Self::X2,
+			2 => Self::X4,
+			_ => panic!("invalid Supersampling"),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(default)]
 struct RenderingSettings {
 	start_chapter_index: usize,
 	end_chapter_index: usize,
+	encoder_profile: EncoderProfile,
+	/// Whether to render every output format instead of just the
+	/// selected one.
+	render_all_formats: bool,
+	supersampling: Supersampling,
+	/// Whether to add the lyrics to rendered videos as a subtitle track.
+	embed_lyrics: bool,
+}
+
+impl RenderingSettings {
+	fn reset_chapter_range(&mut self, chapters: Option<&Chapters>) {
+		self.start_chapter_index = 0;
+		self.end_chapter_index = chapters.map_or(0, |chapters| chapters.len() - 1);
+	}
+
+	fn clamp_chapter_range(&mut self, chapters: Option<&Chapters>) {
+		let num_chapters = chapters.map_or(1, Chapters::len);
+		if self.end_chapter_index >= num_chapters
+			|| self.start_chapter_index > self.end_chapter_index
+		{
+			self.reset_chapter_range(chapters);
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+enum EncoderProfile {
+	#[default]
+	H264,
+	H265,
+	ProRes,
+}
+
+impl EncoderProfile {
+	const NUM_PROFILES: usize = 3;
+
+	fn label(self) -> &'static str {
+		match self {
+			EncoderProfile::H264 => "H.264",
+			EncoderProfile::H265 => "H.265",
+			EncoderProfile::ProRes => "ProRes",
+		}
+	}
+
+	fn file_extension(self) -> &'static str {
+		match self {
+			EncoderProfile::H264 | EncoderProfile::H265 => "mp4",
+			EncoderProfile::ProRes => "mov",
+		}
+	}
+
+	fn ffmpeg_args(self) -> &'static [&'static str] {
+		match self {
+			EncoderProfile::H264 => &["-c:v", "libx264"],
+			EncoderProfile::H265 => &["-c:v", "libx265", "-tag:v", "hvc1"],
+			EncoderProfile::ProRes => &["-c:v", "prores_ks", "-profile:v", "3"],
+		}
+	}
+}
+
+impl From<usize> for EncoderProfile {
+	fn from(value: usize) -> Self {
+		match value {
+			0 => Self::H264,
+			1 => Self::H265,
+			2 => Self::ProRes,
+			_ => panic!("invalid EncoderProfile"),
+		}
+	}
+}
+
+fn analyze_stems(visualizer: &dyn Visualizer) -> anyhow::Result<Vec<(String, Analysis)>> {
+	visualizer
+		.stems()
+		.into_iter()
+		.map(|stem| {
+			let analysis = Analysis::from_file(&stem.path, visualizer.frame_rate())?;
+			Ok((stem.name, analysis))
+		})
+		.collect()
+}
+
+/// Returns the visualizer's chapters, falling back to the chapters
+/// generated from the playlist.
+fn resolve_chapters<'a>(
+	visualizer: &'a dyn Visualizer,
+	playlist: &'a Playlist,
+) -> Option<&'a Chapters> {
+	visualizer.chapters().or(playlist.chapters())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn supersampling_is_limited_by_the_largest_side() {
+		let uhd = UVec2::new(3840, 2160);
+		let full_hd = UVec2::new(1920, 1080);
+		assert!(Supersampling::Off.supports(UVec2::splat(100_000)));
+		assert!(Supersampling::X2.supports(uhd));
+		assert!(!Supersampling::X4.supports(uhd));
+		assert!(Supersampling::X4.supports(full_hd));
+		assert!(!Supersampling::X4.supports(UVec2::new(1080, 2160)));
+	}
 }
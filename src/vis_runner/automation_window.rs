@@ -0,0 +1,315 @@
+use micro::ui::{
+	pos2, vec2, Align2, Checkbox, Color32, ComboBox, DragValue, FontId, Grid, Rect, Sense, Shape,
+	Stroke, Ui, Window,
+};
+
+use crate::{
+	automation::{AutomationTrack, Interpolation, Keyframe, Tempo, TimelinePosition},
+	params::{Param, ParamKind, ParamValue},
+};
+
+use super::{params_window::render_value_editor, VisRunner};
+
+const CURVE_HEIGHT: f32 = 150.0;
+const KEYFRAME_HANDLE_RADIUS: f32 = 4.0;
+
+impl VisRunner {
+	pub fn render_automation_window(
+		&mut self,
+		egui_ctx: &micro::ui::Context,
+	) -> anyhow::Result<()> {
+		if self.params.is_empty() {
+			return Ok(());
+		}
+		let current_frame = self.current_frame();
+		let num_frames = self.num_frames;
+		let tempo = self.visualizer.tempo();
+		let frame_rate = self.visualizer.frame_rate();
+		let mut seek_destination = None;
+		Window::new("Automation")
+			.open(&mut self.show_automation_window)
+			.show(egui_ctx, |ui| {
+				let params = &self.params;
+				ComboBox::new("automation_param", "Parameter").show_index(
+					ui,
+					&mut self.selected_automation_param_index,
+					params.len(),
+					|i| &params.0[i].name,
+				);
+				if tempo.is_some() {
+					ui.add(Checkbox::new(
+						&mut self.automation_keyframes_on_beats,
+						"Place new keyframes on beats",
+					));
+				}
+				let param = &self.params.0[self.selected_automation_param_index];
+				let track = self.automation.0.entry(param.name.clone()).or_default();
+				let timeline = Timeline {
+					num_frames,
+					current_frame,
+					tempo,
+					frame_rate,
+					keyframes_on_beats: self.automation_keyframes_on_beats,
+				};
+				if let Some(range) = scalar_range(&param.kind) {
+					seek_destination = render_curve(ui, track, &timeline, range);
+				}
+				render_keyframe_list(ui, param, track, &timeline);
+				if ui.button("Add keyframe at playhead").clicked() {
+					track.insert(
+						Keyframe {
+							position: timeline.playhead_position(),
+							value: param.value.clone(),
+							interpolation: Interpolation::default(),
+						},
+						tempo,
+						frame_rate,
+					);
+				}
+			});
+		self.automation
+			.0
+			.retain(|_, track| !track.keyframes.is_empty());
+		if let Some(frame) = seek_destination {
+			self.seek(frame)?;
+		}
+		Ok(())
+	}
+}
+
+struct Timeline {
+	num_frames: u64,
+	current_frame: u64,
+	tempo: Option<Tempo>,
+	frame_rate: u64,
+	/// Whether new keyframes are placed on beats instead of frames. Only
+	/// used if the visualizer has a tempo.
+	keyframes_on_beats: bool,
+}
+
+impl Timeline {
+	/// Returns the position of the playhead, snapped to the nearest
+	/// beat if new keyframes are placed on beats.
+	fn playhead_position(&self) -> TimelinePosition {
+		if self.tempo.is_none() || !self.keyframes_on_beats {
+			return TimelinePosition::Frame(self.current_frame);
+		}
+		match TimelinePosition::Beat(0.0).with_frame(
+			self.current_frame as f64,
+			self.tempo,
+			self.frame_rate,
+		) {
+			TimelinePosition::Beat(beat) => TimelinePosition::Beat(beat.round()),
+			position => position,
+		}
+	}
+
+	fn to_frame(&self, position: TimelinePosition) -> f64 {
+		position.to_frame(self.tempo, self.frame_rate)
+	}
+}
+
+/// Draws the curve of a numeric parameter over the whole timeline and
+/// lets the user drag keyframes around. Returns a frame to seek to if
+/// the user clicked on the curve.
+fn render_curve(
+	ui: &mut Ui,
+	track: &mut AutomationTrack,
+	timeline: &Timeline,
+	(min, max): (f32, f32),
+) -> Option<u64> {
+	let (response, painter) =
+		ui.allocate_painter(vec2(ui.available_width(), CURVE_HEIGHT), Sense::click());
+	let rect = response.rect;
+	let num_frames = timeline.num_frames.max(1) as f32;
+	let frame_to_x = |frame: f64| rect.left() + frame as f32 / num_frames * rect.width();
+	let x_to_frame = |x: f32| ((x - rect.left()) / rect.width() * num_frames) as f64;
+	let value_to_y = |value: f32| rect.bottom() - (value - min) / (max - min) * rect.height();
+	let y_to_value = |y: f32| min + (rect.bottom() - y) / rect.height() * (max - min);
+	painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+
+	let points = (0..rect.width() as usize)
+		.filter_map(|i| {
+			let x = rect.left() + i as f32;
+			let value = track.value_at(x_to_frame(x), timeline.tempo, timeline.frame_rate)?;
+			Some(pos2(x, value_to_y(param_value_to_scalar(&value)?)))
+		})
+		.collect::<Vec<_>>();
+	painter.add(Shape::line(
+		points,
+		Stroke::new(1.0, ui.visuals().text_color()),
+	));
+
+	let mut finished_dragging = false;
+	for (i, keyframe) in track.keyframes.iter_mut().enumerate() {
+		let Some(value) = param_value_to_scalar(&keyframe.value) else {
+			continue;
+		};
+		let center = pos2(
+			frame_to_x(timeline.to_frame(keyframe.position)),
+			value_to_y(value),
+		);
+		let handle_size = KEYFRAME_HANDLE_RADIUS * 2.0;
+		let handle_response = ui.interact(
+			Rect::from_center_size(center, vec2(handle_size, handle_size)),
+			response.id.with(i),
+			Sense::drag(),
+		);
+		let center = center + handle_response.drag_delta();
+		if handle_response.dragged() {
+			keyframe.position = keyframe.position.with_frame(
+				x_to_frame(center.x),
+				timeline.tempo,
+				timeline.frame_rate,
+			);
+			set_scalar(
+				&mut keyframe.value,
+				y_to_value(center.y).clamp(min.min(max), min.max(max)),
+			);
+		}
+		finished_dragging |= handle_response.drag_stopped();
+		let color = if handle_response.hovered() || handle_response.dragged() {
+			Color32::YELLOW
+		} else {
+			ui.visuals().text_color()
+		};
+		painter.circle_filled(center, KEYFRAME_HANDLE_RADIUS, color);
+	}
+	// the keyframes aren't re-sorted mid-drag so the dragged handle keeps
+	// its id
+	if finished_dragging {
+		track.sort(timeline.tempo, timeline.frame_rate);
+	}
+
+	let playhead_x = frame_to_x(timeline.current_frame as f64);
+	painter.vline(
+		playhead_x,
+		rect.y_range(),
+		Stroke::new(1.0, Color32::LIGHT_BLUE),
+	);
+	painter.text(
+		rect.left_top(),
+		Align2::LEFT_TOP,
+		format!("{:.2}", max),
+		FontId::monospace(10.0),
+		ui.visuals().weak_text_color(),
+	);
+	painter.text(
+		rect.left_bottom(),
+		Align2::LEFT_BOTTOM,
+		format!("{:.2}", min),
+		FontId::monospace(10.0),
+		ui.visuals().weak_text_color(),
+	);
+
+	if response.clicked() {
+		let x = response.interact_pointer_pos()?.x;
+		return Some(x_to_frame(x).clamp(0.0, timeline.num_frames as f64) as u64);
+	}
+	None
+}
+
+fn render_keyframe_list(
+	ui: &mut Ui,
+	param: &Param,
+	track: &mut AutomationTrack,
+	timeline: &Timeline,
+) {
+	let mut removed_keyframe_index = None;
+	let mut finished_editing_position = false;
+	Grid::new("keyframes").num_columns(4).show(ui, |ui| {
+		for (i, keyframe) in track.keyframes.iter_mut().enumerate() {
+			let response = match &mut keyframe.position {
+				TimelinePosition::Frame(frame) => ui.add(
+					DragValue::new(frame)
+						.range(0..=timeline.num_frames)
+						.suffix(" f"),
+				),
+				TimelinePosition::Beat(beat) => {
+					ui.add(DragValue::new(beat).speed(0.25).suffix(" beats"))
+				}
+			};
+			finished_editing_position |= response.drag_stopped() || response.lost_focus();
+			render_value_editor(
+				ui,
+				&format!("{}_{}", param.name, i),
+				&param.kind,
+				&mut keyframe.value,
+			);
+			render_interpolation_editor(ui, i, &mut keyframe.interpolation);
+			if ui.button("x").clicked() {
+				removed_keyframe_index = Some(i);
+			}
+			ui.end_row();
+		}
+	});
+	if let Some(i) = removed_keyframe_index {
+		track.keyframes.remove(i);
+	}
+	if finished_editing_position {
+		track.sort(timeline.tempo, timeline.frame_rate);
+	}
+}
+
+fn render_interpolation_editor(
+	ui: &mut Ui,
+	keyframe_index: usize,
+	interpolation: &mut Interpolation,
+) {
+	ui.horizontal(|ui| {
+		ComboBox::from_id_source(("interpolation", keyframe_index))
+			.selected_text(interpolation_label(*interpolation))
+			.show_ui(ui, |ui| {
+				for option in [
+					Interpolation::Linear,
+					Interpolation::Step,
+					Interpolation::EASE_IN_OUT,
+				] {
+					let selected =
+						std::mem::discriminant(interpolation) == std::mem::discriminant(&option);
+					let response = ui.selectable_label(selected, interpolation_label(option));
+					if response.clicked() && !selected {
+						*interpolation = option;
+					}
+				}
+			});
+		if let Interpolation::Bezier { x1, y1, x2, y2 } = interpolation {
+			ui.add(DragValue::new(x1).range(0.0..=1.0).speed(0.01));
+			ui.add(DragValue::new(y1).speed(0.01));
+			ui.add(DragValue::new(x2).range(0.0..=1.0).speed(0.01));
+			ui.add(DragValue::new(y2).speed(0.01));
+		}
+	});
+}
+
+fn interpolation_label(interpolation: Interpolation) -> &'static str {
+	match interpolation {
+		Interpolation::Linear => "Linear",
+		Interpolation::Step => "Step",
+		Interpolation::Bezier { .. } => "Bezier",
+	}
+}
+
+fn scalar_range(kind: &ParamKind) -> Option<(f32, f32)> {
+	match kind {
+		ParamKind::Float { range } => Some((*range.start(), *range.end())),
+		ParamKind::Int { range } => Some((*range.start() as f32, *range.end() as f32)),
+		_ => None,
+	}
+}
+
+fn param_value_to_scalar(value: &ParamValue) -> Option<f32> {
+	match value {
+		ParamValue::Float(value) => Some(*value),
+		ParamValue::Int(value) => Some(*value as f32),
+		_ => None,
+	}
+}
+
+fn set_scalar(value: &mut ParamValue, scalar: f32) {
+	match value {
+		ParamValue::Float(value) => *value = scalar,
+		ParamValue::Int(value) => *value = scalar.round() as i64,
+		_ => {}
+	}
+}
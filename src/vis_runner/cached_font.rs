@@ -0,0 +1,60 @@
+use std::path::{Path, PathBuf};
+
+use micro::{
+	graphics::text::{Font, FontSettings},
+	Context,
+};
+
+/// A font for an overlay, which is kept around until the font file or
+/// its size changes.
+pub struct CachedFont {
+	path: PathBuf,
+	size: f32,
+	/// The font, or why it couldn't be loaded. Failures are cached too,
+	/// so a missing font file isn't read again every frame.
+	font: Result<Font, String>,
+}
+
+impl CachedFont {
+	/// Returns the font in `cache`, loading it first if the cache is empty
+	/// or holds a different font or size. Returns `None` if the font
+	/// couldn't be loaded, in which case [`CachedFont::load_error`] says
+	/// why.
+	pub fn get<'a>(
+		cache: &'a mut Option<CachedFont>,
+		ctx: &mut Context,
+		path: &Path,
+		size: f32,
+	) -> Option<&'a Font> {
+		let outdated = cache
+			.as_ref()
+			.map_or(true, |cached| cached.path != path || cached.size != size);
+		if outdated {
+			let font = Font::from_file(
+				ctx,
+				path,
+				FontSettings {
+					scale: size,
+					..Default::default()
+				},
+			)
+			.map_err(|error| format!("Could not load {}: {}", path.display(), error));
+			*cache = Some(CachedFont {
+				path: path.to_path_buf(),
+				size,
+				font,
+			});
+		}
+		cache.as_ref().unwrap().font.as_ref().ok()
+	}
+
+	/// Returns why the font at `path` couldn't be loaded the last time it
+	/// was drawn.
+	pub fn load_error<'a>(cache: &'a Option<CachedFont>, path: &Path) -> Option<&'a str> {
+		cache
+			.as_ref()
+			.filter(|cached| cached.path == path)
+			.and_then(|cached| cached.font.as_ref().err())
+			.map(String::as_str)
+	}
+}
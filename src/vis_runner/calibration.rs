@@ -0,0 +1,127 @@
+use std::sync::Arc;
+
+use kira::{
+	clock::{ClockHandle, ClockSpeed},
+	dsp::Frame,
+	manager::AudioManager,
+	sound::static_sound::{StaticSoundData, StaticSoundSettings},
+	StartTime,
+};
+use micro::ui::{Color32, Sense, Slider, Window};
+
+use super::VisRunner;
+
+const CALIBRATION_BPM: f64 = 120.0;
+const CLICK_SAMPLE_RATE: u32 = 48000;
+const CLICK_FREQUENCY: f32 = 1000.0;
+const CLICK_DURATION_SECONDS: f32 = 0.02;
+/// How much of each beat the screen stays lit for.
+const FLASH_FRACTION: f64 = 0.1;
+const FLASH_SIZE: f32 = 200.0;
+
+/// Plays a click on every beat and flashes in time with it so the
+/// user can tune the A/V offset by eye and ear.
+pub struct Calibration {
+	clock: ClockHandle,
+	click: StaticSoundData,
+	last_scheduled_tick: Option<u64>,
+}
+
+impl Calibration {
+	pub fn new(audio_manager: &mut AudioManager) -> anyhow::Result<Self> {
+		let clock = audio_manager.add_clock(ClockSpeed::TicksPerMinute(CALIBRATION_BPM))?;
+		clock.start();
+		Ok(Self {
+			clock,
+			click: click_sound_data(),
+			last_scheduled_tick: None,
+		})
+	}
+
+	pub fn update(&mut self, audio_manager: &mut AudioManager) -> anyhow::Result<()> {
+		let next_tick = self.clock.time() + 1;
+		if self.last_scheduled_tick == Some(next_tick.ticks) {
+			return Ok(());
+		}
+		let mut click = self.click.clone();
+		click.settings.start_time = StartTime::ClockTime(next_tick);
+		audio_manager.play(click)?;
+		self.last_scheduled_tick = Some(next_tick.ticks);
+		Ok(())
+	}
+
+	/// Returns `true` if the flash should be visible, taking the A/V
+	/// offset into account.
+	pub fn flashing(&self, av_offset_seconds: f64) -> bool {
+		let beats_per_second = CALIBRATION_BPM / 60.0;
+		let position = self.clock.time().ticks as f64 + self.clock.fractional_position()
+			- av_offset_seconds * beats_per_second;
+		position >= 0.0 && position.fract() < FLASH_FRACTION
+	}
+}
+
+impl VisRunner {
+	pub fn render_calibration_window(
+		&mut self,
+		egui_ctx: &micro::ui::Context,
+	) -> anyhow::Result<()> {
+		if self.calibration.is_none() {
+			return Ok(());
+		}
+		let mut open = true;
+		// saved when the user lets go of the slider rather than on
+		// every step of the drag
+		let mut offset_edited = false;
+		Window::new("A/V Calibration")
+			.open(&mut open)
+			.show(egui_ctx, |ui| {
+				ui.label("Adjust the offset until the flash lines up with the click.");
+				let response = ui.add(
+					Slider::new(&mut self.machine_settings.av_offset_ms, -500.0..=500.0)
+						.suffix(" ms"),
+				);
+				offset_edited = response.drag_stopped() || response.lost_focus();
+				let flashing = self.calibration.as_ref().is_some_and(|calibration| {
+					calibration.flashing(self.machine_settings.av_offset_seconds())
+				});
+				let (rect, _) =
+					ui.allocate_exact_size(micro::ui::vec2(FLASH_SIZE, FLASH_SIZE), Sense::hover());
+				let color = if flashing {
+					Color32::WHITE
+				} else {
+					Color32::BLACK
+				};
+				ui.painter().rect_filled(rect, 0.0, color);
+			});
+		if offset_edited || !open {
+			self.machine_settings.save()?;
+		}
+		if !open {
+			self.calibration = None;
+		}
+		Ok(())
+	}
+
+	pub fn start_calibration(&mut self) -> anyhow::Result<()> {
+		self.pause()?;
+		self.calibration = Some(Calibration::new(&mut self.audio_manager)?);
+		Ok(())
+	}
+}
+
+fn click_sound_data() -> StaticSoundData {
+	let num_frames = (CLICK_SAMPLE_RATE as f32 * CLICK_DURATION_SECONDS) as usize;
+	let frames = (0..num_frames)
+		.map(|i| {
+			let time = i as f32 / CLICK_SAMPLE_RATE as f32;
+			let envelope = 1.0 - i as f32 / num_frames as f32;
+			Frame::from_mono((time * CLICK_FREQUENCY * std::f32::consts::TAU).sin() * envelope)
+		})
+		.collect::<Vec<_>>();
+	StaticSoundData {
+		sample_rate: CLICK_SAMPLE_RATE,
+		frames: Arc::from(frames),
+		settings: StaticSoundSettings::default(),
+		slice: None,
+	}
+}
@@ -1,14 +1,21 @@
 use std::time::Duration;
 
-use crate::conversions::frame_to_seconds;
+use micro::ui::{ComboBox, SelectableLabel, Slider, Ui};
+
+use crate::{
+	conversions::{frame_to_seconds, seconds_to_frames},
+	TransitionInfo, TransitionKind, TransitionRole,
+};
 
 use super::VisRunner;
 
 const BEGINNING_OF_CHAPTER_THRESHOLD: Duration = Duration::from_secs(2);
+const NO_CUSTOM_TRANSITION_SHADER: &str =
+	"The visualizer doesn't provide a custom transition shader";
 
 impl VisRunner {
 	pub fn go_to_chapter(&mut self, chapter_index: usize) -> anyhow::Result<()> {
-		let Some(chapters) = self.visualizer.chapters() else {
+		let Some(chapters) = self.chapters() else {
 			return Ok(());
 		};
 		self.seek(chapters[chapter_index].start_frame)?;
@@ -16,7 +23,7 @@ impl VisRunner {
 	}
 
 	pub fn go_to_next_chapter(&mut self) -> anyhow::Result<()> {
-		let Some(chapters) = self.visualizer.chapters() else {
+		let Some(chapters) = self.chapters() else {
 			return Ok(());
 		};
 		let current_chapter_index = chapters
@@ -30,7 +37,7 @@ impl VisRunner {
 	}
 
 	pub fn go_to_previous_chapter(&mut self) -> anyhow::Result<()> {
-		let Some(chapters) = self.visualizer.chapters() else {
+		let Some(chapters) = self.chapters() else {
 			return Ok(());
 		};
 		let current_chapter_index = chapters
@@ -51,4 +58,66 @@ impl VisRunner {
 		}
 		Ok(())
 	}
+
+	/// Returns the transition into the current chapter if it's still in
+	/// progress.
+	pub fn transition_at_frame(&self, frame: u64) -> Option<TransitionInfo> {
+		if self.chapter_transition.kind == TransitionKind::Cut {
+			return None;
+		}
+		let chapters = self.chapters()?;
+		let chapter_index = chapters.index_at_frame(frame)?;
+		if chapter_index == 0 {
+			return None;
+		}
+		let duration_frames = seconds_to_frames(
+			self.chapter_transition.duration.as_secs_f64(),
+			self.visualizer.frame_rate(),
+		);
+		let frames_since_start_of_chapter = frame - chapters[chapter_index].start_frame;
+		if frames_since_start_of_chapter >= duration_frames {
+			return None;
+		}
+		Some(TransitionInfo {
+			from_chapter_index: chapter_index - 1,
+			to_chapter_index: chapter_index,
+			progress: frames_since_start_of_chapter as f32 / duration_frames as f32,
+			role: TransitionRole::To,
+		})
+	}
+
+	pub fn render_transition_menu(&mut self, ui: &mut Ui) {
+		if self.chapters().is_none() {
+			return;
+		}
+		ui.menu_button("Transition", |ui| {
+			let transition_renderer = &self.transition_renderer;
+			let selected_kind = &mut self.chapter_transition.kind;
+			ComboBox::new("transition_kind", "Kind")
+				.selected_text(selected_kind.label())
+				.show_ui(ui, |ui| {
+					for index in 0..TransitionKind::NUM_KINDS {
+						let kind = TransitionKind::from(index);
+						// custom transitions are only available if the
+						// visualizer has a shader for them
+						let response = ui
+							.add_enabled(
+								transition_renderer.supports(kind),
+								SelectableLabel::new(*selected_kind == kind, kind.label()),
+							)
+							.on_disabled_hover_text(NO_CUSTOM_TRANSITION_SHADER);
+						if response.clicked() {
+							*selected_kind = kind;
+						}
+					}
+				});
+			let mut duration = self.chapter_transition.duration.as_secs_f64();
+			ui.add(
+				Slider::new(&mut duration, 0.1..=10.0)
+					.suffix(" s")
+					.text("Duration"),
+			);
+			self.chapter_transition.duration = Duration::from_secs_f64(duration);
+		});
+	}
 }
@@ -0,0 +1,52 @@
+use micro::{
+	color::{ColorConstants, LinSrgba},
+	graphics::{shader::Shader, Canvas, CanvasSettings},
+	math::Vec2,
+	Context,
+};
+
+use crate::shaders::load_shader;
+
+/// Shrinks a supersampled canvas to the output resolution, averaging
+/// the pixels that make up each output pixel.
+pub struct Downsampler {
+	shader: Shader,
+	output_canvas: Option<Canvas>,
+}
+
+impl Downsampler {
+	pub fn new(ctx: &mut Context) -> anyhow::Result<Self> {
+		Ok(Self {
+			shader: load_shader(ctx, include_str!("../shaders/downsample.glsl"))?,
+			output_canvas: None,
+		})
+	}
+
+	/// Returns a canvas with the contents of `canvas` shrunk by `factor`.
+	pub fn apply(
+		&mut self,
+		ctx: &mut Context,
+		canvas: &Canvas,
+		factor: u32,
+	) -> anyhow::Result<&Canvas> {
+		let output_size = canvas.size() / factor;
+		if self
+			.output_canvas
+			.as_ref()
+			.map_or(true, |output_canvas| output_canvas.size() != output_size)
+		{
+			self.output_canvas = Some(Canvas::new(ctx, output_size, CanvasSettings::default()));
+		}
+		let output_canvas = self.output_canvas.as_ref().unwrap();
+		self.shader
+			.send_vec2(ctx, "resolution", canvas.size().as_vec2())?;
+		self.shader.send_f32(ctx, "factor", factor as f32)?;
+		{
+			let ctx = &mut output_canvas.render_to(ctx);
+			ctx.clear(LinSrgba::BLACK);
+			let ctx = &mut ctx.push_shader(&self.shader);
+			canvas.scaled_2d(Vec2::splat(1.0 / factor as f32)).draw(ctx);
+		}
+		Ok(output_canvas)
+	}
+}
@@ -0,0 +1,414 @@
+use std::{
+	path::Path,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc, Mutex,
+	},
+	thread,
+	time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail};
+use cpal::{
+	traits::{DeviceTrait, HostTrait, StreamTrait},
+	FromSample, InputCallbackInfo, SampleFormat, SizedSample, Stream, StreamConfig,
+};
+use kira::{dsp::Frame, tween::Tween};
+use micro::ui::Ui;
+use rfd::FileDialog;
+
+use crate::{
+	analysis::{AudioFeatures, FeatureAccumulator},
+	audio_decoder::FileDecoder,
+	LiveInputSource,
+};
+
+use super::{open_audio::AUDIO_FILE_EXTENSIONS, Mode, VisRunner};
+
+/// Audio that's captured from an input device (or a file standing in for
+/// one) as it arrives.
+pub struct LiveInput {
+	source: LiveInputSource,
+	sample_rate: u32,
+	/// Frames that have arrived since the last call to
+	/// [`LiveInput::take_frames`].
+	frames: Arc<Mutex<Vec<Frame>>>,
+	/// The last error from the device or the thread streaming the file.
+	error: Arc<Mutex<Option<String>>>,
+	_backend: LiveInputBackend,
+}
+
+impl LiveInput {
+	pub fn open(source: LiveInputSource) -> anyhow::Result<Self> {
+		let frames = Arc::new(Mutex::new(vec![]));
+		let error = Arc::new(Mutex::new(None));
+		let (backend, sample_rate) = match &source {
+			LiveInputSource::DefaultDevice => open_device(None, frames.clone(), error.clone())?,
+			LiveInputSource::Device(name) => {
+				open_device(Some(name), frames.clone(), error.clone())?
+			}
+			LiveInputSource::File(path) => open_file(path, frames.clone(), error.clone())?,
+		};
+		Ok(Self {
+			source,
+			sample_rate,
+			frames,
+			error,
+			_backend: backend,
+		})
+	}
+
+	pub fn source(&self) -> &LiveInputSource {
+		&self.source
+	}
+
+	pub fn sample_rate(&self) -> u32 {
+		self.sample_rate
+	}
+
+	pub fn take_frames(&self) -> Vec<Frame> {
+		std::mem::take(&mut *self.frames.lock().unwrap())
+	}
+
+	/// Returns the last error that happened while capturing audio.
+	pub fn error(&self) -> Option<String> {
+		self.error.lock().unwrap().clone()
+	}
+}
+
+/// Keeps the audio flowing until the [`LiveInput`] is dropped.
+enum LiveInputBackend {
+	Device { _stream: Stream },
+	File { _file_input: FileInput },
+}
+
+/// Streams an audio file into the live input buffer at the rate it
+/// would play back, looping when it reaches the end.
+struct FileInput {
+	stopped: Arc<AtomicBool>,
+}
+
+impl Drop for FileInput {
+	fn drop(&mut self) {
+		self.stopped.store(true, Ordering::Relaxed);
+	}
+}
+
+impl VisRunner {
+	pub fn live(&self) -> bool {
+		matches!(self.mode, Mode::Live { .. })
+	}
+
+	pub fn start_live_input(&mut self, source: LiveInputSource) -> anyhow::Result<()> {
+		self.stop_live_recording();
+		if let Mode::PlayingOrPaused { sound, .. } = &mut self.mode {
+			sound.stop(Tween::default());
+		}
+		self.stop_recording();
+		self.mode = live_mode(source)?;
+		self.drawn_state = None;
+		self.show_rendering_window = false;
+		Ok(())
+	}
+
+	pub fn stop_live_input(&mut self) -> anyhow::Result<()> {
+		if !self.live() || self.playlist.is_empty() {
+			return Ok(());
+		}
+		self.stop_live_recording();
+		self.mode = Mode::Stopped {
+			data: Some(self.playlist.sound_data()?),
+			start_frame: 0,
+		};
+		self.drawn_state = None;
+		Ok(())
+	}
+
+	/// Analyzes the audio that's arrived since the last update and
+	/// writes it to the live recording if there is one.
+	pub fn update_live_input(&mut self) -> anyhow::Result<()> {
+		let Mode::Live {
+			input, features, ..
+		} = &mut self.mode
+		else {
+			return Ok(());
+		};
+		let frames = input.take_frames();
+		if frames.is_empty() {
+			return Ok(());
+		}
+		if let Some(live_recording) = &mut self.live_recording {
+			live_recording.write_audio(&frames)?;
+		}
+		*features = analyze_frames(&frames);
+		Ok(())
+	}
+
+	pub fn render_live_input_menu(&mut self, ui: &mut Ui) -> anyhow::Result<()> {
+		let mut selected_source = None;
+		let mut stop_requested = false;
+		ui.menu_button("Live input", |ui| {
+			let device_names = self
+				.live_input_device_names
+				.get_or_insert_with(|| input_device_names().unwrap_or_default());
+			let current_source = match &self.mode {
+				Mode::Live { input, .. } => Some(input.source()),
+				_ => None,
+			};
+			if ui
+				.selectable_label(
+					current_source == Some(&LiveInputSource::DefaultDevice),
+					"Default device",
+				)
+				.clicked()
+			{
+				ui.close_menu();
+				selected_source = Some(LiveInputSource::DefaultDevice);
+			}
+			for name in device_names.iter() {
+				let source = LiveInputSource::Device(name.clone());
+				if ui
+					.selectable_label(current_source == Some(&source), name)
+					.clicked()
+				{
+					ui.close_menu();
+					selected_source = Some(source);
+				}
+			}
+			if ui.button("Refresh devices").clicked() {
+				self.live_input_device_names = None;
+			}
+			ui.separator();
+			if ui.button("Audio file…").clicked() {
+				ui.close_menu();
+				selected_source = FileDialog::new()
+					.add_filter("audio", AUDIO_FILE_EXTENSIONS)
+					.pick_file()
+					.map(LiveInputSource::File);
+			}
+			if current_source.is_some() && !self.playlist.is_empty() {
+				ui.separator();
+				if ui.button("Stop live input").clicked() {
+					ui.close_menu();
+					stop_requested = true;
+				}
+			}
+		});
+		if let Some(source) = selected_source {
+			self.start_live_input(source)?;
+		}
+		if stop_requested {
+			self.stop_live_input()?;
+		}
+		Ok(())
+	}
+}
+
+pub fn live_mode(source: LiveInputSource) -> anyhow::Result<Mode> {
+	Ok(Mode::Live {
+		input: LiveInput::open(source)?,
+		start_time: Instant::now(),
+		features: Default::default(),
+	})
+}
+
+fn input_device_names() -> anyhow::Result<Vec<String>> {
+	Ok(cpal::default_host()
+		.input_devices()?
+		.filter_map(|device| device.name().ok())
+		.collect())
+}
+
+fn analyze_frames(frames: &[Frame]) -> AudioFeatures {
+	let mut accumulator = FeatureAccumulator::default();
+	for frame in frames {
+		accumulator.add(*frame);
+	}
+	accumulator.finish()
+}
+
+fn open_device(
+	name: Option<&str>,
+	frames: Arc<Mutex<Vec<Frame>>>,
+	error: Arc<Mutex<Option<String>>>,
+) -> anyhow::Result<(LiveInputBackend, u32)> {
+	let host = cpal::default_host();
+	let device = match name {
+		Some(name) => host
+			.input_devices()?
+			.find(|device| device.name().is_ok_and(|device_name| device_name == name))
+			.ok_or_else(|| anyhow!("no input device named {}", name))?,
+		None => host
+			.default_input_device()
+			.ok_or_else(|| anyhow!("no default input device"))?,
+	};
+	let supported_config = device.default_input_config()?;
+	let sample_format = supported_config.sample_format();
+	let config: StreamConfig = supported_config.into();
+	let stream = match sample_format {
+		SampleFormat::I16 => build_input_stream::<i16>(&device, &config, frames, error)?,
+		SampleFormat::I32 => build_input_stream::<i32>(&device, &config, frames, error)?,
+		SampleFormat::U16 => build_input_stream::<u16>(&device, &config, frames, error)?,
+		SampleFormat::F32 => build_input_stream::<f32>(&device, &config, frames, error)?,
+		SampleFormat::F64 => build_input_stream::<f64>(&device, &config, frames, error)?,
+		sample_format => bail!("unsupported sample format: {:?}", sample_format),
+	};
+	stream.play()?;
+	Ok((
+		LiveInputBackend::Device { _stream: stream },
+		config.sample_rate.0,
+	))
+}
+
+fn build_input_stream<T>(
+	device: &cpal::Device,
+	config: &StreamConfig,
+	frames: Arc<Mutex<Vec<Frame>>>,
+	error: Arc<Mutex<Option<String>>>,
+) -> anyhow::Result<Stream>
+where
+	T: SizedSample,
+	f32: FromSample<T>,
+{
+	let num_channels = config.channels as usize;
+	let stream = device.build_input_stream(
+		config,
+		move |data: &[T], _: &InputCallbackInfo| {
+			let mut frames = frames.lock().unwrap();
+			frames.extend(data.chunks(num_channels).map(|samples| {
+				let left = f32::from_sample_(samples[0]);
+				let right = samples
+					.get(1)
+					.map_or(left, |sample| f32::from_sample_(*sample));
+				Frame::new(left, right)
+			}));
+		},
+		move |stream_error| *error.lock().unwrap() = Some(stream_error.to_string()),
+		None,
+	)?;
+	Ok(stream)
+}
+
+fn open_file(
+	path: &Path,
+	frames: Arc<Mutex<Vec<Frame>>>,
+	error: Arc<Mutex<Option<String>>>,
+) -> anyhow::Result<(LiveInputBackend, u32)> {
+	let decoder = FileDecoder::open(path)?;
+	let sample_rate = decoder.sample_rate();
+	let stopped = Arc::new(AtomicBool::new(false));
+	thread::spawn({
+		let path = path.to_path_buf();
+		let stopped = stopped.clone();
+		move || {
+			if let Err(stream_error) = stream_file(decoder, frames, &stopped) {
+				*error.lock().unwrap() = Some(format!(
+					"error streaming {}: {}",
+					path.display(),
+					stream_error
+				));
+			}
+		}
+	});
+	let backend = LiveInputBackend::File {
+		_file_input: FileInput { stopped },
+	};
+	Ok((backend, sample_rate))
+}
+
+fn stream_file(
+	mut decoder: FileDecoder,
+	frames: Arc<Mutex<Vec<Frame>>>,
+	stopped: &AtomicBool,
+) -> anyhow::Result<()> {
+	let start_time = Instant::now();
+	let mut num_frames_streamed = 0;
+	let mut num_frames_streamed_this_loop = 0;
+	while !stopped.load(Ordering::Relaxed) {
+		let Some(chunk) = decoder.decode()? else {
+			// looping a file with no audio would never wait
+			if num_frames_streamed_this_loop == 0 {
+				bail!("the file has no audio");
+			}
+			num_frames_streamed_this_loop = 0;
+			decoder.seek(0)?;
+			continue;
+		};
+		num_frames_streamed += chunk.len();
+		num_frames_streamed_this_loop += chunk.len();
+		// hand over each chunk once it would've finished arriving from
+		// a real device
+		let chunk_end_time =
+			Duration::from_secs_f64(num_frames_streamed as f64 / decoder.sample_rate() as f64);
+		if let Some(wait_time) = chunk_end_time.checked_sub(start_time.elapsed()) {
+			thread::sleep(wait_time);
+		}
+		frames.lock().unwrap().extend(chunk);
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::path::PathBuf;
+
+	use hound::{SampleFormat, WavSpec, WavWriter};
+
+	use crate::vis_runner::rendering::temp_file_path;
+
+	use super::*;
+
+	const SAMPLE_RATE: u32 = 8000;
+
+	/// Writes a square wave at half amplitude to a temporary WAV file.
+	fn write_square_wave(num_frames: u32) -> PathBuf {
+		let path = temp_file_path("live-input-test.wav");
+		let spec = WavSpec {
+			channels: 1,
+			sample_rate: SAMPLE_RATE,
+			bits_per_sample: 16,
+			sample_format: SampleFormat::Int,
+		};
+		let mut writer = WavWriter::create(&path, spec).unwrap();
+		for i in 0..num_frames {
+			let sample = if i % 2 == 0 {
+				i16::MAX / 2
+			} else {
+				i16::MIN / 2
+			};
+			writer.write_sample(sample).unwrap();
+		}
+		writer.finalize().unwrap();
+		path
+	}
+
+	#[test]
+	fn file_input_streams_the_file_in_real_time() {
+		let path = write_square_wave(SAMPLE_RATE / 10);
+		let input = LiveInput::open(LiveInputSource::File(path.clone())).unwrap();
+		assert_eq!(input.sample_rate, SAMPLE_RATE);
+		thread::sleep(Duration::from_millis(300));
+		let frames = input.take_frames();
+		drop(input);
+		std::fs::remove_file(path).unwrap();
+		// the file is 100ms long, so it should have looped at least once
+		assert!(frames.len() > (SAMPLE_RATE / 10) as usize);
+		let features = analyze_frames(&frames);
+		assert!((features.rms - 0.5).abs() < 0.01);
+		assert!((features.peak - 0.5).abs() < 0.01);
+	}
+
+	#[test]
+	fn file_input_reports_files_with_no_audio() {
+		let path = write_square_wave(0);
+		let error = match LiveInput::open(LiveInputSource::File(path.clone())) {
+			Ok(input) => {
+				thread::sleep(Duration::from_millis(100));
+				input.error()
+			}
+			Err(error) => Some(error.to_string()),
+		};
+		std::fs::remove_file(path).unwrap();
+		assert!(error.is_some());
+	}
+}
@@ -0,0 +1,203 @@
+use std::{
+	fs::{self, File},
+	io::{BufWriter, Write},
+	path::PathBuf,
+	process::{Child, Command},
+	thread::{self, JoinHandle},
+};
+
+use anyhow::bail;
+use hound::{SampleFormat, WavSpec, WavWriter};
+use kira::dsp::Frame;
+use micro::Context;
+
+use super::{rendering::canvas_read_buffer, Mode, VisRunner};
+
+/// A live input session that's being recorded to video in real time.
+pub struct LiveRecording {
+	video_path: PathBuf,
+	/// The video without sound. It's combined with the recorded audio
+	/// once the recording stops.
+	silent_video_path: PathBuf,
+	audio_path: PathBuf,
+	wav_writer: WavWriter<BufWriter<File>>,
+	ffmpeg_process: Child,
+	start_frame: u64,
+	num_frames_written: u64,
+	canvas_read_buffer: Vec<u8>,
+}
+
+/// A stopped live recording whose audio is being added to the video on
+/// another thread.
+pub struct FinishingLiveRecording {
+	video_path: PathBuf,
+	thread: JoinHandle<anyhow::Result<()>>,
+}
+
+impl FinishingLiveRecording {
+	/// Waits for the video to be finished.
+	pub fn finish(self) -> Result<PathBuf, String> {
+		let result = match self.thread.join() {
+			Ok(result) => result.map_err(|error| error.to_string()),
+			Err(_) => Err("the thread finishing the video panicked".to_string()),
+		};
+		result
+			.map(|()| self.video_path.clone())
+			.map_err(|error| format!("could not finish {}: {}", self.video_path.display(), error))
+	}
+}
+
+impl LiveRecording {
+	pub fn write_audio(&mut self, frames: &[Frame]) -> anyhow::Result<()> {
+		for frame in frames {
+			self.wav_writer.write_sample(frame.left)?;
+			self.wav_writer.write_sample(frame.right)?;
+		}
+		Ok(())
+	}
+}
+
+impl VisRunner {
+	pub fn live_recording(&self) -> bool {
+		self.live_recording.is_some()
+	}
+
+	pub fn start_live_recording(&mut self) -> anyhow::Result<()> {
+		let Mode::Live { input, .. } = &self.mode else {
+			return Ok(());
+		};
+		let sample_rate = input.sample_rate();
+		let Some(video_path) = self.pick_video_path() else {
+			return Ok(());
+		};
+		let encoder_profile = self.rendering_settings.encoder_profile;
+		let silent_video_path =
+			video_path.with_extension(format!("silent.{}", encoder_profile.file_extension()));
+		let audio_path = video_path.with_extension("wav");
+		let wav_writer = WavWriter::create(
+			&audio_path,
+			WavSpec {
+				channels: 2,
+				sample_rate,
+				bits_per_sample: 32,
+				sample_format: SampleFormat::Float,
+			},
+		)?;
+		let ffmpeg_process = self
+			.ffmpeg_command(self.canvas.size())
+			.args(encoder_profile.ffmpeg_args())
+			.arg("-r")
+			.arg(self.visualizer.frame_rate().to_string())
+			.arg(&silent_video_path)
+			.spawn()?;
+		self.live_recording = Some(LiveRecording {
+			video_path,
+			silent_video_path,
+			audio_path,
+			wav_writer,
+			ffmpeg_process,
+			start_frame: self.current_frame(),
+			num_frames_written: 0,
+			canvas_read_buffer: canvas_read_buffer(self.canvas.size()),
+		});
+		Ok(())
+	}
+
+	/// Stops recording. The video is finished and the recorded audio is
+	/// added to it on another thread; the result shows up in the main
+	/// menu once it's done.
+	pub fn stop_live_recording(&mut self) {
+		let Some(live_recording) = self.live_recording.take() else {
+			return;
+		};
+		self.finishing_live_recordings.push(FinishingLiveRecording {
+			video_path: live_recording.video_path.clone(),
+			thread: thread::spawn(move || finish_live_recording(live_recording)),
+		});
+	}
+
+	/// Collects the results of live recordings that have finished in the
+	/// background.
+	pub fn update_finishing_live_recordings(&mut self) {
+		let (finished, unfinished) = std::mem::take(&mut self.finishing_live_recordings)
+			.into_iter()
+			.partition::<Vec<_>, _>(|recording| recording.thread.is_finished());
+		self.finishing_live_recordings = unfinished;
+		for recording in finished {
+			self.live_recording_result = Some(recording.finish());
+		}
+	}
+
+	pub fn toggle_live_recording(&mut self) -> anyhow::Result<()> {
+		if self.live_recording() {
+			self.stop_live_recording();
+			Ok(())
+		} else {
+			self.start_live_recording()
+		}
+	}
+
+	/// Sends the canvas to ffmpeg once for every frame that's elapsed
+	/// since the last write. If the app couldn't keep up with the frame
+	/// rate, the same image is written multiple times so the video stays
+	/// in sync with the audio.
+	pub fn write_live_recording_frames(&mut self, ctx: &mut Context) -> anyhow::Result<()> {
+		let current_frame = self.current_frame();
+		let Some(live_recording) = &mut self.live_recording else {
+			return Ok(());
+		};
+		let num_frames_due = current_frame.saturating_sub(live_recording.start_frame) + 1;
+		if live_recording.num_frames_written >= num_frames_due {
+			return Ok(());
+		}
+		self.canvas
+			.read(ctx, &mut live_recording.canvas_read_buffer);
+		let ffmpeg_stdin = live_recording.ffmpeg_process.stdin.as_mut().unwrap();
+		let write_result = (live_recording.num_frames_written..num_frames_due)
+			.try_for_each(|_| ffmpeg_stdin.write_all(&live_recording.canvas_read_buffer));
+		live_recording.num_frames_written = num_frames_due;
+		if write_result.is_err() {
+			self.stop_live_recording();
+		}
+		Ok(())
+	}
+}
+
+/// Finishes the video and adds the recorded audio to it. The audio is
+/// also kept as a separate WAV file next to the video.
+fn finish_live_recording(live_recording: LiveRecording) -> anyhow::Result<()> {
+	let LiveRecording {
+		video_path,
+		silent_video_path,
+		audio_path,
+		wav_writer,
+		mut ffmpeg_process,
+		..
+	} = live_recording;
+	// closing stdin lets ffmpeg know there are no more frames coming
+	drop(ffmpeg_process.stdin.take());
+	ffmpeg_process.wait()?;
+	wav_writer.finalize()?;
+	let status = Command::new("ffmpeg")
+		.arg("-y")
+		.arg("-i")
+		.arg(&silent_video_path)
+		.arg("-i")
+		.arg(&audio_path)
+		.arg("-map")
+		.arg("0:v")
+		.arg("-map")
+		.arg("1:a")
+		.arg("-c:v")
+		.arg("copy")
+		.arg("-b:a")
+		.arg("320k")
+		.arg("-shortest")
+		.arg(&video_path)
+		.status()?;
+	if !status.success() {
+		bail!("ffmpeg could not add the recorded audio");
+	}
+	fs::remove_file(silent_video_path)?;
+	Ok(())
+}
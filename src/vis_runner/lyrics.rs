@@ -0,0 +1,219 @@
+use std::{
+	path::{Path, PathBuf},
+	time::Duration,
+};
+
+use micro::{
+	color::LinSrgba,
+	graphics::{
+		mesh::Mesh,
+		text::{LayoutSettings, Text},
+	},
+	math::{Rect, Vec2},
+	ui::{Checkbox, DragValue, Grid, Slider, Window},
+	Context,
+};
+use rfd::FileDialog;
+
+use crate::{
+	conversions::frame_to_seconds,
+	lyrics::{ActiveLyric, Lyrics},
+};
+
+use super::{cached_font::CachedFont, rendering::temp_file_path, VisRunner};
+
+/// The space between the text and the edge of its background, relative
+/// to the font size.
+const PADDING_FACTOR: f32 = 0.4;
+
+impl VisRunner {
+	pub fn load_lyrics(&mut self, path: PathBuf) -> anyhow::Result<()> {
+		self.lyrics = Some(Lyrics::from_file(&path)?);
+		self.lyrics_path = Some(path);
+		Ok(())
+	}
+
+	pub fn active_lyric_at_frame(&self, frame: u64) -> Option<ActiveLyric> {
+		if self.live() {
+			return None;
+		}
+		let time = Duration::from_secs_f64(frame_to_seconds(frame, self.visualizer.frame_rate()));
+		self.lyrics.as_ref()?.active_at(time)
+	}
+
+	/// Writes the lyrics to an SRT file (starting at `start_frame`) so they
+	/// can be added to a rendered video as a subtitle track. Returns
+	/// `None` if there are no lyrics or subtitles are turned off.
+	pub fn write_lyrics_subtitles(&self, start_frame: u64) -> anyhow::Result<Option<PathBuf>> {
+		if !self.rendering_settings.embed_lyrics {
+			return Ok(None);
+		}
+		let Some(lyrics) = &self.lyrics else {
+			return Ok(None);
+		};
+		let start_time =
+			Duration::from_secs_f64(frame_to_seconds(start_frame, self.visualizer.frame_rate()));
+		let path = temp_file_path("lyrics.srt");
+		std::fs::write(&path, lyrics.to_srt(start_time))?;
+		Ok(Some(path))
+	}
+
+	pub fn draw_lyrics(&mut self, ctx: &mut Context) -> anyhow::Result<()> {
+		let settings = &self.lyrics_display_settings;
+		if !settings.enabled {
+			return Ok(());
+		}
+		let Some(font_path) = settings.font_path.clone() else {
+			return Ok(());
+		};
+		let Some(active_lyric) = self.active_lyric_at_frame(self.current_frame()) else {
+			return Ok(());
+		};
+		let canvas_size = self.canvas.size().as_vec2();
+		let font_size = (settings.font_size * canvas_size.y).round().max(1.0);
+		let padding = font_size * PADDING_FACTOR;
+		let center = Vec2::from_array(settings.position) * canvas_size;
+		let [red, green, blue, alpha] = settings.text_color;
+		let text_color = LinSrgba::new(red, green, blue, alpha);
+		let [red, green, blue, alpha] = settings.highlight_color;
+		let highlight_color = LinSrgba::new(red, green, blue, alpha);
+		let [red, green, blue, alpha] = settings.background_color;
+		let background_color = LinSrgba::new(red, green, blue, alpha);
+
+		let Some(font) = CachedFont::get(&mut self.lyrics_font, ctx, &font_path, font_size) else {
+			return Ok(());
+		};
+		let line = &active_lyric.line;
+		let text = Text::new(ctx, font, &line.text, LayoutSettings::default());
+		// the words that have been sung are drawn again on top of the line
+		// in the highlight color. since they're the start of the line, they
+		// line up with the first part of it.
+		let highlighted_text = active_lyric.word.map(|active_word| {
+			let sung_words = line.words[..=active_word.index]
+				.iter()
+				.map(|word| word.text.as_str())
+				.collect::<Vec<_>>()
+				.join(" ");
+			Text::new(ctx, font, &sung_words, LayoutSettings::default())
+		});
+		let text_size = text.bounds().map_or(Vec2::ZERO, |bounds| bounds.size());
+		let top_left = center - text_size / 2.0;
+
+		let ctx = &mut self.canvas.render_to(ctx);
+		if background_color.alpha > 0.0 {
+			Mesh::rectangle(
+				ctx,
+				Rect::new(
+					top_left - Vec2::splat(padding),
+					text_size + Vec2::splat(padding * 2.0),
+				),
+			)
+			.color(background_color)
+			.draw(ctx);
+		}
+		text.translated_2d(top_left).color(text_color).draw(ctx);
+		if let Some(highlighted_text) = highlighted_text {
+			highlighted_text
+				.translated_2d(top_left)
+				.color(highlight_color)
+				.draw(ctx);
+		}
+		Ok(())
+	}
+
+	pub fn render_lyrics_window(&mut self, egui_ctx: &micro::ui::Context) -> anyhow::Result<()> {
+		let mut load = false;
+		let mut clear = false;
+		let lyrics_file_name = self
+			.lyrics_path
+			.as_deref()
+			.and_then(Path::file_name)
+			.map_or_else(
+				|| "None".to_string(),
+				|name| name.to_string_lossy().into_owned(),
+			);
+		let font_error = self
+			.lyrics_display_settings
+			.font_path
+			.as_deref()
+			.and_then(|path| CachedFont::load_error(&self.lyrics_font, path))
+			.map(str::to_string);
+		let settings = &mut self.lyrics_display_settings;
+		Window::new("Lyrics")
+			.open(&mut self.show_lyrics_window)
+			.show(egui_ctx, |ui| {
+				ui.horizontal(|ui| {
+					ui.label(format!("File: {}", lyrics_file_name));
+					load = ui.button("Load…").clicked();
+					clear = ui.button("Clear").clicked();
+				});
+				ui.add(Checkbox::new(&mut settings.enabled, "Show lyrics"));
+				Grid::new("lyrics_display_settings")
+					.num_columns(2)
+					.show(ui, |ui| {
+						ui.label("Font");
+						ui.horizontal(|ui| {
+							let font_name = settings
+								.font_path
+								.as_deref()
+								.and_then(Path::file_name)
+								.map_or_else(
+									|| "None".to_string(),
+									|name| name.to_string_lossy().into_owned(),
+								);
+							ui.label(font_name);
+							if ui.button("Choose…").clicked() {
+								if let Some(path) = FileDialog::new()
+									.add_filter("font", &["ttf", "otf"])
+									.pick_file()
+								{
+									settings.font_path = Some(path);
+								}
+							}
+							if let Some(error) = &font_error {
+								let error_color = ui.visuals().error_fg_color;
+								ui.colored_label(error_color, error);
+							}
+						});
+						ui.end_row();
+
+						ui.label("Font size");
+						ui.add(Slider::new(&mut settings.font_size, 0.01..=0.2));
+						ui.end_row();
+
+						ui.label("Position");
+						ui.horizontal(|ui| {
+							let [x, y] = &mut settings.position;
+							ui.add(DragValue::new(x).range(0.0..=1.0).speed(0.01));
+							ui.add(DragValue::new(y).range(0.0..=1.0).speed(0.01));
+						});
+						ui.end_row();
+
+						ui.label("Text color");
+						ui.color_edit_button_rgba_unmultiplied(&mut settings.text_color);
+						ui.end_row();
+
+						ui.label("Highlight color");
+						ui.color_edit_button_rgba_unmultiplied(&mut settings.highlight_color);
+						ui.end_row();
+
+						ui.label("Background color");
+						ui.color_edit_button_rgba_unmultiplied(&mut settings.background_color);
+						ui.end_row();
+					});
+			});
+		if load {
+			if let Some(path) = FileDialog::new()
+				.add_filter("lyrics", &["lrc", "srt"])
+				.pick_file()
+			{
+				self.load_lyrics(path)?;
+			}
+		}
+		if clear {
+			self.lyrics = None;
+			self.lyrics_path = None;
+		}
+		Ok(())
+	}
+}
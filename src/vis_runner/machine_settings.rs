@@ -0,0 +1,55 @@
+use std::path::PathBuf;
+
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+use super::osc::OscSettings;
+
+const FILE_NAME: &str = "machine_settings.json";
+
+/// Settings that depend on the computer the visualizer is running on
+/// rather than on the visualizer itself.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MachineSettings {
+	/// How much to delay the visuals relative to the audio during
+	/// live preview (in milliseconds). Negative values make the
+	/// visuals run ahead of the audio.
+	pub av_offset_ms: f64,
+	/// Audio files that were opened recently, most recent first.
+	pub recent_audio_paths: Vec<PathBuf>,
+	/// The MIDI input to connect to on startup.
+	pub midi_port_name: Option<String>,
+	pub osc: OscSettings,
+}
+
+impl MachineSettings {
+	pub fn load() -> Self {
+		let Some(path) = path() else {
+			return Self::default();
+		};
+		std::fs::read_to_string(path)
+			.ok()
+			.and_then(|contents| serde_json::from_str(&contents).ok())
+			.unwrap_or_default()
+	}
+
+	pub fn save(&self) -> anyhow::Result<()> {
+		let Some(path) = path() else {
+			return Ok(());
+		};
+		if let Some(parent) = path.parent() {
+			std::fs::create_dir_all(parent)?;
+		}
+		std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+		Ok(())
+	}
+
+	pub fn av_offset_seconds(&self) -> f64 {
+		self.av_offset_ms / 1000.0
+	}
+}
+
+fn path() -> Option<PathBuf> {
+	ProjectDirs::from("", "", "micro-visualizer").map(|dirs| dirs.config_dir().join(FILE_NAME))
+}
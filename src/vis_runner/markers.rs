@@ -0,0 +1,165 @@
+use std::{path::Path, sync::Arc};
+
+use micro::ui::{Button, DragValue, Grid, ScrollArea, TextEdit, Window};
+use rfd::FileDialog;
+
+use crate::{
+	conversions::{frame_to_seconds, seconds_to_frames},
+	Marker, Markers,
+};
+
+use super::{Mode, VisRunner};
+
+const MAX_MARKER_LIST_HEIGHT: f32 = 400.0;
+
+impl VisRunner {
+	pub fn add_marker_at_playhead(&mut self) {
+		if matches!(self.mode, Mode::Rendering { .. } | Mode::Live { .. }) {
+			return;
+		}
+		let name = format!("Marker {}", self.markers.len() + 1);
+		Arc::make_mut(&mut self.markers).insert(Marker::new(
+			name,
+			self.new_marker_kind.clone(),
+			self.current_frame(),
+		));
+	}
+
+	pub fn render_markers_window(&mut self, egui_ctx: &micro::ui::Context) -> anyhow::Result<()> {
+		if !self.show_markers_window {
+			return Ok(());
+		}
+		let frame_rate = self.visualizer.frame_rate();
+		let mut add_marker = false;
+		let mut import = false;
+		let mut export = false;
+		let mut seek_destination = None;
+		let mut edited_markers = vec![];
+		let mut removed_marker_index = None;
+		let mut frames_changed = false;
+		// the markers are shared with the last drawn frame, so they're
+		// only copied when one is actually edited
+		let markers = &self.markers;
+		let new_marker_kind = &mut self.new_marker_kind;
+		let import_error = &self.markers_import_error;
+		Window::new("Markers")
+			.open(&mut self.show_markers_window)
+			.show(egui_ctx, |ui| {
+				ui.horizontal(|ui| {
+					ui.label("Kind for new markers");
+					ui.add(TextEdit::singleline(new_marker_kind).desired_width(80.0));
+				});
+				ScrollArea::vertical()
+					.max_height(MAX_MARKER_LIST_HEIGHT)
+					.show(ui, |ui| {
+						Grid::new("markers").num_columns(5).show(ui, |ui| {
+							for (i, marker) in markers.0.iter().enumerate() {
+								let mut name = marker.name.clone();
+								let mut kind = marker.kind.clone();
+								let mut frame = marker.frame;
+								ui.add(TextEdit::singleline(&mut name).desired_width(120.0));
+								ui.add(TextEdit::singleline(&mut kind).desired_width(80.0));
+								let mut seconds = frame_to_seconds(frame, frame_rate);
+								if ui
+									.add(DragValue::new(&mut seconds).speed(0.01).suffix(" s"))
+									.changed()
+								{
+									frame = seconds_to_frames(seconds.max(0.0), frame_rate);
+									frames_changed = true;
+								}
+								if name != marker.name
+									|| kind != marker.kind || frame != marker.frame
+								{
+									edited_markers.push((i, Marker::new(name, kind, frame)));
+								}
+								if ui.button("Go").clicked() {
+									seek_destination = Some(marker.frame);
+								}
+								if ui.button("Remove").clicked() {
+									removed_marker_index = Some(i);
+								}
+								ui.end_row();
+							}
+						});
+					});
+				ui.horizontal(|ui| {
+					add_marker = ui.button("Add marker at playhead (M)").clicked();
+					import = ui.button("Import…").clicked();
+					export = ui
+						.add_enabled(!markers.is_empty(), Button::new("Export…"))
+						.clicked();
+				});
+				if let Some(error) = import_error {
+					let error_color = ui.visuals().error_fg_color;
+					ui.colored_label(error_color, error);
+				}
+			});
+		if !edited_markers.is_empty() || removed_marker_index.is_some() {
+			let markers = Arc::make_mut(&mut self.markers);
+			for (index, marker) in edited_markers {
+				markers.0[index] = marker;
+			}
+			if let Some(index) = removed_marker_index {
+				markers.0.remove(index);
+			}
+			if frames_changed {
+				markers.sort();
+			}
+		}
+		if add_marker {
+			self.add_marker_at_playhead();
+		}
+		if let Some(frame) = seek_destination {
+			self.seek(frame)?;
+		}
+		if import {
+			self.import_markers();
+		}
+		if export {
+			self.export_markers()?;
+		}
+		Ok(())
+	}
+
+	fn import_markers(&mut self) {
+		let Some(path) = FileDialog::new()
+			.add_filter("markers", &["json"])
+			.pick_file()
+		else {
+			return;
+		};
+		match read_markers(&path) {
+			Ok(markers) => {
+				self.markers = Arc::new(markers);
+				self.markers_import_error = None;
+			}
+			Err(error) => {
+				self.markers_import_error =
+					Some(format!("Could not import {}: {}", path.display(), error));
+			}
+		}
+	}
+
+	fn export_markers(&mut self) -> anyhow::Result<()> {
+		let directory = self
+			.last_output_directory
+			.clone()
+			.unwrap_or_else(|| std::env::current_exe().unwrap());
+		let Some(path) = FileDialog::new()
+			.set_directory(directory)
+			.set_file_name("markers.json")
+			.add_filter("markers", &["json"])
+			.save_file()
+		else {
+			return Ok(());
+		};
+		std::fs::write(path, serde_json::to_string_pretty(&self.markers)?)?;
+		Ok(())
+	}
+}
+
+fn read_markers(path: &Path) -> anyhow::Result<Markers> {
+	let mut markers: Markers = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+	markers.sort();
+	Ok(markers)
+}
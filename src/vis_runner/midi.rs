@@ -0,0 +1,640 @@
+use std::{
+	collections::HashSet,
+	sync::mpsc::{channel, Receiver, Sender},
+};
+
+use anyhow::anyhow;
+use micro::{
+	ui::{ComboBox, Grid, Ui, Window},
+	Context,
+};
+use midir::{MidiInput, MidiInputConnection};
+use serde::{Deserialize, Serialize};
+
+use crate::MidiMessage;
+
+use super::{recording::RecordedInput, VisRunner};
+
+const CLIENT_NAME: &str = "Micro Visualizer";
+/// CC values at or above this count as a button being pressed.
+const CC_BUTTON_THRESHOLD: u8 = 64;
+
+/// An open connection to a MIDI input port.
+pub struct MidiConnection {
+	port_name: String,
+	receiver: Receiver<MidiMessage>,
+	/// `None` for fake inputs, which receive messages from a [`Sender`]
+	/// instead of a MIDI port.
+	_connection: Option<MidiInputConnection<()>>,
+}
+
+impl MidiConnection {
+	pub fn connect(port_name: &str) -> anyhow::Result<Self> {
+		let midi_input = MidiInput::new(CLIENT_NAME)?;
+		let port = midi_input
+			.ports()
+			.into_iter()
+			.find(|port| midi_input.port_name(port).ok().as_deref() == Some(port_name))
+			.ok_or_else(|| anyhow!("no MIDI input named {}", port_name))?;
+		let (sender, receiver) = channel();
+		let connection = midi_input
+			.connect(
+				&port,
+				CLIENT_NAME,
+				move |_, bytes, _| {
+					sender.send(MidiMessage::from_bytes(bytes)).ok();
+				},
+				(),
+			)
+			.map_err(|error| anyhow!("could not connect to {}: {}", port_name, error))?;
+		Ok(Self {
+			port_name: port_name.to_string(),
+			receiver,
+			_connection: Some(connection),
+		})
+	}
+
+	/// Creates a virtual port that other programs (or `aconnect`/`amidi`
+	/// on Linux) can send MIDI messages to.
+	#[cfg(unix)]
+	pub fn create_virtual_port() -> anyhow::Result<Self> {
+		use midir::os::unix::VirtualInput;
+
+		let (sender, receiver) = channel();
+		let connection = MidiInput::new(CLIENT_NAME)?
+			.create_virtual(
+				CLIENT_NAME,
+				move |_, bytes, _| {
+					sender.send(MidiMessage::from_bytes(bytes)).ok();
+				},
+				(),
+			)
+			.map_err(|error| anyhow!("could not create virtual MIDI port: {}", error))?;
+		Ok(Self {
+			port_name: format!("{} (virtual)", CLIENT_NAME),
+			receiver,
+			_connection: Some(connection),
+		})
+	}
+
+	#[cfg(not(unix))]
+	pub fn create_virtual_port() -> anyhow::Result<Self> {
+		anyhow::bail!("virtual MIDI ports are not supported on this platform")
+	}
+
+	/// Creates an input that isn't connected to any MIDI port. Messages
+	/// sent with the returned [`Sender`] are received as if they came from
+	/// a controller.
+	#[cfg(test)]
+	pub fn fake() -> (Self, Sender<MidiMessage>) {
+		let (sender, receiver) = channel();
+		let connection = Self {
+			port_name: "Fake input".to_string(),
+			receiver,
+			_connection: None,
+		};
+		(connection, sender)
+	}
+
+	pub fn port_names() -> anyhow::Result<Vec<String>> {
+		let midi_input = MidiInput::new(CLIENT_NAME)?;
+		Ok(midi_input
+			.ports()
+			.iter()
+			.filter_map(|port| midi_input.port_name(port).ok())
+			.collect())
+	}
+
+	fn receive(&self) -> impl Iterator<Item = MidiMessage> + '_ {
+		self.receiver.try_iter()
+	}
+}
+
+/// A knob, fader, key or button on a MIDI controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MidiControl {
+	ControlChange { channel: u8, controller: u8 },
+	Note { channel: u8, note: u8 },
+}
+
+impl MidiControl {
+	fn label(self) -> String {
+		match self {
+			MidiControl::ControlChange {
+				channel,
+				controller,
+			} => format!("CC {} (ch. {})", controller, channel + 1),
+			MidiControl::Note { channel, note } => format!("Note {} (ch. {})", note, channel + 1),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TransportAction {
+	TogglePlayback,
+	SeekBackward,
+	SeekForward,
+	PreviousChapter,
+	NextChapter,
+	ToggleRecording,
+}
+
+impl TransportAction {
+	const ALL: [Self; 6] = [
+		Self::TogglePlayback,
+		Self::SeekBackward,
+		Self::SeekForward,
+		Self::PreviousChapter,
+		Self::NextChapter,
+		Self::ToggleRecording,
+	];
+
+	fn label(self) -> &'static str {
+		match self {
+			TransportAction::TogglePlayback => "Play/Pause",
+			TransportAction::SeekBackward => "Seek backward",
+			TransportAction::SeekForward => "Seek forward",
+			TransportAction::PreviousChapter => "Previous chapter",
+			TransportAction::NextChapter => "Next chapter",
+			TransportAction::ToggleRecording => "Record",
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MidiLearnTarget {
+	Param(String),
+	Transport(TransportAction),
+}
+
+/// Which MIDI controls are bound to which parameters and transport
+/// actions.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MidiBindings {
+	pub params: Vec<(MidiControl, String)>,
+	pub transport: Vec<(MidiControl, TransportAction)>,
+}
+
+impl MidiBindings {
+	fn bind(&mut self, control: MidiControl, target: MidiLearnTarget) {
+		self.unbind_target(&target);
+		self.params
+			.retain(|(bound_control, _)| *bound_control != control);
+		self.transport
+			.retain(|(bound_control, _)| *bound_control != control);
+		match target {
+			MidiLearnTarget::Param(name) => self.params.push((control, name)),
+			MidiLearnTarget::Transport(action) => self.transport.push((control, action)),
+		}
+	}
+
+	fn unbind_target(&mut self, target: &MidiLearnTarget) {
+		match target {
+			MidiLearnTarget::Param(name) => {
+				self.params.retain(|(_, bound_name)| bound_name != name);
+			}
+			MidiLearnTarget::Transport(action) => {
+				self.transport
+					.retain(|(_, bound_action)| bound_action != action);
+			}
+		}
+	}
+
+	fn control_for(&self, target: &MidiLearnTarget) -> Option<MidiControl> {
+		match target {
+			MidiLearnTarget::Param(name) => self
+				.params
+				.iter()
+				.find(|(_, bound_name)| bound_name == name)
+				.map(|(control, _)| *control),
+			MidiLearnTarget::Transport(action) => self
+				.transport
+				.iter()
+				.find(|(_, bound_action)| bound_action == action)
+				.map(|(control, _)| *control),
+		}
+	}
+}
+
+/// What the runner should do in response to a MIDI message.
+#[derive(Debug, Clone, PartialEq, Default)]
+struct MidiResponse {
+	/// Parameters to set, with values from 0 to 1.
+	param_values: Vec<(String, f32)>,
+	transport_actions: Vec<TransportAction>,
+}
+
+/// Updates the bindings, learn target and set of held controls for a
+/// MIDI message and returns what the message should do.
+fn respond_to_midi_message(
+	message: &MidiMessage,
+	bindings: &mut MidiBindings,
+	learn_target: &mut Option<MidiLearnTarget>,
+	pressed_controls: &mut HashSet<MidiControl>,
+) -> MidiResponse {
+	let (control, value, pressed) = match *message {
+		MidiMessage::ControlChange {
+			channel,
+			controller,
+			value,
+		} => (
+			MidiControl::ControlChange {
+				channel,
+				controller,
+			},
+			value,
+			value >= CC_BUTTON_THRESHOLD,
+		),
+		// a note on with a velocity of 0 is a note off by convention
+		MidiMessage::NoteOn {
+			channel,
+			note,
+			velocity,
+		} => (MidiControl::Note { channel, note }, velocity, velocity > 0),
+		MidiMessage::NoteOff { channel, note, .. } => {
+			(MidiControl::Note { channel, note }, 0, false)
+		}
+		MidiMessage::Other(_) => return MidiResponse::default(),
+	};
+	// releasing a key that was held down when learning started
+	// shouldn't bind it
+	let note_released = matches!(control, MidiControl::Note { .. }) && !pressed;
+	if !note_released {
+		if let Some(target) = learn_target.take() {
+			bindings.bind(control, target);
+			return MidiResponse::default();
+		}
+	}
+	let param_values = bindings
+		.params
+		.iter()
+		.filter(|(bound_control, _)| *bound_control == control)
+		.map(|(_, name)| (name.clone(), value as f32 / 127.0))
+		.collect();
+	let newly_pressed = if pressed {
+		pressed_controls.insert(control)
+	} else {
+		pressed_controls.remove(&control);
+		false
+	};
+	let transport_actions = if newly_pressed {
+		bindings
+			.transport
+			.iter()
+			.filter(|(bound_control, _)| *bound_control == control)
+			.map(|(_, action)| *action)
+			.collect()
+	} else {
+		vec![]
+	};
+	MidiResponse {
+		param_values,
+		transport_actions,
+	}
+}
+
+impl VisRunner {
+	pub fn handle_midi_messages(&mut self, ctx: &mut Context) -> anyhow::Result<()> {
+		let Some(connection) = &self.midi_connection else {
+			return Ok(());
+		};
+		let messages = connection.receive().collect::<Vec<_>>();
+		for message in messages {
+			// messages that control playback aren't replayed when
+			// rendering, like runner hotkeys
+			if !self.handle_midi_message(&message)? {
+				self.record_input(RecordedInput::Midi(message.clone()));
+			}
+			self.visualizer.midi(ctx, self.vis_info(), message)?;
+		}
+		Ok(())
+	}
+
+	/// Applies the message's bindings. Returns `true` if it triggered a
+	/// transport action.
+	fn handle_midi_message(&mut self, message: &MidiMessage) -> anyhow::Result<bool> {
+		let response = respond_to_midi_message(
+			message,
+			&mut self.midi_bindings,
+			&mut self.midi_learn_target,
+			&mut self.pressed_midi_controls,
+		);
+		for (name, value) in response.param_values {
+			if let Some(param) = self.params.get_mut(&name) {
+				param.set_normalized(value);
+			}
+		}
+		let triggered_transport_action = !response.transport_actions.is_empty();
+		for action in response.transport_actions {
+			self.perform_transport_action(action)?;
+		}
+		Ok(triggered_transport_action)
+	}
+
+	fn perform_transport_action(&mut self, action: TransportAction) -> anyhow::Result<()> {
+		if matches!(self.mode, super::Mode::Rendering { .. }) {
+			return Ok(());
+		}
+		match action {
+			TransportAction::TogglePlayback => self.toggle_playback(),
+			TransportAction::SeekBackward => self.seek_by_seconds(-10.0),
+			TransportAction::SeekForward => self.seek_by_seconds(10.0),
+			TransportAction::PreviousChapter => self.go_to_previous_chapter(),
+			TransportAction::NextChapter => self.go_to_next_chapter(),
+			TransportAction::ToggleRecording => self.toggle_recording(),
+		}
+	}
+
+	pub fn connect_midi(&mut self, port_name: &str) -> anyhow::Result<()> {
+		self.midi_connection = Some(MidiConnection::connect(port_name)?);
+		self.machine_settings.midi_port_name = Some(port_name.to_string());
+		self.machine_settings.save()?;
+		Ok(())
+	}
+
+	pub fn render_midi_window(&mut self, egui_ctx: &micro::ui::Context) {
+		if !self.show_midi_window {
+			// the port list is read again the next time the window opens
+			self.midi_port_names = None;
+			return;
+		}
+		let port_names = self
+			.midi_port_names
+			.get_or_insert_with(|| MidiConnection::port_names().unwrap_or_default());
+		let mut selected_port_name = None;
+		let mut create_virtual_port = false;
+		let mut refresh_port_names = false;
+		let connection_error = self.midi_connection_error.as_deref();
+		Window::new("MIDI")
+			.open(&mut self.show_midi_window)
+			.show(egui_ctx, |ui| {
+				let connected_port_name = self
+					.midi_connection
+					.as_ref()
+					.map_or("Not connected", |connection| connection.port_name.as_str());
+				ui.horizontal(|ui| {
+					ComboBox::new("midi_port", "Input")
+						.selected_text(connected_port_name)
+						.show_ui(ui, |ui| {
+							for port_name in port_names.iter() {
+								if ui.selectable_label(false, port_name).clicked() {
+									selected_port_name = Some(port_name.clone());
+								}
+							}
+						});
+					if ui.button("Refresh").clicked() {
+						refresh_port_names = true;
+					}
+				});
+				if cfg!(unix) && ui.button("Create virtual port").clicked() {
+					create_virtual_port = true;
+				}
+				if let Some(error) = connection_error {
+					let error_color = ui.visuals().error_fg_color;
+					ui.colored_label(error_color, format!("Could not connect: {}", error));
+				}
+				ui.separator();
+				Grid::new("midi_bindings").num_columns(3).show(ui, |ui| {
+					for param in &self.params.0 {
+						render_binding_row(
+							ui,
+							&param.name,
+							MidiLearnTarget::Param(param.name.clone()),
+							&mut self.midi_bindings,
+							&mut self.midi_learn_target,
+						);
+					}
+					for action in TransportAction::ALL {
+						render_binding_row(
+							ui,
+							action.label(),
+							MidiLearnTarget::Transport(action),
+							&mut self.midi_bindings,
+							&mut self.midi_learn_target,
+						);
+					}
+				});
+			});
+		if refresh_port_names {
+			self.midi_port_names = None;
+		}
+		if let Some(port_name) = selected_port_name {
+			self.midi_connection_error = self
+				.connect_midi(&port_name)
+				.err()
+				.map(|error| error.to_string());
+		}
+		if create_virtual_port {
+			match MidiConnection::create_virtual_port() {
+				Ok(connection) => {
+					self.midi_connection = Some(connection);
+					self.midi_connection_error = None;
+				}
+				Err(error) => self.midi_connection_error = Some(error.to_string()),
+			}
+		}
+	}
+}
+
+fn render_binding_row(
+	ui: &mut Ui,
+	label: &str,
+	target: MidiLearnTarget,
+	bindings: &mut MidiBindings,
+	learn_target: &mut Option<MidiLearnTarget>,
+) {
+	ui.label(label);
+	let learning = learn_target.as_ref() == Some(&target);
+	let binding_label = if learning {
+		"Waiting for input…".to_string()
+	} else {
+		bindings
+			.control_for(&target)
+			.map_or("-".to_string(), MidiControl::label)
+	};
+	ui.label(binding_label);
+	ui.horizontal(|ui| {
+		if ui.selectable_label(learning, "Learn").clicked() {
+			*learn_target = if learning { None } else { Some(target.clone()) };
+		}
+		if ui.button("Clear").clicked() {
+			bindings.unbind_target(&target);
+		}
+	});
+	ui.end_row();
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const KNOB: MidiControl = MidiControl::ControlChange {
+		channel: 0,
+		controller: 7,
+	};
+	const PAD: MidiControl = MidiControl::Note {
+		channel: 9,
+		note: 36,
+	};
+
+	#[derive(Default)]
+	struct State {
+		bindings: MidiBindings,
+		learn_target: Option<MidiLearnTarget>,
+		pressed_controls: HashSet<MidiControl>,
+	}
+
+	impl State {
+		fn send(&mut self, message: MidiMessage) -> MidiResponse {
+			let (connection, sender) = MidiConnection::fake();
+			sender.send(message).unwrap();
+			let message = connection.receive().next().unwrap();
+			respond_to_midi_message(
+				&message,
+				&mut self.bindings,
+				&mut self.learn_target,
+				&mut self.pressed_controls,
+			)
+		}
+	}
+
+	fn knob(value: u8) -> MidiMessage {
+		MidiMessage::ControlChange {
+			channel: 0,
+			controller: 7,
+			value,
+		}
+	}
+
+	fn pad_on(velocity: u8) -> MidiMessage {
+		MidiMessage::NoteOn {
+			channel: 9,
+			note: 36,
+			velocity,
+		}
+	}
+
+	fn pad_off() -> MidiMessage {
+		MidiMessage::NoteOff {
+			channel: 9,
+			note: 36,
+			velocity: 0,
+		}
+	}
+
+	#[test]
+	fn fake_input_receives_sent_messages() {
+		let (connection, sender) = MidiConnection::fake();
+		sender.send(knob(1)).unwrap();
+		sender.send(pad_on(100)).unwrap();
+		assert_eq!(
+			connection.receive().collect::<Vec<_>>(),
+			vec![knob(1), pad_on(100)]
+		);
+	}
+
+	#[test]
+	fn learning_binds_the_next_control() {
+		let mut state = State {
+			learn_target: Some(MidiLearnTarget::Param("size".to_string())),
+			..Default::default()
+		};
+		assert_eq!(state.send(knob(50)), MidiResponse::default());
+		assert_eq!(state.learn_target, None);
+		assert_eq!(
+			state
+				.bindings
+				.control_for(&MidiLearnTarget::Param("size".to_string())),
+			Some(KNOB)
+		);
+	}
+
+	#[test]
+	fn releasing_a_held_note_does_not_finish_learning() {
+		let target = MidiLearnTarget::Transport(TransportAction::TogglePlayback);
+		let mut state = State {
+			learn_target: Some(target.clone()),
+			..Default::default()
+		};
+		state.send(pad_off());
+		assert_eq!(state.learn_target, Some(target.clone()));
+		state.send(pad_on(100));
+		assert_eq!(state.bindings.control_for(&target), Some(PAD));
+	}
+
+	#[test]
+	fn binding_a_control_replaces_its_previous_binding() {
+		let mut bindings = MidiBindings::default();
+		bindings.bind(KNOB, MidiLearnTarget::Param("size".to_string()));
+		bindings.bind(KNOB, MidiLearnTarget::Param("speed".to_string()));
+		assert_eq!(bindings.params, vec![(KNOB, "speed".to_string())]);
+	}
+
+	#[test]
+	fn bound_controls_set_params() {
+		let mut state = State::default();
+		state
+			.bindings
+			.bind(KNOB, MidiLearnTarget::Param("size".to_string()));
+		assert_eq!(
+			state.send(knob(127)).param_values,
+			vec![("size".to_string(), 1.0)]
+		);
+		assert_eq!(
+			state.send(knob(0)).param_values,
+			vec![("size".to_string(), 0.0)]
+		);
+	}
+
+	#[test]
+	fn transport_actions_fire_once_per_press() {
+		let mut state = State::default();
+		state.bindings.bind(
+			PAD,
+			MidiLearnTarget::Transport(TransportAction::TogglePlayback),
+		);
+		let toggle = vec![TransportAction::TogglePlayback];
+		assert_eq!(state.send(pad_on(100)).transport_actions, toggle);
+		assert!(state.pressed_controls.contains(&PAD));
+		assert!(state.send(pad_on(100)).transport_actions.is_empty());
+		assert!(state.send(pad_off()).transport_actions.is_empty());
+		assert!(!state.pressed_controls.contains(&PAD));
+		assert_eq!(state.send(pad_on(100)).transport_actions, toggle);
+	}
+
+	#[test]
+	fn note_on_with_zero_velocity_is_a_release() {
+		let mut state = State::default();
+		state.bindings.bind(
+			PAD,
+			MidiLearnTarget::Transport(TransportAction::NextChapter),
+		);
+		state.send(pad_on(100));
+		assert!(state.send(pad_on(0)).transport_actions.is_empty());
+		assert!(!state.pressed_controls.contains(&PAD));
+		assert_eq!(
+			state.send(pad_on(100)).transport_actions,
+			vec![TransportAction::NextChapter]
+		);
+	}
+
+	#[test]
+	fn cc_buttons_press_at_the_threshold() {
+		let mut state = State::default();
+		state.bindings.bind(
+			KNOB,
+			MidiLearnTarget::Transport(TransportAction::ToggleRecording),
+		);
+		assert!(state
+			.send(knob(CC_BUTTON_THRESHOLD - 1))
+			.transport_actions
+			.is_empty());
+		assert_eq!(
+			state.send(knob(CC_BUTTON_THRESHOLD)).transport_actions,
+			vec![TransportAction::ToggleRecording]
+		);
+		state.send(knob(0));
+		assert!(!state.pressed_controls.contains(&KNOB));
+	}
+}
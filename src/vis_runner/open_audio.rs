@@ -0,0 +1,180 @@
+use std::path::{Path, PathBuf};
+
+use kira::{
+	sound::{streaming::StreamingSoundData, FromFileError},
+	tween::Tween,
+};
+use micro::{
+	ui::{Button, Ui},
+	Context,
+};
+use rfd::FileDialog;
+
+use crate::{analysis::Analysis, Sections, Visualizer};
+
+use super::{analyze_stems, playlist::Playlist, resolve_chapters, Mode, VisRunner};
+
+const MAX_RECENT_AUDIO_PATHS: usize = 10;
+pub const AUDIO_FILE_EXTENSIONS: &[&str] = &["flac", "mp3", "ogg", "wav"];
+
+impl VisRunner {
+	/// Replaces the audio that's being visualized. If the audio can't be
+	/// loaded, the current audio is left as it was and the error is shown
+	/// in the main menu.
+	pub fn open_audio(&mut self, ctx: &mut Context, path: PathBuf) -> anyhow::Result<()> {
+		// the stem files may have been replaced along with the audio, so
+		// they're analyzed again before the visualizer sees their features
+		let stems = self.visualizer.stems();
+		let LoadedAudio {
+			playlist,
+			analysis,
+			sound_data,
+			stem_analyses,
+		} = match LoadedAudio::load(&path, self.visualizer.as_ref()) {
+			Ok(loaded_audio) => loaded_audio,
+			Err(error) => {
+				self.audio_error = Some(format!("Could not open {}: {}", path.display(), error));
+				return Ok(());
+			}
+		};
+		self.audio_error = None;
+		self.stop_live_recording();
+		self.stop_recording();
+		if let Mode::PlayingOrPaused { sound, .. } = &mut self.mode {
+			sound.stop(Tween::default());
+		}
+		let chapter_names = self.chapter_names();
+		let previous_audio_path = self.playlist.first_path().map(Path::to_path_buf);
+		self.playlist = playlist;
+		self.num_frames = self.playlist.num_frames(self.visualizer.frame_rate());
+		self.analysis = analysis;
+		self.stem_analyses = stem_analyses;
+		self.mode = Mode::Stopped {
+			data: Some(sound_data),
+			start_frame: 0,
+		};
+		self.drawn_state = None;
+		// automation, markers and lyrics are timed to the audio they were
+		// made for
+		self.switch_timeline(previous_audio_path);
+		// sections are keyed by chapter index, so they'd end up on the
+		// wrong chapters
+		if self.chapter_names() != chapter_names {
+			self.sections = Sections::new();
+		}
+		self.rendering_settings
+			.reset_chapter_range(resolve_chapters(self.visualizer.as_ref(), &self.playlist));
+		self.remember_recent_audio_path(&path);
+		if let Err(error) = self.machine_settings.save() {
+			self.audio_error = Some(format!("Could not save the recent files: {}", error));
+		}
+		self.visualizer.audio_changed(ctx, self.vis_info(), &path)?;
+		// the visualizer may have swapped out its stems to match the new audio
+		if self.visualizer.stems() != stems {
+			match analyze_stems(self.visualizer.as_ref()) {
+				Ok(stem_analyses) => self.stem_analyses = stem_analyses,
+				Err(error) => {
+					self.stem_analyses = vec![];
+					self.audio_error = Some(format!("Could not analyze the stems: {}", error));
+				}
+			}
+		}
+		Ok(())
+	}
+
+	pub fn open_audio_with_dialog(&mut self, ctx: &mut Context) -> anyhow::Result<()> {
+		let mut dialog = FileDialog::new().add_filter("audio", AUDIO_FILE_EXTENSIONS);
+		if let Some(directory) = self.playlist.first_path().and_then(Path::parent) {
+			dialog = dialog.set_directory(directory);
+		}
+		let Some(path) = dialog.pick_file() else {
+			return Ok(());
+		};
+		self.open_audio(ctx, path)
+	}
+
+	pub fn open_dropped_file(&mut self, ctx: &mut Context, path: &Path) -> anyhow::Result<()> {
+		if matches!(self.mode, Mode::Rendering { .. }) || !is_audio_file(path) {
+			return Ok(());
+		}
+		self.open_audio(ctx, path.to_path_buf())
+	}
+
+	pub fn render_file_menu(&mut self, ctx: &mut Context, ui: &mut Ui) -> anyhow::Result<()> {
+		if matches!(self.mode, Mode::Rendering { .. }) {
+			return Ok(());
+		}
+		ui.menu_button("File", |ui| -> anyhow::Result<()> {
+			if ui.button("Open audio…").clicked() {
+				ui.close_menu();
+				self.open_audio_with_dialog(ctx)?;
+			}
+			let mut selected_recent_path = None;
+			ui.add_enabled_ui(!self.machine_settings.recent_audio_paths.is_empty(), |ui| {
+				ui.menu_button("Open recent", |ui| {
+					for path in &self.machine_settings.recent_audio_paths {
+						if ui.button(path.display().to_string()).clicked() {
+							ui.close_menu();
+							selected_recent_path = Some(path.clone());
+						}
+					}
+				});
+			});
+			if let Some(path) = selected_recent_path {
+				self.open_audio(ctx, path)?;
+			}
+			if ui.button("Save project").clicked() {
+				ui.close_menu();
+				self.save_project_and_report_errors();
+			}
+			self.render_live_input_menu(ui)?;
+			if ui
+				.add_enabled(self.chapters().is_some(), Button::new("Export tracklist…"))
+				.clicked()
+			{
+				ui.close_menu();
+				self.export_tracklist()?;
+			}
+			Ok(())
+		})
+		.inner
+		.transpose()?;
+		Ok(())
+	}
+
+	fn remember_recent_audio_path(&mut self, audio_path: &Path) {
+		let recent_audio_paths = &mut self.machine_settings.recent_audio_paths;
+		recent_audio_paths.retain(|path| path != audio_path);
+		recent_audio_paths.insert(0, audio_path.to_path_buf());
+		recent_audio_paths.truncate(MAX_RECENT_AUDIO_PATHS);
+	}
+}
+
+/// Everything that's read from disk when opening audio.
+struct LoadedAudio {
+	playlist: Playlist,
+	analysis: Analysis,
+	sound_data: StreamingSoundData<FromFileError>,
+	stem_analyses: Vec<(String, Analysis)>,
+}
+
+impl LoadedAudio {
+	fn load(path: &Path, visualizer: &dyn Visualizer) -> anyhow::Result<Self> {
+		let frame_rate = visualizer.frame_rate();
+		let playlist = Playlist::load(vec![path.to_path_buf()], frame_rate)?;
+		let analysis = Analysis::from_files(playlist.paths(), frame_rate)?;
+		let sound_data = playlist.sound_data()?;
+		Ok(Self {
+			playlist,
+			analysis,
+			sound_data,
+			stem_analyses: analyze_stems(visualizer)?,
+		})
+	}
+}
+
+fn is_audio_file(path: &Path) -> bool {
+	path.extension()
+		.and_then(|extension| extension.to_str())
+		.is_some_and(|extension| AUDIO_FILE_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
@@ -0,0 +1,280 @@
+use std::{
+	io::ErrorKind,
+	net::{Ipv4Addr, UdpSocket},
+};
+
+use micro::{
+	ui::{Checkbox, DragValue, Window},
+	Context,
+};
+use rosc::{OscMessage, OscPacket, OscType};
+use serde::{Deserialize, Serialize};
+
+use crate::{conversions::seconds_to_frames, params::ParamValue, VisualizerEvent};
+
+use super::{recording::RecordedInput, Mode, VisRunner};
+
+const MAX_PACKET_SIZE: usize = 65536;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OscSettings {
+	pub enabled: bool,
+	pub port: u16,
+	/// Whether to accept messages from other computers on the network.
+	/// Otherwise only messages from this computer are accepted.
+	pub allow_remote: bool,
+}
+
+impl Default for OscSettings {
+	fn default() -> Self {
+		Self {
+			enabled: false,
+			port: 9000,
+			allow_remote: false,
+		}
+	}
+}
+
+/// Listens for OSC messages on a UDP port.
+pub struct OscServer {
+	socket: UdpSocket,
+	settings: OscSettings,
+}
+
+impl OscServer {
+	pub fn new(settings: OscSettings) -> anyhow::Result<Self> {
+		let address = if settings.allow_remote {
+			Ipv4Addr::UNSPECIFIED
+		} else {
+			Ipv4Addr::LOCALHOST
+		};
+		let socket = UdpSocket::bind((address, settings.port))?;
+		socket.set_nonblocking(true)?;
+		Ok(Self { socket, settings })
+	}
+
+	/// Returns all of the messages that have arrived since the last call,
+	/// with bundles flattened into their messages.
+	fn receive(&self) -> Vec<OscMessage> {
+		let mut messages = vec![];
+		let mut buffer = [0; MAX_PACKET_SIZE];
+		loop {
+			let size = match self.socket.recv(&mut buffer) {
+				Ok(size) => size,
+				Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+				// errors like a connection reset on Windows only affect one
+				// packet, so the remaining packets are read next frame
+				Err(error) => {
+					eprintln!("could not receive OSC packet: {}", error);
+					break;
+				}
+			};
+			// ignore malformed packets rather than crashing the visualizer
+			if let Ok((_, packet)) = rosc::decoder::decode_udp(&buffer[..size]) {
+				flatten_packet(packet, &mut messages);
+			}
+		}
+		messages
+	}
+}
+
+/// The error from the last attempt to start the OSC server.
+pub struct OscBindError {
+	/// The settings the server couldn't be started with. Starting the
+	/// server isn't tried again until the settings change.
+	settings: OscSettings,
+	message: String,
+}
+
+impl VisRunner {
+	/// Starts, stops or restarts the OSC server to match the current
+	/// settings.
+	pub fn update_osc_server(&mut self) {
+		let settings = self.machine_settings.osc;
+		if !settings.enabled {
+			self.osc_server = None;
+			self.osc_bind_error = None;
+			return;
+		}
+		let up_to_date = self
+			.osc_server
+			.as_ref()
+			.is_some_and(|server| server.settings == settings)
+			|| self
+				.osc_bind_error
+				.as_ref()
+				.is_some_and(|error| error.settings == settings);
+		if up_to_date {
+			return;
+		}
+		self.osc_server = None;
+		self.osc_bind_error = None;
+		match OscServer::new(settings) {
+			Ok(server) => self.osc_server = Some(server),
+			Err(error) => {
+				self.osc_bind_error = Some(OscBindError {
+					settings,
+					message: error.to_string(),
+				})
+			}
+		}
+	}
+
+	pub fn handle_osc_messages(&mut self, ctx: &mut Context) -> anyhow::Result<()> {
+		let Some(server) = &self.osc_server else {
+			return Ok(());
+		};
+		for message in server.receive() {
+			if !self.handle_osc_message(&message)? {
+				self.record_input(RecordedInput::Event(VisualizerEvent::Osc(message.clone())));
+				self.visualizer
+					.event(ctx, self.vis_info(), VisualizerEvent::Osc(message))?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Performs the action for an OSC message. Returns `false` if the
+	/// runner doesn't handle the message's address.
+	fn handle_osc_message(&mut self, message: &OscMessage) -> anyhow::Result<bool> {
+		if let Some(name) = message.addr.strip_prefix("/param/") {
+			if let Some(param) = self.params.get_mut(name) {
+				if let Some(value) = osc_args_to_param_value(&param.default, &message.args) {
+					param.set(value);
+				}
+				return Ok(true);
+			}
+			return Ok(false);
+		}
+		let handled = matches!(
+			message.addr.as_str(),
+			"/transport/play" | "/transport/pause" | "/transport/toggle" | "/seek" | "/chapter"
+		);
+		if !handled || matches!(self.mode, Mode::Rendering { .. }) {
+			return Ok(handled);
+		}
+		let first_arg = message.args.first().and_then(osc_arg_to_f64);
+		match (message.addr.as_str(), first_arg) {
+			("/transport/play", _) => {
+				if !self.playing() {
+					self.play_or_resume()?;
+				}
+			}
+			("/transport/pause", _) => self.pause()?,
+			("/transport/toggle", _) => self.toggle_playback()?,
+			("/seek", Some(seconds)) => {
+				let frame = seconds_to_frames(seconds.max(0.0), self.visualizer.frame_rate());
+				self.seek(frame.min(self.num_frames))?;
+			}
+			("/chapter", Some(index)) => {
+				let index = index as usize;
+				if self
+					.chapters()
+					.is_some_and(|chapters| index < chapters.len())
+				{
+					self.go_to_chapter(index)?;
+				}
+			}
+			_ => {}
+		}
+		Ok(true)
+	}
+
+	pub fn render_osc_window(&mut self, egui_ctx: &micro::ui::Context) -> anyhow::Result<()> {
+		let mut settings_changed = false;
+		let running = self.osc_server.is_some();
+		let bind_error = self.osc_bind_error.as_ref().map(|error| &error.message);
+		Window::new("OSC")
+			.open(&mut self.show_osc_window)
+			.show(egui_ctx, |ui| {
+				let settings = &mut self.machine_settings.osc;
+				settings_changed |= ui
+					.add(Checkbox::new(&mut settings.enabled, "Enabled"))
+					.changed();
+				ui.horizontal(|ui| {
+					ui.label("Port");
+					// the port is only changed once the user is done
+					// editing it so the socket isn't rebound for every
+					// number in between
+					let port = self.osc_port_input.get_or_insert(settings.port);
+					let response = ui.add(DragValue::new(port));
+					if response.drag_stopped() || response.lost_focus() {
+						if *port != settings.port {
+							settings.port = *port;
+							settings_changed = true;
+						}
+						self.osc_port_input = None;
+					}
+				});
+				settings_changed |= ui
+					.add(Checkbox::new(
+						&mut settings.allow_remote,
+						"Accept messages from other computers",
+					))
+					.changed();
+				match bind_error {
+					Some(message) => {
+						let error_color = ui.visuals().error_fg_color;
+						ui.colored_label(
+							error_color,
+							format!("Could not listen on this port: {}", message),
+						);
+					}
+					None => {
+						ui.label(if running {
+							"Listening"
+						} else {
+							"Not listening"
+						});
+					}
+				}
+			});
+		if settings_changed {
+			self.machine_settings.save()?;
+		}
+		Ok(())
+	}
+}
+
+fn flatten_packet(packet: OscPacket, messages: &mut Vec<OscMessage>) {
+	match packet {
+		OscPacket::Message(message) => messages.push(message),
+		OscPacket::Bundle(bundle) => {
+			for packet in bundle.content {
+				flatten_packet(packet, messages);
+			}
+		}
+	}
+}
+
+fn osc_arg_to_f64(arg: &OscType) -> Option<f64> {
+	match arg {
+		OscType::Int(value) => Some(*value as f64),
+		OscType::Long(value) => Some(*value as f64),
+		OscType::Float(value) => Some(*value as f64),
+		OscType::Double(value) => Some(*value),
+		OscType::Bool(value) => Some(if *value { 1.0 } else { 0.0 }),
+		_ => None,
+	}
+}
+
+/// Converts OSC arguments into a value of the same type as `like`.
+fn osc_args_to_param_value(like: &ParamValue, args: &[OscType]) -> Option<ParamValue> {
+	let numbers = args.iter().filter_map(osc_arg_to_f64).collect::<Vec<_>>();
+	let value = match (like, numbers.as_slice()) {
+		(ParamValue::Float(_), [value, ..]) => ParamValue::Float(*value as f32),
+		(ParamValue::Int(_), [value, ..]) => ParamValue::Int(value.round() as i64),
+		(ParamValue::Bool(_), [value, ..]) => ParamValue::Bool(*value >= 0.5),
+		(ParamValue::Choice(_), [value, ..]) => ParamValue::Choice(value.max(0.0) as usize),
+		(ParamValue::Vec2(_), [x, y, ..]) => ParamValue::Vec2([*x as f32, *y as f32]),
+		(ParamValue::Color(_), [red, green, blue]) => {
+			ParamValue::Color([*red as f32, *green as f32, *blue as f32, 1.0])
+		}
+		(ParamValue::Color(_), [red, green, blue, alpha, ..]) => {
+			ParamValue::Color([*red as f32, *green as f32, *blue as f32, *alpha as f32])
+		}
+		_ => return None,
+	};
+	Some(value)
+}
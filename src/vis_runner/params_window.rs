@@ -0,0 +1,89 @@
+use micro::ui::{Checkbox, ComboBox, DragValue, Grid, Slider, Ui, Window};
+
+use crate::params::{Gradient, GradientStop, ParamKind, ParamValue};
+
+use super::VisRunner;
+
+impl VisRunner {
+	pub fn render_params_window(&mut self, egui_ctx: &micro::ui::Context) -> anyhow::Result<()> {
+		if self.params.is_empty() {
+			return Ok(());
+		}
+		Window::new("Parameters")
+			.open(&mut self.show_params_window)
+			.show(egui_ctx, |ui| {
+				Grid::new("params").num_columns(3).show(ui, |ui| {
+					for param in &mut self.params.0 {
+						ui.label(&param.name);
+						render_value_editor(ui, &param.name, &param.kind, &mut param.value);
+						if ui.button("Reset").clicked() {
+							param.reset();
+						}
+						ui.end_row();
+					}
+				});
+			});
+		Ok(())
+	}
+}
+
+pub fn render_value_editor(ui: &mut Ui, id_source: &str, kind: &ParamKind, value: &mut ParamValue) {
+	match (kind, value) {
+		(ParamKind::Float { range }, ParamValue::Float(value)) => {
+			ui.add(Slider::new(value, range.clone()));
+		}
+		(ParamKind::Int { range }, ParamValue::Int(value)) => {
+			ui.add(Slider::new(value, range.clone()));
+		}
+		(ParamKind::Color, ParamValue::Color(color)) => {
+			ui.color_edit_button_rgba_unmultiplied(color);
+		}
+		(ParamKind::Bool, ParamValue::Bool(value)) => {
+			ui.add(Checkbox::without_text(value));
+		}
+		(ParamKind::Choice { options }, ParamValue::Choice(index)) => {
+			ComboBox::from_id_source(id_source)
+				.show_index(ui, index, options.len(), |i| &options[i]);
+		}
+		(ParamKind::Vec2 { range }, ParamValue::Vec2([x, y])) => {
+			ui.horizontal(|ui| {
+				ui.add(DragValue::new(x).range(range.clone()).speed(0.01));
+				ui.add(DragValue::new(y).range(range.clone()).speed(0.01));
+			});
+		}
+		(ParamKind::Gradient, ParamValue::Gradient(gradient)) => {
+			render_gradient_editor(ui, gradient);
+		}
+		_ => unreachable!("parameter value does not match its kind"),
+	}
+}
+
+fn render_gradient_editor(ui: &mut Ui, gradient: &mut Gradient) {
+	ui.vertical(|ui| {
+		let mut removed_stop_index = None;
+		for (i, stop) in gradient.stops.iter_mut().enumerate() {
+			ui.horizontal(|ui| {
+				ui.add(
+					DragValue::new(&mut stop.position)
+						.range(0.0..=1.0)
+						.speed(0.01),
+				);
+				ui.color_edit_button_rgba_unmultiplied(&mut stop.color);
+				if ui.button("x").clicked() {
+					removed_stop_index = Some(i);
+				}
+			});
+		}
+		if let Some(i) = removed_stop_index {
+			gradient.stops.remove(i);
+		}
+		if ui.button("Add stop").clicked() {
+			let color = gradient.sample(1.0);
+			gradient.stops.push(GradientStop {
+				position: 1.0,
+				color: [color.red, color.green, color.blue, color.alpha],
+			});
+		}
+		gradient.sort();
+	});
+}
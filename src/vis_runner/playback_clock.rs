@@ -0,0 +1,114 @@
+use std::time::{Duration, Instant};
+
+/// If the interpolated position drifts further than this from the
+/// audio clock, it jumps back to the audio clock.
+const RESYNC_THRESHOLD: Duration = Duration::from_millis(50);
+
+/// Tracks the playback position of a sound, filling in the gaps between
+/// the audio thread's updates with a monotonic timer.
+///
+/// The position reported by kira only advances when the audio thread
+/// processes a buffer, so reading it directly makes some video frames
+/// repeat and others get skipped.
+pub struct PlaybackClock {
+	anchor_position: f64,
+	anchor_instant: Instant,
+	running: bool,
+}
+
+impl PlaybackClock {
+	pub fn new(position: f64) -> Self {
+		Self {
+			anchor_position: position,
+			anchor_instant: Instant::now(),
+			running: false,
+		}
+	}
+
+	/// Returns the current playback position (in seconds).
+	pub fn position(&self) -> f64 {
+		self.position_at(Instant::now())
+	}
+
+	/// Compares the interpolated position to the audio clock and
+	/// re-syncs if needed.
+	pub fn update(&mut self, audio_position: f64, playing: bool) {
+		self.update_at(audio_position, playing, Instant::now());
+	}
+
+	pub fn resync(&mut self, audio_position: f64) {
+		self.resync_at(audio_position, Instant::now());
+	}
+
+	fn position_at(&self, now: Instant) -> f64 {
+		if self.running {
+			self.anchor_position + (now - self.anchor_instant).as_secs_f64()
+		} else {
+			self.anchor_position
+		}
+	}
+
+	fn update_at(&mut self, audio_position: f64, playing: bool, now: Instant) {
+		if playing != self.running {
+			self.resync_at(audio_position, now);
+			self.running = playing;
+			return;
+		}
+		let drift = (self.position_at(now) - audio_position).abs();
+		if !self.running || drift > RESYNC_THRESHOLD.as_secs_f64() {
+			self.resync_at(audio_position, now);
+		}
+	}
+
+	fn resync_at(&mut self, audio_position: f64, now: Instant) {
+		self.anchor_position = audio_position;
+		self.anchor_instant = now;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn playing_clock(position: f64, now: Instant) -> PlaybackClock {
+		let mut clock = PlaybackClock::new(position);
+		clock.update_at(position, true, now);
+		clock
+	}
+
+	#[test]
+	fn interpolates_between_audio_updates() {
+		let start = Instant::now();
+		let mut clock = playing_clock(1.0, start);
+		// the audio clock hasn't advanced yet, but it's within the
+		// threshold, so the interpolated position is kept
+		let now = start + Duration::from_millis(20);
+		clock.update_at(1.0, true, now);
+		assert!((clock.position_at(now) - 1.02).abs() < 1e-9);
+	}
+
+	#[test]
+	fn resyncs_when_drifting_past_the_threshold() {
+		let start = Instant::now();
+		let mut clock = playing_clock(1.0, start);
+		let now = start + RESYNC_THRESHOLD + Duration::from_millis(10);
+		clock.update_at(1.0, true, now);
+		assert_eq!(clock.position_at(now), 1.0);
+		// seeking moves the audio clock far away from the interpolated
+		// position
+		clock.update_at(30.0, true, now);
+		assert_eq!(clock.position_at(now), 30.0);
+	}
+
+	#[test]
+	fn paused_clocks_follow_the_audio_clock() {
+		let start = Instant::now();
+		let mut clock = playing_clock(1.0, start);
+		let paused_at = start + Duration::from_millis(500);
+		clock.update_at(1.5, false, paused_at);
+		let later = paused_at + Duration::from_secs(10);
+		assert_eq!(clock.position_at(later), 1.5);
+		clock.update_at(1.49, false, later);
+		assert_eq!(clock.position_at(later), 1.49);
+	}
+}
@@ -0,0 +1,266 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+use kira::{
+	dsp::Frame,
+	sound::{
+		streaming::{Decoder, StreamingSoundData},
+		FromFileError,
+	},
+};
+
+use crate::{
+	audio_decoder::{FileDecoder, FileTags},
+	conversions::{frame_to_seconds, seconds_to_frames},
+	Chapter, Chapters,
+};
+
+/// One or more audio files that are played back to back as a single
+/// timeline.
+pub struct Playlist {
+	tracks: Vec<PlaylistTrack>,
+	sample_rate: u32,
+	chapters: Option<Chapters>,
+}
+
+impl Playlist {
+	pub fn load(paths: Vec<PathBuf>, frame_rate: u64) -> anyhow::Result<Self> {
+		if paths.is_empty() {
+			bail!("a playlist must contain at least one audio file");
+		}
+		let mut tracks: Vec<PlaylistTrack> = vec![];
+		let mut sample_rate = None;
+		for path in paths {
+			let decoder = FileDecoder::open(&path)?;
+			match (sample_rate, tracks.first()) {
+				(Some(sample_rate), Some(first_track)) if sample_rate != decoder.sample_rate() => {
+					bail!(
+						"every file in a playlist must have the same sample rate, but {} is {} Hz \
+						 and {} is {} Hz",
+						first_track.path.display(),
+						sample_rate,
+						path.display(),
+						decoder.sample_rate(),
+					)
+				}
+				_ => sample_rate = Some(decoder.sample_rate()),
+			}
+			tracks.push(PlaylistTrack {
+				name: track_name(&path, &decoder),
+				tags: decoder.tags().clone(),
+				num_audio_frames: decoder.num_frames(),
+				path,
+			});
+		}
+		let sample_rate = sample_rate.unwrap();
+		let chapters = (tracks.len() > 1).then(|| {
+			let mut start_audio_frame = 0;
+			let chapters = tracks
+				.iter()
+				.map(|track| {
+					let mut chapter = Chapter::new(
+						track.name.clone(),
+						seconds_to_frames(
+							start_audio_frame as f64 / sample_rate as f64,
+							frame_rate,
+						),
+					);
+					chapter.artist = track.tags.artist.clone();
+					chapter.title = track.tags.title.clone();
+					chapter.album = track.tags.album.clone();
+					start_audio_frame += track.num_audio_frames;
+					chapter
+				})
+				.collect();
+			Chapters(chapters)
+		});
+		Ok(Self {
+			tracks,
+			sample_rate,
+			chapters,
+		})
+	}
+
+	/// Returns a playlist with no tracks, which is used when the
+	/// visualizer starts in live mode.
+	pub fn empty() -> Self {
+		Self {
+			tracks: vec![],
+			sample_rate: 0,
+			chapters: None,
+		}
+	}
+
+	pub fn paths(&self) -> impl Iterator<Item = &Path> {
+		self.tracks.iter().map(|track| track.path.as_path())
+	}
+
+	pub fn first_path(&self) -> Option<&Path> {
+		self.tracks.first().map(|track| track.path.as_path())
+	}
+
+	pub fn num_tracks(&self) -> usize {
+		self.tracks.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.tracks.is_empty()
+	}
+
+	/// Returns one chapter per track, or `None` if the playlist only
+	/// has a single track.
+	pub fn chapters(&self) -> Option<&Chapters> {
+		self.chapters.as_ref()
+	}
+
+	pub fn num_frames(&self, frame_rate: u64) -> u64 {
+		if self.is_empty() {
+			return 0;
+		}
+		let num_audio_frames: usize = self.tracks.iter().map(|track| track.num_audio_frames).sum();
+		seconds_to_frames(
+			num_audio_frames as f64 / self.sample_rate as f64,
+			frame_rate,
+		)
+	}
+
+	pub fn sound_data(&self) -> Result<StreamingSoundData<FromFileError>, FromFileError> {
+		if let [track] = self.tracks.as_slice() {
+			return StreamingSoundData::from_file(&track.path);
+		}
+		Ok(StreamingSoundData::from_decoder(PlaylistDecoder::new(
+			self,
+		)?))
+	}
+
+	/// Returns the ffmpeg filter graph that joins the playlist's files
+	/// (starting at the given ffmpeg input index) into a single audio
+	/// stream labeled `[audio]`, starting at `start_frame`.
+	pub fn ffmpeg_concat_filter(
+		&self,
+		first_input_index: usize,
+		start_frame: u64,
+		frame_rate: u64,
+	) -> String {
+		let mut filter = String::new();
+		for i in 0..self.tracks.len() {
+			filter += &format!(
+				"[{}:a]aformat=channel_layouts=stereo[a{}];",
+				first_input_index + i,
+				i
+			);
+		}
+		for i in 0..self.tracks.len() {
+			filter += &format!("[a{}]", i);
+		}
+		filter += &format!(
+			"concat=n={}:v=0:a=1,atrim=start={},asetpts=PTS-STARTPTS[audio]",
+			self.tracks.len(),
+			frame_to_seconds(start_frame, frame_rate)
+		);
+		filter
+	}
+}
+
+struct PlaylistTrack {
+	path: PathBuf,
+	name: String,
+	tags: FileTags,
+	num_audio_frames: usize,
+}
+
+/// Streams the files of a playlist one after another without gaps.
+struct PlaylistDecoder {
+	tracks: Vec<PlaylistDecoderTrack>,
+	sample_rate: u32,
+	current_track_index: usize,
+}
+
+impl PlaylistDecoder {
+	fn new(playlist: &Playlist) -> Result<Self, FromFileError> {
+		let mut tracks = vec![];
+		let mut start_frame = 0;
+		for track in &playlist.tracks {
+			tracks.push(PlaylistDecoderTrack {
+				decoder: FileDecoder::open(&track.path)?,
+				start_frame,
+				num_frames: track.num_audio_frames,
+				frames_decoded: 0,
+			});
+			start_frame += track.num_audio_frames;
+		}
+		Ok(Self {
+			tracks,
+			sample_rate: playlist.sample_rate,
+			current_track_index: 0,
+		})
+	}
+}
+
+impl Decoder for PlaylistDecoder {
+	type Error = FromFileError;
+
+	fn sample_rate(&self) -> u32 {
+		self.sample_rate
+	}
+
+	fn num_frames(&self) -> usize {
+		self.tracks.iter().map(|track| track.num_frames).sum()
+	}
+
+	fn decode(&mut self) -> Result<Vec<Frame>, Self::Error> {
+		loop {
+			let is_last_track = self.current_track_index == self.tracks.len() - 1;
+			let track = &mut self.tracks[self.current_track_index];
+			let remaining_frames = track.num_frames - track.frames_decoded;
+			if remaining_frames == 0 && !is_last_track {
+				self.current_track_index += 1;
+				let next_track = &mut self.tracks[self.current_track_index];
+				next_track.decoder.seek(0)?;
+				next_track.frames_decoded = 0;
+				continue;
+			}
+			// keep every track exactly as long as its reported length so the
+			// generated chapters line up with the audio
+			let mut frames = match track.decoder.decode()? {
+				Some(frames) => frames,
+				None => vec![Frame::ZERO; remaining_frames],
+			};
+			frames.truncate(remaining_frames);
+			track.frames_decoded += frames.len();
+			return Ok(frames);
+		}
+	}
+
+	fn seek(&mut self, index: usize) -> Result<usize, Self::Error> {
+		let track_index = self
+			.tracks
+			.iter()
+			.rposition(|track| track.start_frame <= index)
+			.unwrap_or(0);
+		let track = &mut self.tracks[track_index];
+		let seeked_to = track.decoder.seek(index - track.start_frame)?;
+		track.frames_decoded = seeked_to;
+		self.current_track_index = track_index;
+		Ok(track.start_frame + seeked_to)
+	}
+}
+
+struct PlaylistDecoderTrack {
+	decoder: FileDecoder,
+	start_frame: usize,
+	num_frames: usize,
+	frames_decoded: usize,
+}
+
+fn track_name(path: &Path, decoder: &FileDecoder) -> String {
+	let tags = decoder.tags();
+	match (&tags.artist, &tags.title) {
+		(Some(artist), Some(title)) => format!("{} - {}", artist, title),
+		(None, Some(title)) => title.clone(),
+		_ => path
+			.file_stem()
+			.map(|stem| stem.to_string_lossy().into_owned())
+			.unwrap_or_else(|| path.display().to_string()),
+	}
+}
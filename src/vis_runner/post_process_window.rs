@@ -0,0 +1,72 @@
+use micro::ui::{Button, Checkbox, ComboBox, Grid, Window};
+
+use crate::post_process::{PostProcessEffect, PostProcessEffectKind};
+
+use super::{params_window::render_value_editor, VisRunner};
+
+impl VisRunner {
+	pub fn render_post_process_window(
+		&mut self,
+		egui_ctx: &micro::ui::Context,
+	) -> anyhow::Result<()> {
+		Window::new("Post-processing")
+			.open(&mut self.show_post_process_window)
+			.show(egui_ctx, |ui| {
+				let effects = &mut self.post_process.0;
+				let num_effects = effects.len();
+				let mut moved_effect = None;
+				let mut removed_effect_index = None;
+				for (i, effect) in effects.iter_mut().enumerate() {
+					ui.horizontal(|ui| {
+						ui.add(Checkbox::new(&mut effect.enabled, effect.kind.label()));
+						if ui.add_enabled(i > 0, Button::new("Up")).clicked() {
+							moved_effect = Some((i, i - 1));
+						}
+						if ui
+							.add_enabled(i + 1 < num_effects, Button::new("Down"))
+							.clicked()
+						{
+							moved_effect = Some((i, i + 1));
+						}
+						if ui.button("Remove").clicked() {
+							removed_effect_index = Some(i);
+						}
+					});
+					ui.add_enabled_ui(effect.enabled, |ui| {
+						Grid::new(("post_process_effect", i))
+							.num_columns(2)
+							.show(ui, |ui| {
+								for param in effect.kind.params().0 {
+									let Some(value) = effect.values.0.get_mut(&param.name) else {
+										continue;
+									};
+									ui.label(&param.name);
+									render_value_editor(ui, &param.name, &param.kind, value);
+									ui.end_row();
+								}
+							});
+					});
+					ui.separator();
+				}
+				if let Some((from, to)) = moved_effect {
+					effects.swap(from, to);
+				}
+				if let Some(i) = removed_effect_index {
+					effects.remove(i);
+				}
+				ui.horizontal(|ui| {
+					ComboBox::new("new_post_process_effect", "").show_index(
+						ui,
+						&mut self.selected_effect_kind_index,
+						PostProcessEffectKind::NUM_KINDS,
+						|i| PostProcessEffectKind::from(i).label(),
+					);
+					if ui.button("Add effect").clicked() {
+						let kind = PostProcessEffectKind::from(self.selected_effect_kind_index);
+						effects.push(PostProcessEffect::new(kind));
+					}
+				});
+			});
+		Ok(())
+	}
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use anyhow::bail;
+use micro::{
+	color::{ColorConstants, LinSrgba},
+	graphics::{shader::Shader, Canvas, CanvasSettings},
+	math::{UVec2, Vec2, Vec4},
+	Context,
+};
+
+use crate::{
+	params::ParamValue,
+	post_process::{PostProcessChain, PostProcessEffectKind},
+	shaders::load_shader,
+};
+
+/// The output height that pixel sizes in effect parameters refer to.
+const REFERENCE_HEIGHT: f32 = 1080.0;
+
+/// Runs a [`PostProcessChain`] on the main canvas.
+pub struct PostProcessor {
+	shaders: HashMap<PostProcessEffectKind, Shader>,
+	/// Created the first time an effect is enabled, so visualizers that
+	/// don't use post-processing don't pay for the extra canvases.
+	canvases: Option<EffectCanvases>,
+}
+
+impl PostProcessor {
+	pub fn new(ctx: &mut Context) -> anyhow::Result<Self> {
+		let mut shaders = HashMap::new();
+		for i in 0..PostProcessEffectKind::NUM_KINDS {
+			let kind = PostProcessEffectKind::from(i);
+			shaders.insert(kind, load_shader(ctx, shader_source(kind))?);
+		}
+		Ok(Self {
+			shaders,
+			canvases: None,
+		})
+	}
+
+	/// Applies the enabled effects in the chain to the canvas in place.
+	pub fn apply(
+		&mut self,
+		ctx: &mut Context,
+		canvas: &Canvas,
+		chain: &PostProcessChain,
+		time: f32,
+	) -> anyhow::Result<()> {
+		if !chain.0.iter().any(|effect| effect.enabled) {
+			return Ok(());
+		}
+		if let Some(canvases) = &self.canvases {
+			if canvases.previous_frame.size() != canvas.size() {
+				self.canvases = None;
+			}
+		}
+		let canvases = &*self
+			.canvases
+			.get_or_insert_with(|| EffectCanvases::new(ctx, canvas.size()));
+		let mut source = canvas;
+		for (i, effect) in chain.0.iter().filter(|effect| effect.enabled).enumerate() {
+			let target = &canvases.effect_targets[i % 2];
+			let shader = &self.shaders[&effect.kind];
+			shader.send_vec2(ctx, "resolution", canvas.size().as_vec2())?;
+			shader.send_f32(ctx, "time", time)?;
+			shader.send_f32(ctx, "pixel_scale", pixel_scale(canvas.size()))?;
+			if effect.kind == PostProcessEffectKind::Feedback {
+				shader.send_canvas(ctx, "previous_frame", &canvases.previous_frame)?;
+			}
+			for (name, value) in &effect.values.0 {
+				send_param_value(ctx, shader, &uniform_name(name), value)?;
+			}
+			let ctx = &mut target.render_to(ctx);
+			ctx.clear(LinSrgba::BLACK);
+			let ctx = &mut ctx.push_shader(shader);
+			source.draw(ctx);
+			source = target;
+		}
+		{
+			let ctx = &mut canvases.previous_frame.render_to(ctx);
+			ctx.clear(LinSrgba::BLACK);
+			source.draw(ctx);
+		}
+		let ctx = &mut canvas.render_to(ctx);
+		ctx.clear(LinSrgba::BLACK);
+		source.draw(ctx);
+		Ok(())
+	}
+}
+
+struct EffectCanvases {
+	/// Effects draw from one of these canvases into the other.
+	effect_targets: [Canvas; 2],
+	/// The output of the last frame, which the feedback effect draws on
+	/// top of.
+	previous_frame: Canvas,
+}
+
+impl EffectCanvases {
+	fn new(ctx: &mut Context, size: UVec2) -> Self {
+		Self {
+			effect_targets: [
+				Canvas::new(ctx, size, CanvasSettings::default()),
+				Canvas::new(ctx, size, CanvasSettings::default()),
+			],
+			previous_frame: Canvas::new(ctx, size, CanvasSettings::default()),
+		}
+	}
+}
+
+/// Returns how many pixels of a canvas of the given size cover one pixel
+/// of a 1080p output. Effects measure sizes in 1080p pixels so they look
+/// the same in scaled-down previews and supersampled renders.
+fn pixel_scale(canvas_size: UVec2) -> f32 {
+	canvas_size.y as f32 / REFERENCE_HEIGHT
+}
+
+fn shader_source(kind: PostProcessEffectKind) -> &'static str {
+	match kind {
+		PostProcessEffectKind::Bloom => include_str!("../shaders/post_process/bloom.glsl"),
+		PostProcessEffectKind::ChromaticAberration => {
+			include_str!("../shaders/post_process/chromatic_aberration.glsl")
+		}
+		PostProcessEffectKind::FilmGrain => {
+			include_str!("../shaders/post_process/film_grain.glsl")
+		}
+		PostProcessEffectKind::Vignette => include_str!("../shaders/post_process/vignette.glsl"),
+		PostProcessEffectKind::Crt => include_str!("../shaders/post_process/crt.glsl"),
+		PostProcessEffectKind::Feedback => include_str!("../shaders/post_process/feedback.glsl"),
+	}
+}
+
+/// Converts a parameter name like "Scanline count" to the name of its
+/// uniform, like `scanline_count`.
+fn uniform_name(param_name: &str) -> String {
+	param_name.to_lowercase().replace(' ', "_")
+}
+
+fn send_param_value(
+	ctx: &mut Context,
+	shader: &Shader,
+	name: &str,
+	value: &ParamValue,
+) -> anyhow::Result<()> {
+	match value {
+		ParamValue::Float(value) => shader.send_f32(ctx, name, *value)?,
+		ParamValue::Int(value) => shader.send_f32(ctx, name, *value as f32)?,
+		ParamValue::Bool(value) => shader.send_f32(ctx, name, if *value { 1.0 } else { 0.0 })?,
+		ParamValue::Color(color) => shader.send_vec4(ctx, name, Vec4::from_array(*color))?,
+		ParamValue::Vec2(vector) => shader.send_vec2(ctx, name, Vec2::from_array(*vector))?,
+		ParamValue::Choice(_) | ParamValue::Gradient(_) => {
+			bail!("post-process effects can't have choice or gradient parameters")
+		}
+	}
+	Ok(())
+}
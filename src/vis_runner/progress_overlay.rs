@@ -0,0 +1,195 @@
+use std::path::Path;
+
+use micro::{
+	color::LinSrgba,
+	graphics::{
+		mesh::Mesh,
+		text::{LayoutSettings, Text},
+	},
+	math::{Rect, Vec2},
+	ui::{Checkbox, ComboBox, DragValue, Grid, Slider, Window},
+	Context,
+};
+use rfd::FileDialog;
+
+use crate::{
+	conversions::{format_timestamp, frame_to_seconds},
+	ProgressScope,
+};
+
+use super::{cached_font::CachedFont, VisRunner};
+
+/// The space between the time and the bar, relative to the font size.
+const SPACING_FACTOR: f32 = 0.4;
+
+impl VisRunner {
+	/// Returns the number of frames played and the total number of frames
+	/// in the overlay's scope.
+	fn progress_overlay_frames(&self) -> (u64, u64) {
+		let current_frame = self.current_frame();
+		if self.progress_overlay_settings.scope == ProgressScope::Chapter {
+			if let Some(timing) = self
+				.chapters()
+				.and_then(|chapters| chapters.timing_at_frame(current_frame, self.num_frames))
+			{
+				return (timing.frame_in_chapter, timing.length);
+			}
+		}
+		(current_frame.min(self.num_frames), self.num_frames)
+	}
+
+	pub fn draw_progress_overlay(&mut self, ctx: &mut Context) -> anyhow::Result<()> {
+		let settings = &self.progress_overlay_settings;
+		if !settings.enabled || self.live() {
+			return Ok(());
+		}
+		let (elapsed_frames, total_frames) = self.progress_overlay_frames();
+		let progress = if total_frames == 0 {
+			0.0
+		} else {
+			elapsed_frames as f32 / total_frames as f32
+		};
+		let canvas_size = self.canvas.size().as_vec2();
+		let top_left = Vec2::from_array(settings.position) * canvas_size;
+		let font_size = (settings.font_size * canvas_size.y).round().max(1.0);
+		let bar_size = Vec2::from_array(settings.bar_size) * canvas_size;
+		let show_bar = settings.show_bar;
+		let [red, green, blue, alpha] = settings.bar_color;
+		let bar_color = LinSrgba::new(red, green, blue, alpha);
+		let [red, green, blue, alpha] = settings.bar_background_color;
+		let bar_background_color = LinSrgba::new(red, green, blue, alpha);
+		let [red, green, blue, alpha] = settings.text_color;
+		let text_color = LinSrgba::new(red, green, blue, alpha);
+
+		let text = match settings.font_path.clone().filter(|_| settings.show_time) {
+			Some(font_path) => {
+				let frame_rate = self.visualizer.frame_rate();
+				let total_seconds = frame_to_seconds(total_frames, frame_rate);
+				let include_hours = total_seconds >= 3600.0;
+				let time = format!(
+					"{} / {}",
+					format_timestamp(frame_to_seconds(elapsed_frames, frame_rate), include_hours),
+					format_timestamp(total_seconds, include_hours)
+				);
+				// the bar is still drawn if the font can't be loaded
+				CachedFont::get(&mut self.progress_overlay_font, ctx, &font_path, font_size)
+					.map(|font| Text::new(ctx, font, &time, LayoutSettings::default()))
+			}
+			None => None,
+		};
+
+		let ctx = &mut self.canvas.render_to(ctx);
+		let mut bar_top_left = top_left;
+		if let Some(text) = text {
+			text.translated_2d(top_left).color(text_color).draw(ctx);
+			let text_height = text.bounds().map_or(0.0, |bounds| bounds.size().y);
+			bar_top_left.y += text_height + font_size * SPACING_FACTOR;
+		}
+		if show_bar {
+			Mesh::rectangle(ctx, Rect::new(bar_top_left, bar_size))
+				.color(bar_background_color)
+				.draw(ctx);
+			Mesh::rectangle(
+				ctx,
+				Rect::new(bar_top_left, Vec2::new(bar_size.x * progress, bar_size.y)),
+			)
+			.color(bar_color)
+			.draw(ctx);
+		}
+		Ok(())
+	}
+
+	pub fn render_progress_overlay_window(&mut self, egui_ctx: &micro::ui::Context) {
+		let font_error = self
+			.progress_overlay_settings
+			.font_path
+			.as_deref()
+			.and_then(|path| CachedFont::load_error(&self.progress_overlay_font, path))
+			.map(str::to_string);
+		let settings = &mut self.progress_overlay_settings;
+		Window::new("Progress bar")
+			.open(&mut self.show_progress_overlay_window)
+			.show(egui_ctx, |ui| {
+				ui.add(Checkbox::new(&mut settings.enabled, "Show progress"));
+				Grid::new("progress_overlay_settings")
+					.num_columns(2)
+					.show(ui, |ui| {
+						ui.label("Progress through");
+						let mut scope_index = settings.scope as usize;
+						ComboBox::from_id_source("progress_scope").show_index(
+							ui,
+							&mut scope_index,
+							ProgressScope::NUM_SCOPES,
+							|index| ProgressScope::from(index).label(),
+						);
+						settings.scope = ProgressScope::from(scope_index);
+						ui.end_row();
+
+						ui.label("Show");
+						ui.horizontal(|ui| {
+							ui.add(Checkbox::new(&mut settings.show_bar, "Bar"));
+							ui.add(Checkbox::new(&mut settings.show_time, "Time"));
+						});
+						ui.end_row();
+
+						ui.label("Font");
+						ui.horizontal(|ui| {
+							let font_name = settings
+								.font_path
+								.as_deref()
+								.and_then(Path::file_name)
+								.map_or_else(
+									|| "None".to_string(),
+									|name| name.to_string_lossy().into_owned(),
+								);
+							ui.label(font_name);
+							if ui.button("Choose…").clicked() {
+								if let Some(path) = FileDialog::new()
+									.add_filter("font", &["ttf", "otf"])
+									.pick_file()
+								{
+									settings.font_path = Some(path);
+								}
+							}
+							if let Some(error) = &font_error {
+								let error_color = ui.visuals().error_fg_color;
+								ui.colored_label(error_color, error);
+							}
+						});
+						ui.end_row();
+
+						ui.label("Font size");
+						ui.add(Slider::new(&mut settings.font_size, 0.01..=0.1));
+						ui.end_row();
+
+						ui.label("Position");
+						ui.horizontal(|ui| {
+							let [x, y] = &mut settings.position;
+							ui.add(DragValue::new(x).range(0.0..=1.0).speed(0.01));
+							ui.add(DragValue::new(y).range(0.0..=1.0).speed(0.01));
+						});
+						ui.end_row();
+
+						ui.label("Bar size");
+						ui.horizontal(|ui| {
+							let [width, height] = &mut settings.bar_size;
+							ui.add(DragValue::new(width).range(0.0..=1.0).speed(0.01));
+							ui.add(DragValue::new(height).range(0.0..=0.1).speed(0.001));
+						});
+						ui.end_row();
+
+						ui.label("Bar color");
+						ui.color_edit_button_rgba_unmultiplied(&mut settings.bar_color);
+						ui.end_row();
+
+						ui.label("Bar background color");
+						ui.color_edit_button_rgba_unmultiplied(&mut settings.bar_background_color);
+						ui.end_row();
+
+						ui.label("Text color");
+						ui.color_edit_button_rgba_unmultiplied(&mut settings.text_color);
+						ui.end_row();
+					});
+			});
+	}
+}
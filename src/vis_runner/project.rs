@@ -0,0 +1,387 @@
+use std::{
+	collections::BTreeMap,
+	path::{Path, PathBuf},
+	sync::Arc,
+};
+
+use anyhow::anyhow;
+use kira::Volume;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	Automation, ChapterTransition, LyricsDisplaySettings, Markers, ParamValues, PostProcessChain,
+	ProgressOverlaySettings, Sections, TitleCardSettings,
+};
+
+use super::{
+	midi::MidiBindings, recording::SavedEvent, resolve_chapters, LiveResolution, Mode,
+	RenderingSettings, VisRunner,
+};
+
+/// Runner state that's saved between sessions of a visualizer.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Project {
+	volume_decibels: f64,
+	live_resolution: LiveResolution,
+	rendering_settings: RenderingSettings,
+	show_rendering_window: bool,
+	playhead_frame: u64,
+	last_output_directory: Option<PathBuf>,
+	params: ParamValues,
+	/// The audio file that `timeline` belongs to. `None` if the project
+	/// was saved before audio files could be opened, or in live mode.
+	audio_path: Option<PathBuf>,
+	#[serde(flatten)]
+	timeline: Timeline,
+	/// The timelines of other audio files that were opened, so they come
+	/// back when those files are opened again.
+	other_timelines: BTreeMap<PathBuf, Timeline>,
+	midi_bindings: MidiBindings,
+	/// `None` if the project was saved before post-processing existed,
+	/// in which case the visualizer's default chain is used.
+	post_process: Option<PostProcessChain>,
+	/// `None` if the project was saved before chapter transitions
+	/// existed, in which case the visualizer's default is used.
+	chapter_transition: Option<ChapterTransition>,
+	title_cards: Option<TitleCardSettings>,
+	sections: Option<Sections>,
+	/// The names of the chapters the sections belong to. Sections are
+	/// keyed by chapter index, so they're only loaded if the chapters
+	/// are the same.
+	section_chapter_names: Option<Vec<String>>,
+	lyrics_display: Option<LyricsDisplaySettings>,
+	progress_overlay: Option<ProgressOverlaySettings>,
+	/// The name of the selected output format.
+	output_format: Option<String>,
+	/// State saved by [`Visualizer::save_state`](crate::Visualizer::save_state).
+	visualizer_state: Option<serde_json::Value>,
+}
+
+/// The parts of a project that are tied to a particular audio file.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Timeline {
+	automation: Automation,
+	/// Input events recorded in live takes, with the frames they happened
+	/// on.
+	recorded_events: Vec<(u64, SavedEvent)>,
+	/// `None` if the project was saved before markers existed, in which
+	/// case the visualizer's default markers are used.
+	markers: Option<Markers>,
+	/// `None` if no lyrics have been loaded, in which case the
+	/// visualizer's default lyrics file is used.
+	lyrics_path: Option<PathBuf>,
+}
+
+impl Project {
+	/// Loads the project at the given path, or returns the default
+	/// project if it doesn't exist yet.
+	///
+	/// A project that can't be read is copied to a `.bak` file so it
+	/// isn't lost when the project is saved again.
+	fn load(path: &Path) -> anyhow::Result<Self> {
+		if !path.exists() {
+			return Ok(Self::default());
+		}
+		Self::read(path).map_err(|error| {
+			let mut backup_path = path.as_os_str().to_owned();
+			backup_path.push(".bak");
+			let error = anyhow!(
+				"Could not load project {} (backed up to {}): {}",
+				path.display(),
+				Path::new(&backup_path).display(),
+				error
+			);
+			std::fs::copy(path, backup_path).ok();
+			error
+		})
+	}
+
+	fn read(path: &Path) -> anyhow::Result<Self> {
+		Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+	}
+
+	fn save(&self, path: &Path) -> anyhow::Result<()> {
+		std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+		Ok(())
+	}
+}
+
+impl VisRunner {
+	/// Loads the saved project. Problems that don't stop the rest of the
+	/// project from loading are added to the project errors.
+	pub fn load_project(&mut self) -> anyhow::Result<()> {
+		let project = Project::load(&self.visualizer.project_path()).unwrap_or_else(|error| {
+			self.project_errors.push(error.to_string());
+			Project::default()
+		});
+		self.volume = Volume::Decibels(project.volume_decibels);
+		self.live_resolution = project.live_resolution;
+		self.rendering_settings = project.rendering_settings;
+		self.show_rendering_window = project.show_rendering_window;
+		self.rendering_settings
+			.clamp_chapter_range(resolve_chapters(self.visualizer.as_ref(), &self.playlist));
+		self.last_output_directory = project.last_output_directory;
+		self.params.apply_values(&project.params);
+		self.other_timelines = project.other_timelines;
+		let audio_path = self.audio_path();
+		let timeline = match project.audio_path {
+			Some(project_audio_path) if Some(&project_audio_path) != audio_path.as_ref() => {
+				// the visualizer's audio has changed since the project was
+				// saved
+				self.other_timelines
+					.insert(project_audio_path, project.timeline);
+				audio_path.and_then(|path| self.other_timelines.remove(&path))
+			}
+			_ => Some(project.timeline),
+		};
+		if let Some(timeline) = timeline {
+			self.apply_timeline(timeline);
+		}
+		self.midi_bindings = project.midi_bindings;
+		if let Some(mut post_process) = project.post_process {
+			for effect in &mut post_process.0 {
+				effect.fill_missing_values();
+			}
+			self.post_process = post_process;
+		}
+		if let Some(title_cards) = project.title_cards {
+			self.title_card_settings = title_cards;
+		}
+		if let Some(sections) = project.sections {
+			let chapter_names = self.chapter_names();
+			if project
+				.section_chapter_names
+				.map_or(true, |names| names == chapter_names)
+			{
+				self.sections = sections;
+			} else {
+				self.project_errors.push(
+					"Sections weren't loaded, since they were made for different chapters"
+						.to_string(),
+				);
+			}
+		}
+		if let Some(lyrics_display) = project.lyrics_display {
+			self.lyrics_display_settings = lyrics_display;
+		}
+		if let Some(progress_overlay) = project.progress_overlay {
+			self.progress_overlay_settings = progress_overlay;
+		}
+		if let Some(index) = project.output_format.and_then(|name| {
+			self.output_formats
+				.iter()
+				.position(|format| format.name == name)
+		}) {
+			self.output_format_index = index;
+		}
+		if let Some(chapter_transition) = project.chapter_transition {
+			if self.transition_renderer.supports(chapter_transition.kind) {
+				self.chapter_transition = chapter_transition;
+			}
+		}
+		self.seek(project.playhead_frame.min(self.num_frames))?;
+		if let Some(visualizer_state) = project.visualizer_state {
+			// the state may be from an older version of the visualizer
+			if let Err(error) = self.visualizer.load_state(visualizer_state) {
+				self.project_errors
+					.push(format!("Could not load visualizer state: {}", error));
+			}
+		}
+		Ok(())
+	}
+
+	/// Saves the project. If the visualizer's state can't be saved, the
+	/// rest of the project is still saved (with the state from the last
+	/// save) before the error is returned.
+	pub fn save_project(&self) -> anyhow::Result<()> {
+		let Volume::Decibels(volume_decibels) = self.volume else {
+			unreachable!()
+		};
+		let project_path = self.visualizer.project_path();
+		let (visualizer_state, visualizer_state_error) = match self.visualizer.save_state() {
+			Ok(state) => (state, None),
+			Err(error) => (
+				Project::read(&project_path)
+					.ok()
+					.and_then(|project| project.visualizer_state),
+				Some(error),
+			),
+		};
+		let playhead_frame = match &self.mode {
+			Mode::Rendering { .. } | Mode::Live { .. } => 0,
+			_ => self.current_frame(),
+		};
+		Project {
+			volume_decibels,
+			live_resolution: self.live_resolution,
+			rendering_settings: self.rendering_settings,
+			show_rendering_window: self.show_rendering_window,
+			playhead_frame,
+			last_output_directory: self.last_output_directory.clone(),
+			params: self.params.values(),
+			audio_path: self.audio_path(),
+			timeline: self.timeline(),
+			other_timelines: self.other_timelines.clone(),
+			midi_bindings: self.midi_bindings.clone(),
+			post_process: Some(self.post_process.clone()),
+			chapter_transition: Some(self.chapter_transition),
+			title_cards: Some(self.title_card_settings.clone()),
+			sections: Some(self.sections.clone()),
+			section_chapter_names: Some(self.chapter_names()),
+			lyrics_display: Some(self.lyrics_display_settings.clone()),
+			progress_overlay: Some(self.progress_overlay_settings.clone()),
+			output_format: Some(self.output_format().name.clone()),
+			visualizer_state,
+		}
+		.save(&project_path)?;
+		match visualizer_state_error {
+			Some(error) => Err(error.context("Could not save visualizer state")),
+			None => Ok(()),
+		}
+	}
+
+	pub fn save_project_and_report_errors(&mut self) {
+		if let Err(error) = self.save_project() {
+			self.project_errors
+				.push(format!("Could not save project: {:#}", error));
+		}
+	}
+
+	/// Puts away the timeline of the audio that was open before and
+	/// brings back the timeline of the current audio, or starts an empty
+	/// one if the current audio hasn't been opened before.
+	pub fn switch_timeline(&mut self, previous_audio_path: Option<PathBuf>) {
+		let previous_timeline = self.timeline();
+		if let Some(previous_audio_path) = previous_audio_path {
+			self.other_timelines
+				.insert(previous_audio_path, previous_timeline);
+		}
+		self.markers = Arc::default();
+		self.lyrics_path = None;
+		self.lyrics = None;
+		let timeline = self
+			.audio_path()
+			.and_then(|path| self.other_timelines.remove(&path))
+			.unwrap_or_default();
+		self.apply_timeline(timeline);
+	}
+
+	/// The audio file the timeline belongs to, or `None` in live mode.
+	fn audio_path(&self) -> Option<PathBuf> {
+		self.playlist.first_path().map(Path::to_path_buf)
+	}
+
+	fn timeline(&self) -> Timeline {
+		Timeline {
+			automation: self.automation.clone(),
+			recorded_events: self
+				.recorded_events
+				.iter()
+				.filter_map(|(frame, input)| Some((*frame, SavedEvent::from_input(input)?)))
+				.collect(),
+			markers: Some(Markers::clone(&self.markers)),
+			lyrics_path: self.lyrics_path.clone(),
+		}
+	}
+
+	/// Replaces the automation and recorded events with the timeline's.
+	/// The markers and lyrics are only replaced if the timeline has them.
+	fn apply_timeline(&mut self, timeline: Timeline) {
+		self.automation = timeline.automation;
+		self.automation
+			.sort(self.visualizer.tempo(), self.visualizer.frame_rate());
+		self.recorded_events = timeline
+			.recorded_events
+			.iter()
+			.filter_map(|(frame, event)| Some((*frame, event.to_input()?)))
+			.collect();
+		if let Some(mut markers) = timeline.markers {
+			// the project file may have been edited by hand
+			markers.sort();
+			self.markers = Arc::new(markers);
+		}
+		if let Some(lyrics_path) = timeline.lyrics_path {
+			// the lyrics file may have been moved since the project was saved
+			if let Err(error) = self.load_lyrics(lyrics_path) {
+				self.project_errors
+					.push(format!("Could not load lyrics: {}", error));
+			}
+		}
+	}
+}
+
+impl Drop for VisRunner {
+	fn drop(&mut self) {
+		self.stop_live_recording();
+		// the videos would be left unfinished if the app exited before
+		// their audio was added
+		// the window is already closed, so errors can only be printed.
+		// saving from the file menu shows them in the menu bar instead.
+		for recording in self.finishing_live_recordings.drain(..) {
+			if let Err(error) = recording.finish() {
+				eprintln!("{}", error);
+			}
+		}
+		if let Err(error) = self.save_project() {
+			eprintln!("could not save project: {:#}", error);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::Marker;
+
+	use super::*;
+
+	#[test]
+	fn projects_saved_before_timelines_existed_keep_their_timeline() {
+		let project: Project = serde_json::from_str(
+			r#"{
+				"volume_decibels": -6.0,
+				"markers": [{ "name": "Drop", "kind": "drop", "frame": 120 }],
+				"lyrics_path": "lyrics.lrc"
+			}"#,
+		)
+		.unwrap();
+		assert_eq!(project.audio_path, None);
+		assert_eq!(
+			project.timeline.markers,
+			Some(Markers(vec![Marker {
+				name: "Drop".to_string(),
+				kind: "drop".to_string(),
+				frame: 120,
+			}]))
+		);
+		assert_eq!(
+			project.timeline.lyrics_path,
+			Some(PathBuf::from("lyrics.lrc"))
+		);
+	}
+
+	#[test]
+	fn timelines_survive_saving() {
+		let project = Project {
+			audio_path: Some(PathBuf::from("song.flac")),
+			timeline: Timeline {
+				markers: Some(Markers::new()),
+				..Default::default()
+			},
+			other_timelines: [(
+				PathBuf::from("other song.flac"),
+				Timeline {
+					lyrics_path: Some(PathBuf::from("other song.lrc")),
+					..Default::default()
+				},
+			)]
+			.into_iter()
+			.collect(),
+			..Default::default()
+		};
+		let loaded: Project =
+			serde_json::from_str(&serde_json::to_string(&project).unwrap()).unwrap();
+		assert_eq!(loaded, project);
+	}
+}
@@ -0,0 +1,350 @@
+use std::collections::{BTreeMap, HashSet};
+
+use micro::{input::Scancode, Context, Event};
+use rosc::{OscMessage, OscType};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	automation::{Interpolation, Keyframe, TimelinePosition},
+	params::ParamValues,
+	MidiMessage, VisualizerEvent,
+};
+
+use super::{Mode, VisRunner};
+
+/// A live performance that's currently being recorded.
+pub struct Take {
+	start_frame: u64,
+	previous_param_values: ParamValues,
+	/// The parameters that have changed since the take started.
+	touched_params: HashSet<String>,
+	/// The keyframes of the touched parameters that were at or after the
+	/// frame each parameter was first touched. The ones after the end of
+	/// the take are put back when recording stops.
+	replaced_keyframes: BTreeMap<String, Vec<Keyframe>>,
+	events: Vec<(u64, RecordedInput)>,
+}
+
+/// Input from a take that's sent to the visualizer again when rendering.
+#[derive(Debug, Clone)]
+pub enum RecordedInput {
+	Event(VisualizerEvent),
+	Midi(MidiMessage),
+}
+
+impl VisRunner {
+	pub fn recording(&self) -> bool {
+		self.take.is_some()
+	}
+
+	pub fn start_recording(&mut self) -> anyhow::Result<()> {
+		// there's no timeline to record keyframes onto in live mode
+		if self.live() {
+			return Ok(());
+		}
+		self.take = Some(Take {
+			start_frame: self.current_frame(),
+			previous_param_values: self.params.values(),
+			touched_params: HashSet::new(),
+			replaced_keyframes: BTreeMap::new(),
+			events: vec![],
+		});
+		if !self.playing() {
+			self.play_or_resume()?;
+		}
+		Ok(())
+	}
+
+	/// Stops recording and keeps the input events from the take so they
+	/// can be replayed when rendering. Parameter changes are already
+	/// stored as automation keyframes while recording.
+	pub fn stop_recording(&mut self) {
+		let Some(take) = self.take.take() else {
+			return;
+		};
+		let end_frame = self.current_frame();
+		let tempo = self.visualizer.tempo();
+		let frame_rate = self.visualizer.frame_rate();
+		for (name, keyframes) in take.replaced_keyframes {
+			let track = self.automation.0.entry(name).or_default();
+			for keyframe in keyframes {
+				if keyframe.position.to_frame(tempo, frame_rate) > end_frame as f64 {
+					track.insert(keyframe, tempo, frame_rate);
+				}
+			}
+		}
+		self.recorded_events
+			.retain(|(frame, _)| *frame < take.start_frame || *frame > end_frame);
+		self.recorded_events.extend(take.events);
+		self.recorded_events.sort_by_key(|(frame, _)| *frame);
+	}
+
+	pub fn toggle_recording(&mut self) -> anyhow::Result<()> {
+		if self.live() {
+			return self.toggle_live_recording();
+		}
+		if self.recording() {
+			self.stop_recording();
+		} else {
+			self.start_recording()?;
+		}
+		Ok(())
+	}
+
+	/// Turns any parameter changes since the last update into step
+	/// keyframes at the current frame.
+	pub fn record_param_changes(&mut self) {
+		let current_frame = self.current_frame();
+		let Some(take) = &mut self.take else {
+			return;
+		};
+		let param_values = self.params.values();
+		for (name, value) in &param_values.0 {
+			let Some(previous_value) = take.previous_param_values.get(name) else {
+				continue;
+			};
+			if value == previous_value {
+				continue;
+			}
+			let tempo = self.visualizer.tempo();
+			let frame_rate = self.visualizer.frame_rate();
+			let track = self.automation.0.entry(name.clone()).or_default();
+			if take.touched_params.insert(name.clone()) {
+				// punching in replaces the keyframes from the frame the
+				// parameter was touched until recording stops. the value
+				// the automation had there is kept so the curve leading up
+				// to the touch doesn't change.
+				let value_before_touch = track
+					.value_at(current_frame as f64, tempo, frame_rate)
+					.unwrap_or_else(|| previous_value.clone());
+				let first_replaced_index = track.keyframes.partition_point(|keyframe| {
+					keyframe.position.to_frame(tempo, frame_rate) < current_frame as f64
+				});
+				take.replaced_keyframes.insert(
+					name.clone(),
+					track.keyframes.split_off(first_replaced_index),
+				);
+				track.insert(
+					Keyframe {
+						position: TimelinePosition::Frame(current_frame),
+						value: value_before_touch,
+						interpolation: Interpolation::Step,
+					},
+					tempo,
+					frame_rate,
+				);
+			}
+			track.insert(
+				Keyframe {
+					position: TimelinePosition::Frame(current_frame),
+					value: value.clone(),
+					interpolation: Interpolation::Step,
+				},
+				tempo,
+				frame_rate,
+			);
+		}
+		take.previous_param_values = param_values;
+	}
+
+	pub fn record_input(&mut self, input: RecordedInput) {
+		let current_frame = self.current_frame();
+		if let Some(take) = &mut self.take {
+			take.events.push((current_frame, input));
+		}
+	}
+
+	/// Sends the recorded input events for the current frame to the
+	/// visualizer.
+	pub fn replay_recorded_events(&mut self, ctx: &mut Context) -> anyhow::Result<()> {
+		let Mode::Rendering { current_frame, .. } = &self.mode else {
+			return Ok(());
+		};
+		let current_frame = *current_frame;
+		let start_index = self
+			.recorded_events
+			.partition_point(|(frame, _)| *frame < current_frame);
+		let end_index = self
+			.recorded_events
+			.partition_point(|(frame, _)| *frame <= current_frame);
+		for i in start_index..end_index {
+			match self.recorded_events[i].1.clone() {
+				RecordedInput::Event(event) => {
+					self.visualizer.event(ctx, self.vis_info(), event)?
+				}
+				RecordedInput::Midi(message) => {
+					self.visualizer.midi(ctx, self.vis_info(), message)?
+				}
+			}
+		}
+		Ok(())
+	}
+}
+
+/// A recorded input event in a form that can be saved in the project
+/// file.
+///
+/// Only key presses and releases, MIDI messages and OSC messages with
+/// number, string or bool arguments can be saved. Other input is only
+/// replayed in the session it was recorded in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SavedEvent {
+	KeyPressed {
+		scancode: i32,
+		repeat: bool,
+	},
+	KeyReleased {
+		scancode: i32,
+	},
+	Osc {
+		addr: String,
+		args: Vec<SavedOscArg>,
+	},
+	Midi(MidiMessage),
+}
+
+impl SavedEvent {
+	pub fn from_input(input: &RecordedInput) -> Option<Self> {
+		match input {
+			RecordedInput::Event(VisualizerEvent::Input(Event::KeyPressed { key, repeat })) => {
+				Some(Self::KeyPressed {
+					scancode: *key as i32,
+					repeat: *repeat,
+				})
+			}
+			RecordedInput::Event(VisualizerEvent::Input(Event::KeyReleased { key })) => {
+				Some(Self::KeyReleased {
+					scancode: *key as i32,
+				})
+			}
+			RecordedInput::Event(VisualizerEvent::Osc(message)) => Some(Self::Osc {
+				addr: message.addr.clone(),
+				args: message
+					.args
+					.iter()
+					.map(SavedOscArg::from_osc_type)
+					.collect::<Option<_>>()?,
+			}),
+			RecordedInput::Midi(message) => Some(Self::Midi(message.clone())),
+			_ => None,
+		}
+	}
+
+	/// Returns the input, or `None` if it refers to a key that doesn't
+	/// exist.
+	pub fn to_input(&self) -> Option<RecordedInput> {
+		let input = match self {
+			SavedEvent::KeyPressed { scancode, repeat } => {
+				RecordedInput::Event(VisualizerEvent::Input(Event::KeyPressed {
+					key: Scancode::from_i32(*scancode)?,
+					repeat: *repeat,
+				}))
+			}
+			SavedEvent::KeyReleased { scancode } => {
+				RecordedInput::Event(VisualizerEvent::Input(Event::KeyReleased {
+					key: Scancode::from_i32(*scancode)?,
+				}))
+			}
+			SavedEvent::Osc { addr, args } => {
+				RecordedInput::Event(VisualizerEvent::Osc(OscMessage {
+					addr: addr.clone(),
+					args: args.iter().cloned().map(OscType::from).collect(),
+				}))
+			}
+			SavedEvent::Midi(message) => RecordedInput::Midi(message.clone()),
+		};
+		Some(input)
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SavedOscArg {
+	Int(i32),
+	Long(i64),
+	Float(f32),
+	Double(f64),
+	String(String),
+	Bool(bool),
+}
+
+impl SavedOscArg {
+	fn from_osc_type(arg: &OscType) -> Option<Self> {
+		match arg {
+			OscType::Int(value) => Some(Self::Int(*value)),
+			OscType::Long(value) => Some(Self::Long(*value)),
+			OscType::Float(value) => Some(Self::Float(*value)),
+			OscType::Double(value) => Some(Self::Double(*value)),
+			OscType::String(value) => Some(Self::String(value.clone())),
+			OscType::Bool(value) => Some(Self::Bool(*value)),
+			_ => None,
+		}
+	}
+}
+
+impl From<SavedOscArg> for OscType {
+	fn from(arg: SavedOscArg) -> Self {
+		match arg {
+			SavedOscArg::Int(value) => OscType::Int(value),
+			SavedOscArg::Long(value) => OscType::Long(value),
+			SavedOscArg::Float(value) => OscType::Float(value),
+			SavedOscArg::Double(value) => OscType::Double(value),
+			SavedOscArg::String(value) => OscType::String(value),
+			SavedOscArg::Bool(value) => OscType::Bool(value),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn osc_messages_survive_saving() {
+		let message = OscMessage {
+			addr: "/hit".to_string(),
+			args: vec![
+				OscType::Int(1),
+				OscType::Float(0.5),
+				OscType::String("snare".to_string()),
+				OscType::Bool(true),
+			],
+		};
+		let saved =
+			SavedEvent::from_input(&RecordedInput::Event(VisualizerEvent::Osc(message.clone())))
+				.unwrap();
+		let saved =
+			serde_json::from_str::<SavedEvent>(&serde_json::to_string(&saved).unwrap()).unwrap();
+		let Some(RecordedInput::Event(VisualizerEvent::Osc(loaded))) = saved.to_input() else {
+			panic!("expected an OSC message");
+		};
+		assert_eq!(loaded, message);
+	}
+
+	#[test]
+	fn osc_messages_with_unsupported_args_are_not_saved() {
+		let message = OscMessage {
+			addr: "/blob".to_string(),
+			args: vec![OscType::Blob(vec![1, 2, 3])],
+		};
+		assert_eq!(
+			SavedEvent::from_input(&RecordedInput::Event(VisualizerEvent::Osc(message))),
+			None
+		);
+	}
+
+	#[test]
+	fn midi_messages_survive_saving() {
+		let message = MidiMessage::NoteOn {
+			channel: 9,
+			note: 36,
+			velocity: 100,
+		};
+		let saved = SavedEvent::from_input(&RecordedInput::Midi(message.clone())).unwrap();
+		let saved =
+			serde_json::from_str::<SavedEvent>(&serde_json::to_string(&saved).unwrap()).unwrap();
+		let Some(RecordedInput::Midi(loaded)) = saved.to_input() else {
+			panic!("expected a MIDI message");
+		};
+		assert_eq!(loaded, message);
+	}
+}
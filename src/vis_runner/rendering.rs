@@ -1,23 +1,70 @@
-use std::process::{Command, Stdio};
+use std::{
+	path::{Path, PathBuf},
+	process::{Command, Stdio},
+	sync::atomic::{AtomicU64, Ordering},
+};
 
-use kira::sound::streaming::StreamingSoundData;
-use micro::{graphics::SwapInterval, Context};
+use anyhow::bail;
+use micro::{graphics::SwapInterval, math::UVec2, Context};
 use rfd::FileDialog;
 
-use crate::conversions::frame_to_seconds;
+use crate::{conversions::frame_to_seconds, OutputFormat};
 
 use super::{Mode, VisRunner};
 
+/// Tracks the progress of rendering every output format one after
+/// another.
+pub struct BatchRender {
+	/// The path the user chose. Each format's video is named after it.
+	base_video_path: PathBuf,
+	remaining_format_indices: Vec<usize>,
+	/// The format that was selected before the batch started, which is
+	/// selected again afterward.
+	original_format_index: usize,
+}
+
 impl VisRunner {
 	pub fn render(&mut self, ctx: &mut Context) -> anyhow::Result<()> {
-		let Some(video_path) = FileDialog::new()
-			.set_directory(std::env::current_exe().unwrap())
-			.add_filter("mp4 video", &["mp4"])
-			.save_file()
-		else {
+		if self.live() {
+			return Ok(());
+		}
+		let Some(video_path) = self.pick_video_path() else {
 			return Ok(());
 		};
-		let (start_frame, end_frame) = if let Some(chapters) = self.visualizer.chapters() {
+		if self.rendering_settings.render_all_formats && self.output_formats.len() > 1 {
+			let batch = BatchRender {
+				base_video_path: video_path,
+				remaining_format_indices: (0..self.output_formats.len()).collect(),
+				original_format_index: self.output_format_index,
+			};
+			self.render_next_format(ctx, batch)
+		} else {
+			self.start_rendering(ctx, video_path, None)
+		}
+	}
+
+	fn start_rendering(
+		&mut self,
+		ctx: &mut Context,
+		video_path: PathBuf,
+		batch: Option<BatchRender>,
+	) -> anyhow::Result<()> {
+		let encoder_profile = self.rendering_settings.encoder_profile;
+		// not `self.output_format()`, which is the previous format when
+		// moving on to the next format in a batch
+		let output_format = self.output_formats[self.output_format_index].clone();
+		let resolution = output_format.resolution;
+		let supersampling = self.rendering_settings.supersampling;
+		if !supersampling.supports(resolution) {
+			bail!(
+				"the {} format ({}x{}) is too big to render with {} supersampling",
+				output_format.name,
+				resolution.x,
+				resolution.y,
+				supersampling.label()
+			);
+		}
+		let (start_frame, end_frame) = if let Some(chapters) = self.chapters() {
 			let start_frame = chapters[self.rendering_settings.start_chapter_index].start_frame;
 			let end_frame = chapters
 				.end_frame(self.rendering_settings.end_chapter_index)
@@ -26,8 +73,113 @@ impl VisRunner {
 		} else {
 			(0, self.num_frames)
 		};
-		let start_time = frame_to_seconds(start_frame, self.visualizer.frame_rate());
-		let ffmpeg_process = Command::new("ffmpeg")
+		let chapter_metadata_path = self.write_ffmpeg_chapter_metadata(start_frame, end_frame)?;
+		let subtitle_path = self.write_lyrics_subtitles(start_frame)?;
+		let mut command = self.ffmpeg_command(resolution);
+		self.add_ffmpeg_audio_args(
+			&mut command,
+			start_frame,
+			chapter_metadata_path.as_deref(),
+			subtitle_path.as_deref(),
+		);
+		let temp_files = chapter_metadata_path
+			.into_iter()
+			.chain(subtitle_path)
+			.collect::<Vec<_>>();
+		let ffmpeg_process = match command
+			.arg("-b:a")
+			.arg("320k")
+			.args(encoder_profile.ffmpeg_args())
+			.arg("-r")
+			.arg(self.visualizer.frame_rate().to_string())
+			.arg("-shortest")
+			.arg(video_path)
+			.spawn()
+		{
+			Ok(ffmpeg_process) => ffmpeg_process,
+			Err(error) => {
+				remove_temp_files(temp_files);
+				return Err(error.into());
+			}
+		};
+		let previous_mode = std::mem::replace(
+			&mut self.mode,
+			Mode::Rendering {
+				end_frame,
+				current_frame: start_frame,
+				canvas_read_buffer: canvas_read_buffer(resolution),
+				ffmpeg_process,
+				temp_files,
+				output_format,
+				supersampling,
+				batch,
+			},
+		);
+		finish_ffmpeg_in_background(previous_mode);
+		// make sure the first frame is drawn even if it's the same as the
+		// last frame of the previous format
+		self.drawn_state = None;
+		ctx.set_swap_interval(SwapInterval::Immediate)?;
+		Ok(())
+	}
+
+	/// Starts rendering the next format in a batch, naming the video
+	/// after the format.
+	fn render_next_format(
+		&mut self,
+		ctx: &mut Context,
+		mut batch: BatchRender,
+	) -> anyhow::Result<()> {
+		let format_index = batch.remaining_format_indices.remove(0);
+		self.output_format_index = format_index;
+		let video_path =
+			video_path_for_format(&batch.base_video_path, &self.output_formats[format_index]);
+		self.start_rendering(ctx, video_path, Some(batch))
+	}
+
+	/// Moves on to the next format if every format is being rendered, or
+	/// stops rendering otherwise.
+	pub fn finish_rendering(&mut self, ctx: &mut Context) -> anyhow::Result<()> {
+		let next_batch = match &mut self.mode {
+			Mode::Rendering { batch, .. }
+				if batch
+					.as_ref()
+					.is_some_and(|batch| !batch.remaining_format_indices.is_empty()) =>
+			{
+				batch.take()
+			}
+			_ => None,
+		};
+		match next_batch {
+			Some(batch) => self.render_next_format(ctx, batch),
+			None => self.stop_rendering(ctx),
+		}
+	}
+
+	/// Asks the user where to save a video with the current encoder
+	/// profile.
+	pub fn pick_video_path(&mut self) -> Option<PathBuf> {
+		let encoder_profile = self.rendering_settings.encoder_profile;
+		let directory = self
+			.last_output_directory
+			.clone()
+			.unwrap_or_else(|| std::env::current_exe().unwrap());
+		let video_path = FileDialog::new()
+			.set_directory(directory)
+			.add_filter(
+				format!("{} video", encoder_profile.file_extension()),
+				&[encoder_profile.file_extension()],
+			)
+			.save_file()?;
+		self.last_output_directory = video_path.parent().map(Path::to_path_buf);
+		Some(video_path)
+	}
+
+	/// Returns an ffmpeg command whose first input is raw RGBA frames of
+	/// the given size written to stdin.
+	pub fn ffmpeg_command(&self, resolution: UVec2) -> Command {
+		let mut command = Command::new("ffmpeg");
+		command
 			.stdin(Stdio::piped())
 			.arg("-y")
 			.arg("-f")
@@ -35,51 +187,174 @@ impl VisRunner {
 			.arg("-vcodec")
 			.arg("rawvideo")
 			.arg("-s")
-			.arg(&format!(
-				"{}x{}",
-				self.visualizer.video_resolution().x,
-				self.visualizer.video_resolution().y
-			))
+			.arg(&format!("{}x{}", resolution.x, resolution.y))
 			.arg("-pix_fmt")
 			.arg("rgba")
 			.arg("-r")
 			.arg(self.visualizer.frame_rate().to_string())
 			.arg("-i")
-			.arg("-")
-			.arg("-ss")
-			.arg(&format!("{}s", start_time))
-			.arg("-i")
-			.arg(self.visualizer.audio_path())
-			.arg("-b:a")
-			.arg("320k")
-			.arg("-c:v")
-			.arg("libx264")
-			.arg("-r")
-			.arg(self.visualizer.frame_rate().to_string())
-			.arg("-shortest")
-			.arg(video_path)
-			.spawn()?;
-		let canvas_read_buffer = vec![
-			0;
-			(self.visualizer.video_resolution().x * self.visualizer.video_resolution().y * 4)
-				as usize
-		];
-		self.mode = Mode::Rendering {
-			end_frame,
-			current_frame: start_frame,
-			canvas_read_buffer,
-			ffmpeg_process,
-		};
-		ctx.set_swap_interval(SwapInterval::Immediate)?;
-		Ok(())
+			.arg("-");
+		command
+	}
+
+	/// Adds the playlist's files as inputs, followed by the chapter
+	/// metadata and subtitle files if there are any.
+	fn add_ffmpeg_audio_args(
+		&self,
+		command: &mut Command,
+		start_frame: u64,
+		chapter_metadata_path: Option<&Path>,
+		subtitle_path: Option<&Path>,
+	) {
+		let single_track = self.playlist.num_tracks() == 1;
+		if single_track {
+			let start_time = frame_to_seconds(start_frame, self.visualizer.frame_rate());
+			command
+				.arg("-ss")
+				.arg(&format!("{}s", start_time))
+				.arg("-i")
+				.arg(self.playlist.first_path().unwrap());
+		} else {
+			for path in self.playlist.paths() {
+				command.arg("-i").arg(path);
+			}
+		}
+		let mut next_input_index = 1 + self.playlist.num_tracks();
+		let chapter_metadata_input_index = chapter_metadata_path.map(|path| {
+			command.arg("-i").arg(path);
+			next_input_index += 1;
+			next_input_index - 1
+		});
+		let subtitle_input_index = subtitle_path.map(|path| {
+			command.arg("-i").arg(path);
+			next_input_index += 1;
+			next_input_index - 1
+		});
+		if let Some(index) = chapter_metadata_input_index {
+			command
+				.arg("-map_metadata")
+				.arg(index.to_string())
+				.arg("-map_chapters")
+				.arg(index.to_string());
+		}
+		if !single_track {
+			command
+				.arg("-filter_complex")
+				.arg(self.playlist.ffmpeg_concat_filter(
+					1,
+					start_frame,
+					self.visualizer.frame_rate(),
+				));
+		}
+		// with a single track and no subtitles, ffmpeg picks the right
+		// streams by itself
+		if single_track && subtitle_input_index.is_none() {
+			return;
+		}
+		command
+			.arg("-map")
+			.arg("0:v")
+			.arg("-map")
+			.arg(if single_track { "1:a" } else { "[audio]" });
+		if let Some(index) = subtitle_input_index {
+			command
+				.arg("-map")
+				.arg(format!("{}:s", index))
+				.arg("-c:s")
+				.arg("mov_text");
+		}
 	}
 
 	pub fn stop_rendering(&mut self, ctx: &mut Context) -> Result<(), anyhow::Error> {
-		self.mode = Mode::Stopped {
-			data: Some(StreamingSoundData::from_file(self.visualizer.audio_path())?),
-			start_frame: 0,
-		};
+		if let Mode::Rendering {
+			batch: Some(batch), ..
+		} = &self.mode
+		{
+			self.output_format_index = batch.original_format_index;
+		}
+		let previous_mode = std::mem::replace(
+			&mut self.mode,
+			Mode::Stopped {
+				data: Some(self.playlist.sound_data()?),
+				start_frame: 0,
+			},
+		);
+		finish_ffmpeg_in_background(previous_mode);
 		ctx.set_swap_interval(SwapInterval::VSync)?;
 		Ok(())
 	}
 }
+
+/// Returns a path in the temporary directory that no other render (or
+/// other instance of the runner) uses, like `vis-1234-0-lyrics.srt`.
+pub fn temp_file_path(name: &str) -> PathBuf {
+	static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+	let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+	std::env::temp_dir().join(format!("vis-{}-{}-{}", std::process::id(), id, name))
+}
+
+/// If `mode` is a render, closes ffmpeg's input and waits for it to
+/// finish writing the video on another thread, then deletes the
+/// temporary files it was reading.
+fn finish_ffmpeg_in_background(mode: Mode) {
+	let Mode::Rendering {
+		mut ffmpeg_process,
+		temp_files,
+		..
+	} = mode
+	else {
+		return;
+	};
+	drop(ffmpeg_process.stdin.take());
+	std::thread::spawn(move || {
+		if let Err(error) = ffmpeg_process.wait() {
+			eprintln!("error waiting for ffmpeg: {}", error);
+		}
+		remove_temp_files(temp_files);
+	});
+}
+
+fn remove_temp_files(paths: Vec<PathBuf>) {
+	for path in paths {
+		if let Err(error) = std::fs::remove_file(&path) {
+			eprintln!("could not remove {}: {}", path.display(), error);
+		}
+	}
+}
+
+pub fn canvas_read_buffer(resolution: UVec2) -> Vec<u8> {
+	vec![0; (resolution.x * resolution.y * 4) as usize]
+}
+
+/// Returns a path like `video-landscape-16-9.mp4` for one of the videos
+/// in a batch.
+fn video_path_for_format(base_video_path: &Path, format: &OutputFormat) -> PathBuf {
+	let stem = base_video_path
+		.file_stem()
+		.map(|stem| stem.to_string_lossy().into_owned())
+		.unwrap_or_default();
+	let mut file_name = format!("{}-{}", stem, format.file_name_suffix());
+	if let Some(extension) = base_video_path.extension() {
+		file_name += ".";
+		file_name += &extension.to_string_lossy();
+	}
+	base_video_path.with_file_name(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn batch_videos_are_named_after_their_formats() {
+		let format = OutputFormat::new("Vertical (9:16)", UVec2::new(1080, 1920));
+		assert_eq!(
+			video_path_for_format(Path::new("renders/song.mp4"), &format),
+			Path::new("renders/song-vertical-9-16.mp4")
+		);
+		assert_eq!(
+			video_path_for_format(Path::new("song"), &format),
+			Path::new("song-vertical-9-16")
+		);
+	}
+}
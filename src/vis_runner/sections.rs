@@ -0,0 +1,362 @@
+use std::{path::Path, time::Duration};
+
+use micro::ui::{
+	pos2, vec2, Align2, Button, DragValue, FontId, Grid, Rect, Sense, Stroke, TextEdit,
+	TopBottomPanel, Window,
+};
+use rfd::FileDialog;
+
+use crate::{
+	conversions::{frame_to_seconds, seconds_to_frames},
+	Section, SectionTiming, Sections,
+};
+
+use super::{Mode, VisRunner};
+
+/// If the playhead is less than this far into a section, going to the
+/// previous section skips to the one before it.
+const BEGINNING_OF_SECTION_THRESHOLD: Duration = Duration::from_secs(2);
+const TIMELINE_HEIGHT: f32 = 20.0;
+const TIMELINE_FONT_SIZE: f32 = 12.0;
+
+/// Where a frame is within its chapter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChapterPosition {
+	pub chapter_index: usize,
+	pub chapter_start_frame: u64,
+	pub frame_in_chapter: u64,
+	pub chapter_length: u64,
+}
+
+impl VisRunner {
+	/// Returns where the frame is within its chapter. Audio without
+	/// chapters is treated as a single chapter. Returns `None` in live
+	/// mode, since there's no timeline.
+	pub fn chapter_position(&self, frame: u64) -> Option<ChapterPosition> {
+		if self.live() {
+			return None;
+		}
+		let Some(chapters) = self.chapters() else {
+			return Some(ChapterPosition {
+				chapter_index: 0,
+				chapter_start_frame: 0,
+				frame_in_chapter: frame,
+				chapter_length: self.num_frames.max(1),
+			});
+		};
+		let chapter_index = chapters.index_at_frame(frame)?;
+		let timing = chapters.timing(chapter_index, frame, self.num_frames);
+		Some(ChapterPosition {
+			chapter_index,
+			chapter_start_frame: chapters[chapter_index].start_frame,
+			frame_in_chapter: timing.frame_in_chapter,
+			chapter_length: timing.length,
+		})
+	}
+
+	pub fn section_at_frame(&self, frame: u64) -> Option<(Section, SectionTiming)> {
+		let position = self.chapter_position(frame)?;
+		let timing = self.sections.timing_at_frame(
+			position.chapter_index,
+			position.frame_in_chapter,
+			position.chapter_length,
+		)?;
+		let section = &self.sections.for_chapter(position.chapter_index)[timing.section_index];
+		Some((section.clone(), timing))
+	}
+
+	pub fn go_to_next_section(&mut self) -> anyhow::Result<()> {
+		let current_frame = self.current_frame();
+		if let Some(frame) = self
+			.section_start_frames()
+			.into_iter()
+			.find(|frame| *frame > current_frame)
+		{
+			self.seek(frame)?;
+		}
+		Ok(())
+	}
+
+	pub fn go_to_previous_section(&mut self) -> anyhow::Result<()> {
+		let threshold_frames = seconds_to_frames(
+			BEGINNING_OF_SECTION_THRESHOLD.as_secs_f64(),
+			self.visualizer.frame_rate(),
+		);
+		let latest_frame = self.current_frame().saturating_sub(threshold_frames);
+		if let Some(frame) = self
+			.section_start_frames()
+			.into_iter()
+			.rev()
+			.find(|frame| *frame < latest_frame)
+		{
+			self.seek(frame)?;
+		}
+		Ok(())
+	}
+
+	pub fn add_section_at_playhead(&mut self) {
+		let Some(position) = self.chapter_position(self.current_frame()) else {
+			return;
+		};
+		let name = format!(
+			"Section {}",
+			self.sections.for_chapter(position.chapter_index).len() + 1
+		);
+		self.sections.insert(
+			position.chapter_index,
+			Section::new(name, position.frame_in_chapter),
+		);
+	}
+
+	/// Shows the current chapter's sections as a strip above the main
+	/// menu. Clicking a section goes to its start.
+	pub fn render_section_timeline(&mut self, egui_ctx: &micro::ui::Context) -> anyhow::Result<()> {
+		if matches!(self.mode, Mode::Rendering { .. }) {
+			return Ok(());
+		}
+		let Some(position) = self.chapter_position(self.current_frame()) else {
+			return Ok(());
+		};
+		let sections = self.sections.for_chapter(position.chapter_index);
+		if sections.is_empty() {
+			return Ok(());
+		}
+		let current_section_index = self
+			.sections
+			.index_at_frame(position.chapter_index, position.frame_in_chapter);
+		let clicked_section_start_frame = TopBottomPanel::bottom("section_timeline")
+			.show(egui_ctx, |ui| {
+				let (rect, response) = ui.allocate_exact_size(
+					vec2(ui.available_width(), TIMELINE_HEIGHT),
+					Sense::click(),
+				);
+				let x_at_frame = |frame: u64| {
+					rect.left() + rect.width() * frame as f32 / position.chapter_length as f32
+				};
+				let painter = ui.painter();
+				let visuals = ui.visuals();
+				for (i, section) in sections.iter().enumerate() {
+					let end_frame = sections
+						.get(i + 1)
+						.map_or(position.chapter_length, |section| section.start_frame);
+					let section_rect = Rect::from_min_max(
+						pos2(x_at_frame(section.start_frame), rect.top()),
+						pos2(x_at_frame(end_frame), rect.bottom()),
+					);
+					let fill = if current_section_index == Some(i) {
+						visuals.selection.bg_fill
+					} else {
+						visuals.widgets.inactive.bg_fill
+					};
+					painter.rect_filled(section_rect, 0.0, fill);
+					painter.rect_stroke(
+						section_rect,
+						0.0,
+						visuals.widgets.noninteractive.bg_stroke,
+					);
+					painter.with_clip_rect(section_rect).text(
+						section_rect.left_center() + vec2(4.0, 0.0),
+						Align2::LEFT_CENTER,
+						&section.name,
+						FontId::proportional(TIMELINE_FONT_SIZE),
+						visuals.text_color(),
+					);
+				}
+				painter.vline(
+					x_at_frame(position.frame_in_chapter),
+					rect.y_range(),
+					Stroke::new(2.0, visuals.strong_text_color()),
+				);
+				let pointer_position = response
+					.interact_pointer_pos()
+					.filter(|_| response.clicked())?;
+				let clicked_frame = ((pointer_position.x - rect.left()) / rect.width()
+					* position.chapter_length as f32) as u64;
+				sections
+					.iter()
+					.rev()
+					.find(|section| section.start_frame <= clicked_frame)
+					.map(|section| section.start_frame)
+			})
+			.inner;
+		if let Some(frame) = clicked_section_start_frame {
+			self.seek(position.chapter_start_frame + frame)?;
+		}
+		Ok(())
+	}
+
+	pub fn render_sections_window(&mut self, egui_ctx: &micro::ui::Context) -> anyhow::Result<()> {
+		let frame_rate = self.visualizer.frame_rate();
+		let position = self.chapter_position(self.current_frame());
+		let chapter_label = match (self.chapters(), position) {
+			(Some(chapters), Some(position)) => chapters[position.chapter_index].label(),
+			_ => "Whole audio".to_string(),
+		};
+		let mut add_section = false;
+		let mut import = false;
+		let mut export = false;
+		let mut seek_destination = None;
+		let sections = &mut self.sections;
+		let import_error = &self.sections_import_error;
+		Window::new("Sections")
+			.open(&mut self.show_sections_window)
+			.show(egui_ctx, |ui| {
+				ui.label(chapter_label);
+				if let Some(position) = position {
+					let mut removed_section_index = None;
+					let mut start_frames_changed = false;
+					let mut edited_sections = vec![];
+					Grid::new("sections").num_columns(4).show(ui, |ui| {
+						for (i, section) in sections
+							.for_chapter(position.chapter_index)
+							.iter()
+							.enumerate()
+						{
+							let mut name = section.name.clone();
+							let mut start_frame = section.start_frame;
+							ui.add(TextEdit::singleline(&mut name).desired_width(120.0));
+							let mut seconds = frame_to_seconds(start_frame, frame_rate);
+							if ui
+								.add(DragValue::new(&mut seconds).speed(0.05).suffix(" s"))
+								.changed()
+							{
+								start_frame = seconds_to_frames(seconds.max(0.0), frame_rate);
+								start_frames_changed = true;
+							}
+							if name != section.name || start_frame != section.start_frame {
+								edited_sections.push((i, Section::new(name, start_frame)));
+							}
+							if ui.button("Go").clicked() {
+								seek_destination =
+									Some(position.chapter_start_frame + section.start_frame);
+							}
+							if ui.button("Remove").clicked() {
+								removed_section_index = Some(i);
+							}
+							ui.end_row();
+						}
+					});
+					for (index, section) in edited_sections {
+						sections.for_chapter_mut(position.chapter_index)[index] = section;
+					}
+					if let Some(index) = removed_section_index {
+						sections
+							.for_chapter_mut(position.chapter_index)
+							.remove(index);
+					}
+					if start_frames_changed || removed_section_index.is_some() {
+						sections.sort();
+					}
+					add_section = ui.button("Add section at playhead").clicked();
+				}
+				ui.horizontal(|ui| {
+					import = ui.button("Import…").clicked();
+					export = ui
+						.add_enabled(!sections.is_empty(), Button::new("Export…"))
+						.clicked();
+				});
+				if let Some(error) = import_error {
+					let error_color = ui.visuals().error_fg_color;
+					ui.colored_label(error_color, error);
+				}
+			});
+		if add_section {
+			self.add_section_at_playhead();
+		}
+		if let Some(frame) = seek_destination {
+			self.seek(frame)?;
+		}
+		if import {
+			self.import_sections();
+		}
+		if export {
+			self.export_sections()?;
+		}
+		Ok(())
+	}
+
+	fn import_sections(&mut self) {
+		let Some(path) = FileDialog::new()
+			.add_filter("sections", &["json"])
+			.pick_file()
+		else {
+			return;
+		};
+		match read_sections(&path) {
+			Ok(sections) => {
+				self.sections = sections;
+				self.sections_import_error = None;
+			}
+			Err(error) => {
+				self.sections_import_error =
+					Some(format!("Could not import {}: {}", path.display(), error));
+			}
+		}
+	}
+
+	fn export_sections(&mut self) -> anyhow::Result<()> {
+		let directory = self
+			.last_output_directory
+			.clone()
+			.unwrap_or_else(|| std::env::current_exe().unwrap());
+		let Some(path) = FileDialog::new()
+			.set_directory(directory)
+			.set_file_name("sections.json")
+			.add_filter("sections", &["json"])
+			.save_file()
+		else {
+			return Ok(());
+		};
+		std::fs::write(path, serde_json::to_string_pretty(&self.sections)?)?;
+		Ok(())
+	}
+
+	/// Returns the names of the current chapters, or an empty list if the
+	/// audio has no chapters. Used to tell whether sections still line up
+	/// with the chapters they were made for.
+	pub fn chapter_names(&self) -> Vec<String> {
+		self.chapters()
+			.map(|chapters| {
+				chapters
+					.0
+					.iter()
+					.map(|chapter| chapter.name.clone())
+					.collect()
+			})
+			.unwrap_or_default()
+	}
+
+	/// Returns the start frame of every section on the timeline, in order.
+	fn section_start_frames(&self) -> Vec<u64> {
+		let mut start_frames = match self.chapters() {
+			Some(chapters) => self
+				.sections
+				.0
+				.iter()
+				.filter_map(|(chapter_index, sections)| {
+					let chapter_start_frame = chapters.get(*chapter_index)?.start_frame;
+					Some(
+						sections
+							.iter()
+							.map(move |section| chapter_start_frame + section.start_frame),
+					)
+				})
+				.flatten()
+				.collect::<Vec<_>>(),
+			None => self
+				.sections
+				.for_chapter(0)
+				.iter()
+				.map(|section| section.start_frame)
+				.collect(),
+		};
+		start_frames.sort_unstable();
+		start_frames
+	}
+}
+
+fn read_sections(path: &Path) -> anyhow::Result<Sections> {
+	let mut sections: Sections = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+	sections.sort();
+	Ok(sections)
+}
@@ -0,0 +1,213 @@
+use std::{path::Path, time::Duration};
+
+use micro::{
+	color::LinSrgba,
+	graphics::{
+		mesh::Mesh,
+		text::{LayoutSettings, Text},
+	},
+	math::{Rect, Vec2},
+	ui::{Checkbox, ComboBox, DragValue, Grid, Slider, Window},
+	Context,
+};
+use rfd::FileDialog;
+
+use crate::{conversions::frame_to_seconds, TitleCardStyle};
+
+use super::{cached_font::CachedFont, VisRunner};
+
+/// How much smaller the artist and subtitle are than the chapter title.
+const DETAIL_FONT_SIZE_FACTOR: f32 = 0.6;
+/// The space between the text and the edge of a lower third's bar,
+/// relative to the font size.
+const PADDING_FACTOR: f32 = 0.5;
+
+impl VisRunner {
+	pub fn draw_title_card(&mut self, ctx: &mut Context) -> anyhow::Result<()> {
+		let settings = &self.title_card_settings;
+		if !settings.enabled {
+			return Ok(());
+		}
+		let Some(font_path) = settings.font_path.clone() else {
+			return Ok(());
+		};
+		let current_frame = self.current_frame();
+		let Some(chapter) = self
+			.chapters()
+			.and_then(|chapters| chapters.at_frame(current_frame))
+		else {
+			return Ok(());
+		};
+		let time_since_chapter_start = Duration::from_secs_f64(frame_to_seconds(
+			current_frame - chapter.start_frame,
+			self.visualizer.frame_rate(),
+		));
+		let opacity = settings.opacity(time_since_chapter_start);
+		if opacity <= 0.0 {
+			return Ok(());
+		}
+		let title = chapter
+			.title
+			.clone()
+			.unwrap_or_else(|| chapter.name.clone());
+		let mut lines = vec![(title, true)];
+		lines.extend(chapter.artist.clone().map(|artist| (artist, false)));
+		lines.extend(chapter.subtitle.clone().map(|subtitle| (subtitle, false)));
+
+		let canvas_size = self.canvas.size().as_vec2();
+		let font_size = (settings.font_size * canvas_size.y).round().max(1.0);
+		let padding = font_size * PADDING_FACTOR;
+		let style = settings.style;
+		let anchor = Vec2::from_array(settings.position) * canvas_size;
+		let [red, green, blue, alpha] = settings.text_color;
+		let text_color = LinSrgba::new(red, green, blue, alpha * opacity);
+		let [red, green, blue, alpha] = settings.background_color;
+		let background_color = LinSrgba::new(red, green, blue, alpha * opacity);
+
+		let title_font =
+			CachedFont::get(&mut self.title_card_title_font, ctx, &font_path, font_size);
+		let detail_font = CachedFont::get(
+			&mut self.title_card_detail_font,
+			ctx,
+			&font_path,
+			font_size * DETAIL_FONT_SIZE_FACTOR,
+		);
+		let (Some(title_font), Some(detail_font)) = (title_font, detail_font) else {
+			return Ok(());
+		};
+		let texts = lines
+			.iter()
+			.map(|(line, is_title)| {
+				let font = if *is_title { title_font } else { detail_font };
+				Text::new(ctx, font, line, LayoutSettings::default())
+			})
+			.collect::<Vec<_>>();
+		let text_sizes = texts
+			.iter()
+			.map(|text| text.bounds().map_or(Vec2::ZERO, |bounds| bounds.size()))
+			.collect::<Vec<_>>();
+		let block_size = Vec2::new(
+			text_sizes.iter().map(|size| size.x).fold(0.0, f32::max),
+			text_sizes.iter().map(|size| size.y).sum(),
+		);
+
+		let ctx = &mut self.canvas.render_to(ctx);
+		let block_top_left = match style {
+			TitleCardStyle::LowerThird => {
+				Mesh::rectangle(
+					ctx,
+					Rect::new(anchor, block_size + Vec2::splat(padding * 2.0)),
+				)
+				.color(background_color)
+				.draw(ctx);
+				anchor + Vec2::splat(padding)
+			}
+			TitleCardStyle::Centered => anchor - block_size / 2.0,
+		};
+		let mut y = block_top_left.y;
+		for (text, size) in texts.iter().zip(&text_sizes) {
+			let x = match style {
+				TitleCardStyle::LowerThird => block_top_left.x,
+				TitleCardStyle::Centered => anchor.x - size.x / 2.0,
+			};
+			text.translated_2d(Vec2::new(x, y))
+				.color(text_color)
+				.draw(ctx);
+			y += size.y;
+		}
+		Ok(())
+	}
+
+	pub fn render_title_cards_window(&mut self, egui_ctx: &micro::ui::Context) {
+		let font_error = self
+			.title_card_settings
+			.font_path
+			.as_deref()
+			.and_then(|path| CachedFont::load_error(&self.title_card_title_font, path))
+			.map(str::to_string);
+		let settings = &mut self.title_card_settings;
+		Window::new("Title cards")
+			.open(&mut self.show_title_cards_window)
+			.show(egui_ctx, |ui| {
+				ui.add(Checkbox::new(&mut settings.enabled, "Show title cards"));
+				Grid::new("title_card_settings")
+					.num_columns(2)
+					.show(ui, |ui| {
+						ui.label("Font");
+						ui.horizontal(|ui| {
+							let font_name = settings
+								.font_path
+								.as_deref()
+								.and_then(Path::file_name)
+								.map_or_else(
+									|| "None".to_string(),
+									|name| name.to_string_lossy().into_owned(),
+								);
+							ui.label(font_name);
+							if ui.button("Choose…").clicked() {
+								if let Some(path) = FileDialog::new()
+									.add_filter("font", &["ttf", "otf"])
+									.pick_file()
+								{
+									settings.font_path = Some(path);
+								}
+							}
+							if let Some(error) = &font_error {
+								let error_color = ui.visuals().error_fg_color;
+								ui.colored_label(error_color, error);
+							}
+						});
+						ui.end_row();
+
+						ui.label("Style");
+						let mut style_index = settings.style as usize;
+						ComboBox::from_id_source("title_card_style").show_index(
+							ui,
+							&mut style_index,
+							TitleCardStyle::NUM_STYLES,
+							|index| TitleCardStyle::from(index).label(),
+						);
+						settings.style = TitleCardStyle::from(style_index);
+						ui.end_row();
+
+						ui.label("Font size");
+						ui.add(Slider::new(&mut settings.font_size, 0.01..=0.2));
+						ui.end_row();
+
+						ui.label("Position");
+						ui.horizontal(|ui| {
+							let [x, y] = &mut settings.position;
+							ui.add(DragValue::new(x).range(0.0..=1.0).speed(0.01));
+							ui.add(DragValue::new(y).range(0.0..=1.0).speed(0.01));
+						});
+						ui.end_row();
+
+						ui.label("Text color");
+						ui.color_edit_button_rgba_unmultiplied(&mut settings.text_color);
+						ui.end_row();
+
+						ui.label("Background color");
+						ui.color_edit_button_rgba_unmultiplied(&mut settings.background_color);
+						ui.end_row();
+
+						for (label, duration) in [
+							("Delay", &mut settings.delay),
+							("Fade in", &mut settings.fade_in),
+							("Hold", &mut settings.hold),
+							("Fade out", &mut settings.fade_out),
+						] {
+							ui.label(label);
+							let mut seconds = duration.as_secs_f64();
+							ui.add(
+								DragValue::new(&mut seconds)
+									.range(0.0..=60.0)
+									.speed(0.05)
+									.suffix(" s"),
+							);
+							*duration = Duration::from_secs_f64(seconds);
+							ui.end_row();
+						}
+					});
+			});
+	}
+}
@@ -0,0 +1,159 @@
+use std::{fmt::Write as _, path::PathBuf};
+
+use rfd::FileDialog;
+
+use crate::{
+	conversions::{format_timestamp, frame_to_seconds},
+	Chapter, Chapters,
+};
+
+use super::{rendering::temp_file_path, VisRunner};
+
+impl VisRunner {
+	/// Asks the user where to save a text file listing each chapter's
+	/// start time and label, like a YouTube description tracklist.
+	pub fn export_tracklist(&mut self) -> anyhow::Result<()> {
+		let Some(chapters) = self.chapters() else {
+			return Ok(());
+		};
+		let frame_rate = self.visualizer.frame_rate();
+		let include_hours = frame_to_seconds(self.num_frames, frame_rate) >= 3600.0;
+		let mut tracklist = String::new();
+		for chapter in chapters.0.iter() {
+			writeln!(
+				tracklist,
+				"{} {}",
+				format_timestamp(
+					frame_to_seconds(chapter.start_frame, frame_rate),
+					include_hours
+				),
+				chapter.label()
+			)?;
+		}
+		let directory = self
+			.last_output_directory
+			.clone()
+			.unwrap_or_else(|| std::env::current_exe().unwrap());
+		let Some(path) = FileDialog::new()
+			.set_directory(directory)
+			.set_file_name("tracklist.txt")
+			.add_filter("text file", &["txt"])
+			.save_file()
+		else {
+			return Ok(());
+		};
+		std::fs::write(path, tracklist)?;
+		Ok(())
+	}
+
+	/// Writes the chapters between `start_frame` and `end_frame` to an
+	/// ffmpeg metadata file so they can be embedded in a rendered video.
+	/// Returns `None` if there are no chapters.
+	pub fn write_ffmpeg_chapter_metadata(
+		&self,
+		start_frame: u64,
+		end_frame: u64,
+	) -> anyhow::Result<Option<PathBuf>> {
+		let Some(chapters) = self.chapters() else {
+			return Ok(None);
+		};
+		let metadata = ffmpeg_chapter_metadata(
+			chapters,
+			start_frame,
+			end_frame,
+			self.visualizer.frame_rate(),
+		);
+		let path = temp_file_path("chapters.ffmetadata");
+		std::fs::write(&path, metadata)?;
+		Ok(Some(path))
+	}
+}
+
+fn ffmpeg_chapter_metadata(
+	chapters: &Chapters,
+	start_frame: u64,
+	end_frame: u64,
+	frame_rate: u64,
+) -> String {
+	let to_milliseconds = |frame: u64| {
+		(frame_to_seconds(frame.saturating_sub(start_frame), frame_rate) * 1000.0).round() as u64
+	};
+	let mut metadata = ";FFMETADATA1\n".to_string();
+	for (i, chapter) in chapters.0.iter().enumerate() {
+		let chapter_end_frame = chapters
+			.get(i + 1)
+			.map_or(end_frame, |next_chapter| next_chapter.start_frame)
+			.min(end_frame);
+		if chapter_end_frame <= start_frame || chapter.start_frame >= end_frame {
+			continue;
+		}
+		metadata += "\n[CHAPTER]\nTIMEBASE=1/1000\n";
+		metadata += &format!("START={}\n", to_milliseconds(chapter.start_frame));
+		metadata += &format!("END={}\n", to_milliseconds(chapter_end_frame));
+		for (key, value) in chapter_tags(chapter) {
+			metadata += &format!("{}={}\n", escape(&key), escape(&value));
+		}
+	}
+	metadata
+}
+
+fn chapter_tags(chapter: &Chapter) -> Vec<(String, String)> {
+	let mut tags = vec![("title".to_string(), chapter.label())];
+	if let Some(artist) = &chapter.artist {
+		tags.push(("artist".to_string(), artist.clone()));
+	}
+	if let Some(album) = &chapter.album {
+		tags.push(("album".to_string(), album.clone()));
+	}
+	tags.extend(
+		chapter
+			.metadata
+			.iter()
+			.map(|(key, value)| (key.clone(), value.clone())),
+	);
+	tags
+}
+
+/// Escapes the characters that have special meaning in ffmpeg metadata
+/// files.
+fn escape(text: &str) -> String {
+	let mut escaped = String::new();
+	for character in text.chars() {
+		if matches!(character, '=' | ';' | '#' | '\\' | '\n') {
+			escaped.push('\\');
+		}
+		escaped.push(character);
+	}
+	escaped
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn escapes_special_characters() {
+		assert_eq!(escape("a=b;c#d\\e\nf"), "a\\=b\\;c\\#d\\\\e\\\nf");
+		assert_eq!(escape("plain text"), "plain text");
+	}
+
+	#[test]
+	fn chapter_metadata_is_relative_to_the_rendered_range() {
+		let mut second_chapter = Chapter::new("Second", 120);
+		second_chapter.artist = Some("Artist".to_string());
+		second_chapter.title = Some("Song".to_string());
+		let chapters = Chapters(vec![
+			Chapter::new("First", 0),
+			second_chapter,
+			Chapter::new("Third", 240),
+		]);
+		let metadata = ffmpeg_chapter_metadata(&chapters, 60, 180, 60);
+		assert_eq!(
+			metadata,
+			";FFMETADATA1\n\
+			\n[CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=1000\ntitle=First\n\
+			\n[CHAPTER]\nTIMEBASE=1/1000\nSTART=1000\nEND=2000\n\
+			title=Artist - Song\nartist=Artist\n"
+		);
+	}
+}
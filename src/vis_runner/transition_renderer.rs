@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use micro::{
+	color::{ColorConstants, LinSrgba},
+	graphics::{shader::Shader, Canvas, CanvasSettings},
+	math::UVec2,
+	Context,
+};
+
+use crate::{shaders::load_shader, TransitionKind};
+
+/// Blends the previous and next chapters' images during a chapter
+/// transition.
+pub struct TransitionRenderer {
+	shaders: HashMap<TransitionKind, Shader>,
+	/// Created the first time a transition is drawn, so visualizers
+	/// without chapters don't pay for the extra canvases.
+	canvases: Option<TransitionCanvases>,
+}
+
+impl TransitionRenderer {
+	pub fn new(ctx: &mut Context, custom_shader_source: Option<&str>) -> anyhow::Result<Self> {
+		let mut shaders = HashMap::new();
+		shaders.insert(
+			TransitionKind::Crossfade,
+			load_shader(ctx, include_str!("../shaders/transitions/crossfade.glsl"))?,
+		);
+		shaders.insert(
+			TransitionKind::Wipe,
+			load_shader(ctx, include_str!("../shaders/transitions/wipe.glsl"))?,
+		);
+		shaders.insert(
+			TransitionKind::Zoom,
+			load_shader(ctx, include_str!("../shaders/transitions/zoom.glsl"))?,
+		);
+		if let Some(source) = custom_shader_source {
+			shaders.insert(TransitionKind::Custom, load_shader(ctx, source)?);
+		}
+		Ok(Self {
+			shaders,
+			canvases: None,
+		})
+	}
+
+	pub fn supports(&self, kind: TransitionKind) -> bool {
+		kind == TransitionKind::Cut || self.shaders.contains_key(&kind)
+	}
+
+	/// Returns the canvas the previous chapter should be drawn to.
+	pub fn from_canvas(&mut self, ctx: &mut Context, size: UVec2) -> &Canvas {
+		if let Some(canvases) = &self.canvases {
+			if canvases.from.size() != size {
+				self.canvases = None;
+			}
+		}
+		&self
+			.canvases
+			.get_or_insert_with(|| TransitionCanvases::new(ctx, size))
+			.from
+	}
+
+	/// Blends the previous chapter (drawn to [`TransitionRenderer::from_canvas`])
+	/// with the next chapter, which is drawn to `canvas`, and puts the
+	/// result in `canvas`.
+	pub fn apply(
+		&self,
+		ctx: &mut Context,
+		canvas: &Canvas,
+		kind: TransitionKind,
+		progress: f32,
+	) -> anyhow::Result<()> {
+		let Some(shader) = self.shaders.get(&kind) else {
+			return Ok(());
+		};
+		// nothing has been drawn to the previous chapter's canvas yet
+		let Some(canvases) = &self.canvases else {
+			return Ok(());
+		};
+		shader.send_vec2(ctx, "resolution", canvas.size().as_vec2())?;
+		shader.send_canvas(ctx, "to", canvas)?;
+		shader.send_f32(ctx, "progress", progress)?;
+		{
+			let ctx = &mut canvases.output.render_to(ctx);
+			ctx.clear(LinSrgba::BLACK);
+			let ctx = &mut ctx.push_shader(shader);
+			canvases.from.draw(ctx);
+		}
+		let ctx = &mut canvas.render_to(ctx);
+		ctx.clear(LinSrgba::BLACK);
+		canvases.output.draw(ctx);
+		Ok(())
+	}
+}
+
+struct TransitionCanvases {
+	/// What the previous chapter looks like on the current frame.
+	from: Canvas,
+	output: Canvas,
+}
+
+impl TransitionCanvases {
+	fn new(ctx: &mut Context, size: UVec2) -> Self {
+		Self {
+			from: Canvas::new(ctx, size, CanvasSettings::default()),
+			output: Canvas::new(ctx, size, CanvasSettings::default()),
+		}
+	}
+}
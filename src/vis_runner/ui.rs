@@ -1,12 +1,17 @@
 use kira::Volume;
 use micro::{
-	ui::{ComboBox, InnerResponse, Slider, TopBottomPanel, Ui},
+	ui::{
+		Button, Checkbox, ComboBox, DragValue, InnerResponse, SelectableLabel, Slider,
+		TopBottomPanel, Ui,
+	},
 	Context,
 };
 
 use crate::conversions::frame_to_seconds;
 
-use super::{LiveResolution, Mode, VisRunner};
+use super::{resolve_chapters, EncoderProfile, LiveResolution, Mode, Supersampling, VisRunner};
+
+const TOO_BIG_TO_SUPERSAMPLE: &str = "The video is too big to render with this much supersampling";
 
 impl VisRunner {
 	pub fn render_main_menu(
@@ -17,10 +22,54 @@ impl VisRunner {
 		TopBottomPanel::bottom("main_menu")
 			.show(egui_ctx, |ui| -> anyhow::Result<()> {
 				micro::ui::menu::bar(ui, |ui| -> anyhow::Result<()> {
-					self.render_play_pause_button(ui)?;
-					self.render_seekbar(ui)?;
-					self.render_chapter_combo_box(ui)?;
-					if !matches!(self.mode, Mode::Rendering { .. }) {
+					self.render_file_menu(ctx, ui)?;
+					if let Some(error) = &self.audio_error {
+						let error_color = ui.visuals().error_fg_color;
+						ui.colored_label(error_color, error);
+						if ui.button("Dismiss").clicked() {
+							self.audio_error = None;
+						}
+					}
+					if !self.project_errors.is_empty() {
+						let error_color = ui.visuals().error_fg_color;
+						for error in &self.project_errors {
+							ui.colored_label(error_color, error);
+						}
+						if ui.button("Dismiss").clicked() {
+							self.project_errors.clear();
+						}
+					}
+					if let Mode::Live { input, .. } = &self.mode {
+						ui.label("Live");
+						if let Some(error) = input.error() {
+							let error_color = ui.visuals().error_fg_color;
+							ui.colored_label(error_color, error);
+						}
+						if ui
+							.selectable_label(self.live_recording(), "Record")
+							.clicked()
+						{
+							self.toggle_live_recording()?;
+						}
+						if !self.finishing_live_recordings.is_empty() {
+							ui.label("Finishing recording…");
+						} else if let Some(result) = &self.live_recording_result {
+							match result {
+								Ok(path) => {
+									ui.label(format!("Saved {}", path.display()));
+								}
+								Err(error) => {
+									let error_color = ui.visuals().error_fg_color;
+									ui.colored_label(error_color, error);
+								}
+							}
+						}
+					} else {
+						self.render_play_pause_button(ui)?;
+						self.render_seekbar(ui)?;
+						self.render_chapter_combo_box(ui)?;
+					}
+					if !matches!(self.mode, Mode::Rendering { .. } | Mode::Live { .. }) {
 						if ui.button("<<").clicked() {
 							self.go_to_previous_chapter()?;
 						}
@@ -28,7 +77,8 @@ impl VisRunner {
 							self.go_to_next_chapter()?;
 						}
 					}
-					if !matches!(self.mode, Mode::Rendering { .. }) {
+					// the recording's frame size can't change partway through
+					if !matches!(self.mode, Mode::Rendering { .. }) && !self.live_recording() {
 						let mut selected_resolution_index = self.live_resolution as usize;
 						ComboBox::new("resolution", "").show_index(
 							ui,
@@ -37,15 +87,54 @@ impl VisRunner {
 							|index| LiveResolution::from(index).label(),
 						);
 						self.live_resolution = LiveResolution::from(selected_resolution_index);
+						self.render_output_format_combo_box(ui, "preview_output_format");
+					}
+					if !matches!(self.mode, Mode::Rendering { .. }) {
+						self.render_transition_menu(ui);
 					}
-					if ui.button("Render").clicked() {
+					if !self.live() && ui.button("Render").clicked() {
 						self.show_rendering_window = true;
 					}
-					let Volume::Decibels(decibels) = &mut self.volume else {
-						unreachable!()
-					};
-					ui.label("Volume");
-					ui.add(Slider::new(decibels, Volume::MIN_DECIBELS..=0.0));
+					if !self.params.is_empty() {
+						if ui.button("Parameters").clicked() {
+							self.show_params_window = true;
+						}
+						if ui.button("Automation").clicked() {
+							self.show_automation_window = true;
+						}
+					}
+					if ui.button("Effects").clicked() {
+						self.show_post_process_window = true;
+					}
+					if ui.button("Title cards").clicked() {
+						self.show_title_cards_window = true;
+					}
+					if !self.live() && ui.button("Sections").clicked() {
+						self.show_sections_window = true;
+					}
+					if !self.live() && ui.button("Markers").clicked() {
+						self.show_markers_window = true;
+					}
+					if !self.live() && ui.button("Lyrics").clicked() {
+						self.show_lyrics_window = true;
+					}
+					if !self.live() && ui.button("Progress bar").clicked() {
+						self.show_progress_overlay_window = true;
+					}
+					if ui.button("MIDI").clicked() {
+						self.show_midi_window = true;
+					}
+					if ui.button("OSC").clicked() {
+						self.show_osc_window = true;
+					}
+					if !self.live() {
+						let Volume::Decibels(decibels) = &mut self.volume else {
+							unreachable!()
+						};
+						ui.label("Volume");
+						ui.add(Slider::new(decibels, Volume::MIN_DECIBELS..=0.0));
+						self.render_av_offset_controls(ui)?;
+					}
 					self.visualizer.menu(ctx, ui, self.vis_info())?;
 					Ok(())
 				})
@@ -60,29 +149,22 @@ impl VisRunner {
 		ctx: &mut Context,
 		egui_ctx: &micro::ui::Context,
 	) -> anyhow::Result<()> {
+		// the window's contents borrow the whole runner, so the open flag
+		// is copied out
+		let mut show_rendering_window = self.show_rendering_window;
+		let rendering = matches!(self.mode, Mode::Rendering { .. });
 		let response = micro::ui::Window::new("Rendering")
-			.open(&mut self.show_rendering_window)
+			.open(&mut show_rendering_window)
 			.show(egui_ctx, |ui| {
-				let mut rendering_started = false;
-				if let Some(chapters) = self.visualizer.chapters() {
-					ComboBox::new("start_chapter_index", "Start Chapter Index").show_index(
-						ui,
-						&mut self.rendering_settings.start_chapter_index,
-						chapters.len(),
-						|i| &chapters[i].name,
-					);
-					ComboBox::new("end_chapter_index", "End Chapter Index").show_index(
-						ui,
-						&mut self.rendering_settings.end_chapter_index,
-						chapters.len(),
-						|i| &chapters[i].name,
-					);
+				if rendering {
+					ui.label("Rendering…");
 				}
-				if ui.button("Render").clicked() {
-					rendering_started = true;
-				}
-				rendering_started
+				// changing the settings partway through a render would
+				// change the size of the frames sent to ffmpeg
+				ui.add_enabled_ui(!rendering, |ui| self.render_rendering_settings(ui))
+					.inner
 			});
+		self.show_rendering_window = show_rendering_window;
 		if let Some(InnerResponse {
 			inner: Some(true), ..
 		}) = response
@@ -92,6 +174,87 @@ impl VisRunner {
 		Ok(())
 	}
 
+	/// Shows the rendering settings. Returns `true` if the user clicked
+	/// the render button.
+	fn render_rendering_settings(&mut self, ui: &mut Ui) -> bool {
+		if let Some(chapters) = resolve_chapters(self.visualizer.as_ref(), &self.playlist) {
+			ComboBox::new("start_chapter_index", "Start Chapter Index").show_index(
+				ui,
+				&mut self.rendering_settings.start_chapter_index,
+				chapters.len(),
+				|i| chapters[i].label(),
+			);
+			ComboBox::new("end_chapter_index", "End Chapter Index").show_index(
+				ui,
+				&mut self.rendering_settings.end_chapter_index,
+				chapters.len(),
+				|i| chapters[i].label(),
+			);
+		}
+		let mut selected_encoder_profile_index = self.rendering_settings.encoder_profile as usize;
+		ComboBox::new("encoder_profile", "Encoder").show_index(
+			ui,
+			&mut selected_encoder_profile_index,
+			EncoderProfile::NUM_PROFILES,
+			|index| EncoderProfile::from(index).label(),
+		);
+		self.rendering_settings.encoder_profile =
+			EncoderProfile::from(selected_encoder_profile_index);
+		let supported: [bool; Supersampling::NUM_FACTORS] = std::array::from_fn(|index| {
+			self.formats_to_render()
+				.all(|format| Supersampling::from(index).supports(format.resolution))
+		});
+		let supersampling = &mut self.rendering_settings.supersampling;
+		ComboBox::new("supersampling", "Supersampling")
+			.selected_text(supersampling.label())
+			.show_ui(ui, |ui| {
+				for (index, supported) in supported.into_iter().enumerate() {
+					let option = Supersampling::from(index);
+					let response = ui
+						.add_enabled(
+							supported,
+							SelectableLabel::new(*supersampling == option, option.label()),
+						)
+						.on_disabled_hover_text(TOO_BIG_TO_SUPERSAMPLE);
+					if response.clicked() {
+						*supersampling = option;
+					}
+				}
+			});
+		if self.output_formats.len() > 1 {
+			ui.add(Checkbox::new(
+				&mut self.rendering_settings.render_all_formats,
+				"Render all formats",
+			));
+			if !self.rendering_settings.render_all_formats {
+				self.render_output_format_combo_box(ui, "rendering_output_format");
+			}
+		}
+		if self.lyrics.is_some() {
+			ui.add(Checkbox::new(
+				&mut self.rendering_settings.embed_lyrics,
+				"Add lyrics as subtitles",
+			));
+		}
+		let can_render = supported[self.rendering_settings.supersampling as usize];
+		ui.add_enabled(can_render, Button::new("Render"))
+			.on_disabled_hover_text(TOO_BIG_TO_SUPERSAMPLE)
+			.clicked()
+	}
+
+	fn render_output_format_combo_box(&mut self, ui: &mut Ui, id: &str) {
+		if self.output_formats.len() < 2 {
+			return;
+		}
+		let output_formats = &self.output_formats;
+		ComboBox::new(id, "").show_index(
+			ui,
+			&mut self.output_format_index,
+			output_formats.len(),
+			|index| output_formats[index].name.clone(),
+		);
+	}
+
 	fn render_play_pause_button(&mut self, ui: &mut Ui) -> Result<(), anyhow::Error> {
 		if matches!(self.mode, Mode::Rendering { .. }) {
 			return Ok(());
@@ -100,12 +263,15 @@ impl VisRunner {
 		if ui.button(play_pause_button_text).clicked() {
 			self.toggle_playback()?;
 		};
+		if ui.selectable_label(self.recording(), "Record").clicked() {
+			self.toggle_recording()?;
+		}
 		Ok(())
 	}
 
 	fn render_seekbar(&mut self, ui: &mut Ui) -> Result<(), anyhow::Error> {
 		let mut frame = self.current_frame();
-		let (start_frame, end_frame) = if let Some(chapters) = self.visualizer.chapters() {
+		let (start_frame, end_frame) = if let Some(chapters) = self.chapters() {
 			let current_chapter_index = chapters
 				.index_at_frame(self.current_frame())
 				.expect("no current chapter");
@@ -136,8 +302,27 @@ impl VisRunner {
 		Ok(())
 	}
 
+	fn render_av_offset_controls(&mut self, ui: &mut Ui) -> anyhow::Result<()> {
+		if matches!(self.mode, Mode::Rendering { .. }) {
+			return Ok(());
+		}
+		ui.label("A/V Offset");
+		let response = ui.add(
+			DragValue::new(&mut self.machine_settings.av_offset_ms)
+				.speed(1.0)
+				.suffix(" ms"),
+		);
+		if response.drag_stopped() || response.lost_focus() {
+			self.machine_settings.save()?;
+		}
+		if ui.button("Calibrate").clicked() {
+			self.start_calibration()?;
+		}
+		Ok(())
+	}
+
 	fn render_chapter_combo_box(&mut self, ui: &mut Ui) -> anyhow::Result<()> {
-		let Some(chapters) = self.visualizer.chapters() else {
+		let Some(chapters) = self.chapters() else {
 			return Ok(());
 		};
 		let current_frame = self.current_frame();
@@ -145,12 +330,12 @@ impl VisRunner {
 			.index_at_frame(current_frame)
 			.expect("no current chapter");
 		if matches!(self.mode, Mode::Rendering { .. }) {
-			ui.label(&chapters[current_chapter_index].name);
+			ui.label(chapters[current_chapter_index].label());
 		} else {
 			let mut selected = current_chapter_index;
 			let response =
 				ComboBox::new("chapter", "")
-					.show_index(ui, &mut selected, chapters.len(), |i| &chapters[i].name);
+					.show_index(ui, &mut selected, chapters.len(), |i| chapters[i].label());
 			if response.changed() {
 				self.go_to_chapter(selected)?;
 			}